@@ -13,9 +13,9 @@ impl ManifestContext {
     pub fn load(file_path: impl AsRef<Path>) -> Result<Self, Box<dyn std::error::Error>> {
         let file_path = file_path.as_ref();
         let file = std::fs::read_to_string(file_path)?;
-        let spec = toml::from_str::<specification::ManifestSpec>(&file)?;
-        // let base_path = file_path.parent().unwrap();
-        // let spec = spec.normalize(&base_path);
+        let spec = specification::ManifestSpec::from_toml_str(&file)?;
+        let base_path = file_path.parent().unwrap_or(Path::new(""));
+        let spec = spec.normalize(base_path);
         Ok(Self {
             spec,
             file_path: file_path.to_path_buf(),
@@ -1,4 +1,5 @@
-// use std::path::{Path, PathBuf};
+use std::path::{Path, PathBuf};
+use std::collections::BTreeMap;
 use serde::{Deserialize, Serialize};
 
 // #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -33,31 +34,873 @@ pub struct ProjectSpec {
     pub seed_urls: Vec<String>,
     #[serde(default)]
     pub namespace: Option<String>,
-    // pub output_dir: PathBuf,
+    /// Holds one directory per project, named after its `namespace` or `id`.
+    /// Defaults to `.output` in the working directory.
+    #[serde(default)]
+    pub output_root: Option<PathBuf>,
+    /// Directory of this project; takes precedence over `output_root` and
+    /// `namespace`.
+    #[serde(default)]
+    pub output_directory: Option<PathBuf>,
+    /// Number of browser tabs crawling in parallel.
+    #[serde(default)]
+    pub max_concurrent_tabs: Option<usize>,
+    #[serde(default)]
+    pub politeness: Option<PolitenessSpec>,
+    #[serde(default)]
+    pub robots: Option<RobotsSpec>,
+    #[serde(default)]
+    pub sitemaps: Option<SitemapSpec>,
+    #[serde(default)]
+    pub budget: Option<BudgetSpec>,
+    #[serde(default)]
+    pub canonicalization: Option<CanonicalizationSpec>,
+    #[serde(default)]
+    pub recrawl: Option<RecrawlSpec>,
+    #[serde(default)]
+    pub retry: Option<RetrySpec>,
+    #[serde(default)]
+    pub resources: Option<ResourceSpec>,
+    #[serde(default)]
+    pub frontier: Option<FrontierSpec>,
+    #[serde(default)]
+    pub dedup: Option<DedupSpec>,
+    #[serde(default)]
+    pub capture: Option<CaptureSpec>,
+    #[serde(default)]
+    pub warc: Option<WarcSpec>,
+    /// Which hosts besides the seed hosts are in scope. Defaults to
+    /// `exact-host`.
+    #[serde(default)]
+    pub scope: Option<ScopeModeSpec>,
+    /// Ordered include/exclude rules; the first matching rule decides.
+    #[serde(default)]
+    pub url_rules: Vec<UrlRuleSpec>,
+    /// Hosts or origins in scope besides the seed hosts.
+    #[serde(default)]
+    pub allowed_domains: Vec<String>,
+    /// URL schemes never visited, in addition to `tel`.
+    #[serde(default)]
+    pub blocked_protocols: Vec<String>,
+    #[serde(default)]
+    pub browser: Option<BrowserSpec>,
+    #[serde(default)]
+    pub wait: Option<WaitSpec>,
+    #[serde(default)]
+    pub timeouts: Option<TimeoutSpec>,
 }
 
-// impl ProjectSpec {
-//     pub fn normalize(self, base_path: &Path) -> Self {
-//         Self {
-//             id: self.id,
-//             seed_urls: self.seed_urls,
-//             // output_dir: base_path.join(self.output_dir),
-//         }
-//     }
-// }
+/// Per-host request spacing. Unset fields fall back to the crawler defaults.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct PolitenessSpec {
+    /// Minimum delay between two requests to the same host.
+    #[serde(default)]
+    pub min_delay_ms: Option<u64>,
+    /// Maximum number of pages open against the same host at once.
+    #[serde(default)]
+    pub max_in_flight_per_host: Option<usize>,
+    /// Upper bound for the backoff applied after 429/503 responses.
+    #[serde(default)]
+    pub max_backoff_ms: Option<u64>,
+}
+
+/// robots.txt handling. Unset fields fall back to the crawler defaults.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct RobotsSpec {
+    /// Set to `false` to ignore robots.txt entirely.
+    #[serde(default)]
+    pub enabled: Option<bool>,
+    /// Product token matched against `User-agent` lines.
+    #[serde(default)]
+    pub user_agent: Option<String>,
+}
+
+/// Sitemap discovery. Unset fields fall back to the crawler defaults.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct SitemapSpec {
+    /// Set to `false` to seed from `seed_urls` only.
+    #[serde(default)]
+    pub enabled: Option<bool>,
+    /// Maximum number of sitemap files fetched, including nested indexes.
+    #[serde(default)]
+    pub max_sitemaps: Option<usize>,
+}
+
+/// Limits for a single crawl run. Every limit is off unless set.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct BudgetSpec {
+    /// Maximum number of links followed away from a seed.
+    #[serde(default)]
+    pub max_depth: Option<usize>,
+    #[serde(default)]
+    pub max_pages: Option<usize>,
+    #[serde(default)]
+    pub max_pages_per_host: Option<usize>,
+    /// Wall-clock limit for the run, in seconds.
+    #[serde(default)]
+    pub max_duration_secs: Option<u64>,
+}
+
+/// Incremental recrawl of pages that already have a snapshot.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct RecrawlSpec {
+    /// Revisit pages last checked more than this many seconds ago. Changed
+    /// pages get a new snapshot version; the previous one is kept.
+    #[serde(default)]
+    pub max_age_secs: Option<u64>,
+}
+
+/// Near-duplicate detection. Pages whose text matches an existing snapshot
+/// only get a log pointing at that snapshot.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct DedupSpec {
+    #[serde(default)]
+    pub enabled: Option<bool>,
+    /// Differing SimHash bits (out of 64) still counted as a duplicate.
+    /// Defaults to 3.
+    #[serde(default)]
+    pub max_distance: Option<u32>,
+    /// Set to `false` to not follow links found on duplicates.
+    #[serde(default)]
+    pub expand_links: Option<bool>,
+}
 
+/// Screenshots, PDF renders and HAR recordings stored next to each HTML
+/// snapshot.
+///
+/// ```toml
+/// [projects.capture]
+/// screenshot = true
+/// screenshot_format = "jpeg"
+/// jpeg_quality = 80
+/// pdf = true
+/// har = true
+/// har_bodies = "text"
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct CaptureSpec {
+    #[serde(default)]
+    pub screenshot: Option<bool>,
+    /// Defaults to `png`.
+    #[serde(default)]
+    pub screenshot_format: Option<ScreenshotFormatSpec>,
+    /// 0–100, for `jpeg` screenshots only.
+    #[serde(default)]
+    pub jpeg_quality: Option<u8>,
+    /// Set to `false` to capture the viewport only.
+    #[serde(default)]
+    pub full_page: Option<bool>,
+    /// Print-to-PDF render; needs a headless browser.
+    #[serde(default)]
+    pub pdf: Option<bool>,
+    #[serde(default)]
+    pub pdf_landscape: Option<bool>,
+    /// Every request the page made, as `page.har`.
+    #[serde(default)]
+    pub har: Option<bool>,
+    /// Response bodies kept in the HAR. Defaults to `none`.
+    #[serde(default)]
+    pub har_bodies: Option<HarBodiesSpec>,
+    /// Larger bodies are left out of the HAR. Defaults to 1 MiB.
+    #[serde(default)]
+    pub har_max_body_size_bytes: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum HarBodiesSpec {
+    None,
+    Text,
+    All,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ScreenshotFormatSpec {
+    Png,
+    Jpeg,
+}
+
+/// WARC 1.1 output, written to `warc/` in the project directory.
+///
+/// ```toml
+/// [projects.warc]
+/// output = "both"
+/// max_file_size_bytes = 1073741824
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct WarcSpec {
+    /// Defaults to `directory`, i.e. no WARC output.
+    #[serde(default)]
+    pub output: Option<SnapshotOutputSpec>,
+    /// Size at which a new WARC file is started. Defaults to 1 GiB.
+    #[serde(default)]
+    pub max_file_size_bytes: Option<u64>,
+    /// Set to `false` to write plain `.warc` files.
+    #[serde(default)]
+    pub compress: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SnapshotOutputSpec {
+    Directory,
+    Warc,
+    Both,
+}
+
+/// The order in which queued URLs are visited.
+///
+/// ```toml
+/// [projects.frontier]
+/// order = "best-first"
+/// depth_weight = -1.0
+/// path_keywords = { contact = 10.0, about = 5.0 }
+/// anchor_keywords = { "contact us" = 10.0 }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct FrontierSpec {
+    /// Defaults to `breadth-first`.
+    #[serde(default)]
+    pub order: Option<FrontierOrderSpec>,
+    /// Score added when the URL path contains the keyword (`best-first`).
+    #[serde(default)]
+    pub path_keywords: BTreeMap<String, f64>,
+    /// Score added when the link text contains the keyword (`best-first`).
+    #[serde(default)]
+    pub anchor_keywords: BTreeMap<String, f64>,
+    /// Score added per level of depth (`best-first`); negative values favor
+    /// shallow pages.
+    #[serde(default)]
+    pub depth_weight: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FrontierOrderSpec {
+    BreadthFirst,
+    DepthFirst,
+    BestFirst,
+}
+
+/// Downloading non-HTML documents (PDFs, images, CSV, …) instead of
+/// recording them as failures.
+///
+/// ```toml
+/// [projects.resources]
+/// enabled = true
+/// allowed_mime_types = ["application/pdf", "image/*"]
+/// max_size_bytes = 10_000_000
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct ResourceSpec {
+    #[serde(default)]
+    pub enabled: Option<bool>,
+    /// Exact types or `type/*`. Defaults to PDFs, images, CSV, plain text and
+    /// JSON.
+    #[serde(default)]
+    pub allowed_mime_types: Option<Vec<String>>,
+    /// Larger documents are skipped. Defaults to 50 MiB.
+    #[serde(default)]
+    pub max_size_bytes: Option<u64>,
+}
+
+/// Retrying failed URLs. Unset fields fall back to the crawler defaults.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct RetrySpec {
+    /// Attempts per URL, including the first. `1` disables retries.
+    #[serde(default)]
+    pub max_attempts: Option<usize>,
+    /// Delay before the first retry, doubled after every further attempt.
+    #[serde(default)]
+    pub initial_backoff_secs: Option<u64>,
+    #[serde(default)]
+    pub max_backoff_secs: Option<u64>,
+    /// Failures worth retrying. Defaults to all of them.
+    #[serde(default)]
+    pub retryable: Option<Vec<RetryableFailureSpec>>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RetryableFailureSpec {
+    /// HTTP 5xx.
+    ServerError,
+    /// HTTP 429.
+    TooManyRequests,
+    /// HTTP 408.
+    RequestTimeout,
+    NoResponse,
+    Timeout,
+    /// Browser errors such as crashed tabs.
+    Browser,
+}
+
+/// The browser the crawler launches. Unset fields fall back to the
+/// chromiumoxide defaults.
+///
+/// ```toml
+/// [projects.browser]
+/// headless = "new"
+/// window_width = 1280
+/// window_height = 1024
+/// args = ["--lang=de"]
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct BrowserSpec {
+    /// Defaults to `old`.
+    #[serde(default)]
+    pub headless: Option<HeadlessSpec>,
+    /// Chrome/Chromium binary. Defaults to the one found on the system.
+    #[serde(default)]
+    pub executable: Option<PathBuf>,
+    /// Also sets the viewport. Defaults to an 800×600 viewport.
+    #[serde(default)]
+    pub window_width: Option<u32>,
+    #[serde(default)]
+    pub window_height: Option<u32>,
+    /// Profile directory. Defaults to a fresh temporary profile.
+    #[serde(default)]
+    pub user_data_dir: Option<PathBuf>,
+    /// Needed in most containers.
+    #[serde(default)]
+    pub no_sandbox: Option<bool>,
+    /// Limit for a single DevTools command.
+    #[serde(default)]
+    pub request_timeout_ms: Option<u64>,
+    /// Extra command line switches.
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum HeadlessSpec {
+    /// A visible browser window.
+    Off,
+    /// The old headless mode.
+    Old,
+    /// The new headless mode.
+    New,
+}
+
+/// What to wait for after a page loaded, before it is snapshotted.
+///
+/// ```toml
+/// [projects.wait]
+/// strategy = "network-idle"
+/// timeout_ms = 10000
+/// settle_delay_ms = 0
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct WaitSpec {
+    /// Defaults to `fully-settled`.
+    #[serde(default)]
+    pub strategy: Option<WaitStrategySpec>,
+    /// Defaults to 5 s.
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+    /// How long no resource may finish for the network to count as idle.
+    /// Defaults to 500 ms.
+    #[serde(default)]
+    pub quiet_window_ms: Option<u64>,
+    /// Extra delay once the page settled. Defaults to 1 s.
+    #[serde(default)]
+    pub settle_delay_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum WaitStrategySpec {
+    /// Only the navigation itself.
+    Load,
+    /// `document.readyState` is `complete`.
+    DomReady,
+    /// DOM ready and no resource finished within the quiet window.
+    NetworkIdle,
+    /// DOM ready, no fetch/XHR in flight and network idle.
+    FullySettled,
+}
+
+/// Navigation limits. Running into one is not a failure; the page is taken
+/// as far as it loaded.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct TimeoutSpec {
+    /// For the navigation request. Defaults to 10 s.
+    #[serde(default)]
+    pub navigation_ms: Option<u64>,
+    /// For the page to become interactive afterwards. Defaults to 10 s.
+    #[serde(default)]
+    pub ready_ms: Option<u64>,
+    /// For the main document response. Defaults to 5 s.
+    #[serde(default)]
+    pub document_response_ms: Option<u64>,
+}
+
+/// How URLs are rewritten into their canonical form before deduplication,
+/// filtering and snapshot path building.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct CanonicalizationSpec {
+    /// Set to `false` to start from fragment stripping only, instead of the
+    /// built-in rules (lowercase host, drop default port, drop tracking
+    /// parameters, drop an empty query).
+    #[serde(default)]
+    pub standard_rules: Option<bool>,
+    /// Extra rules applied after the built-in ones, e.g. `strip-www`,
+    /// `strip-trailing-slash` or `strip-index-file`.
+    #[serde(default)]
+    pub rules: Vec<NormalizationRuleSpec>,
+    /// Query parameter names to drop; `*` matches any run of characters.
+    #[serde(default)]
+    pub drop_query_params: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum NormalizationRuleSpec {
+    StripFragment,
+    LowercaseHost,
+    RemoveDefaultPort,
+    StripWww,
+    StripTrailingSlash,
+    /// Drops `index.html` and similar file names from the path.
+    StripIndexFile,
+    /// Drops `utm_*`, `fbclid` and other well-known tracking parameters.
+    DropTrackingParams,
+    /// Sorts query parameters by name. Only for sites that ignore their
+    /// order.
+    SortQuery,
+    RemoveEmptyQuery,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ScopeModeSpec {
+    /// Only the seed hosts.
+    ExactHost,
+    /// Seed hosts and their subdomains.
+    IncludeSubdomains,
+    /// Every host under the same registrable domain as a seed host.
+    RegistrableDomain,
+}
+
+/// A single include/exclude rule. Exactly one of `glob` and `regex` must be
+/// set.
+///
+/// ```toml
+/// [[projects.url_rules]]
+/// action = "exclude"
+/// glob = "/cart/**"
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UrlRuleSpec {
+    pub action: UrlRuleActionSpec,
+    /// Defaults to `path`.
+    #[serde(default)]
+    pub target: Option<UrlRuleTargetSpec>,
+    #[serde(default)]
+    pub glob: Option<String>,
+    #[serde(default)]
+    pub regex: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum UrlRuleActionSpec {
+    Include,
+    Exclude,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum UrlRuleTargetSpec {
+    Path,
+    Query,
+    PathAndQuery,
+}
+
+impl ProjectSpec {
+    /// Resolve relative paths against `base_path`, the directory of the
+    /// manifest.
+    pub fn normalize(self, base_path: &Path) -> Self {
+        let resolve = |path: PathBuf| base_path.join(path);
+        Self {
+            output_root: self.output_root.map(resolve),
+            output_directory: self.output_directory.map(resolve),
+            browser: self.browser.map(|browser| BrowserSpec {
+                executable: browser.executable.map(resolve),
+                user_data_dir: browser.user_data_dir.map(resolve),
+                ..browser
+            }),
+            ..self
+        }
+    }
+}
+
+/// ```toml
+/// # Applies to every project, field by field; a project's own value wins.
+/// [defaults]
+/// output_root = "crawls"
+/// max_concurrent_tabs = 8
+/// budget = { max_pages = 1000 }
+///
+/// [[projects]]
+/// id = "example"
+/// seed_urls = ["https://example.com/"]
+/// ```
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ManifestSpec {
+    /// Any `ProjectSpec` field except `id`. Tables are merged key by key,
+    /// everything else, including arrays, is only used where a project
+    /// leaves it out.
+    #[serde(default)]
+    pub defaults: toml::Table,
     pub projects: Vec<ProjectSpec>,
 }
 
-// impl ManifestSpec {
-//     pub fn normalize(self, base_path: &Path) -> Self {
-//         Self {
-//             projects: self.projects
-//                 .into_iter()
-//                 .map(|x| x.normalize(base_path))
-//                 .collect()
-//         }
-//     }
-// }
+impl ManifestSpec {
+    /// Parse a manifest, apply its `[defaults]` to every project and
+    /// [validate](ProjectSpec::validate) the result.
+    ///
+    /// Projects are read one at a time, so that an invalid value is reported
+    /// with the project it belongs to.
+    pub fn from_toml_str(source: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let document = toml::from_str::<toml::Table>(source)?;
+        let defaults = match document.get("defaults") {
+            Some(toml::Value::Table(defaults)) => defaults.clone(),
+            Some(_) => return Err("`defaults` must be a table".into()),
+            None => toml::Table::new(),
+        };
+        let projects = match document.get("projects") {
+            Some(toml::Value::Array(projects)) => projects.clone(),
+            Some(_) => return Err("`projects` must be an array of tables".into()),
+            None => return Err("missing `projects`".into()),
+        };
+        let projects = projects
+            .into_iter()
+            .enumerate()
+            .map(|(index, project)| {
+                let mut project = match project {
+                    toml::Value::Table(project) => project,
+                    _ => return Err(format!("`projects[{index}]` must be a table").into()),
+                };
+                merge_defaults(&mut project, &defaults);
+                let id = match project.get("id") {
+                    Some(toml::Value::String(id)) => id.clone(),
+                    _ => format!("#{}", index + 1),
+                };
+                let project = toml::Value::Table(project)
+                    .try_into::<ProjectSpec>()
+                    .map_err(|error| ManifestError {
+                        project: id,
+                        field: None,
+                        message: error.to_string().trim_end().to_string(),
+                    })?;
+                project.validate()?;
+                Ok(project)
+            })
+            .collect::<Result<Vec<_>, Box<dyn std::error::Error>>>()?;
+        Ok(Self { defaults, projects })
+    }
+    pub fn normalize(self, base_path: &Path) -> Self {
+        Self {
+            defaults: self.defaults,
+            projects: self.projects
+                .into_iter()
+                .map(|x| x.normalize(base_path))
+                .collect()
+        }
+    }
+}
+
+fn merge_defaults(table: &mut toml::Table, defaults: &toml::Table) {
+    for (key, default) in defaults.iter() {
+        if key == "id" {
+            continue
+        }
+        match (table.get_mut(key), default) {
+            (None, default) => {
+                table.insert(key.clone(), default.clone());
+            }
+            (Some(toml::Value::Table(table)), toml::Value::Table(default)) => {
+                merge_defaults(table, default);
+            }
+            (Some(_), _) => (),
+        }
+    }
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// VALIDATION
+// ————————————————————————————————————————————————————————————————————————————
+
+/// Longest accepted timeout or delay, one day. Much larger values overflow
+/// the deadlines computed from them.
+pub const MAX_DURATION_MS: u64 = 24 * 60 * 60 * 1000;
+
+/// A manifest value the crawler cannot run with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestError {
+    /// The project `id`, or `#<n>` for a project without one.
+    pub project: String,
+    /// Dotted path below the project, e.g. `browser.window_width`. Missing
+    /// for type errors, whose message already names the field.
+    pub field: Option<String>,
+    pub message: String,
+}
+
+impl std::fmt::Display for ManifestError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.field {
+            Some(field) => write!(f, "project {:?}, `{field}`: {}", self.project, self.message),
+            None => write!(f, "project {:?}: {}", self.project, self.message),
+        }
+    }
+}
+
+impl std::error::Error for ManifestError {}
+
+impl ProjectSpec {
+    /// Check the values that deserialize fine but that the crawler cannot
+    /// run with: seed URLs, URL rule patterns, the window size and
+    /// timeouts.
+    pub fn validate(&self) -> Result<(), ManifestError> {
+        let error = |field: &str, message: String| ManifestError {
+            project: self.id.clone(),
+            field: Some(field.to_string()),
+            message,
+        };
+        // - SEED URLS -
+        for (index, seed_url) in self.seed_urls.iter().enumerate() {
+            let field = format!("seed_urls[{index}]");
+            match url::Url::parse(seed_url) {
+                Ok(url) if url.has_host() => (),
+                Ok(_) => return Err(error(&field, format!("{seed_url:?} has no host"))),
+                Err(parse_error) => return Err(error(&field, format!("invalid URL {seed_url:?}: {parse_error}"))),
+            }
+        }
+        // - URL RULES -
+        for (index, rule) in self.url_rules.iter().enumerate() {
+            match (rule.glob.as_deref(), rule.regex.as_deref()) {
+                (Some(glob), None) => {
+                    if let Err(glob_error) = wax::Glob::new(glob) {
+                        let field = format!("url_rules[{index}].glob");
+                        return Err(error(&field, format!("invalid glob {glob:?}: {glob_error}")))
+                    }
+                }
+                (None, Some(regex)) => {
+                    if let Err(regex_error) = regex::Regex::new(regex) {
+                        let field = format!("url_rules[{index}].regex");
+                        return Err(error(&field, format!("invalid regex {regex:?}: {regex_error}")))
+                    }
+                }
+                _ => {
+                    let field = format!("url_rules[{index}]");
+                    return Err(error(&field, "needs exactly one of `glob` or `regex`".to_string()))
+                }
+            }
+        }
+        // - BROWSER -
+        if let Some(browser) = self.browser.as_ref() {
+            match (browser.window_width, browser.window_height) {
+                (Some(_), None) => {
+                    return Err(error("browser.window_height", "must be set along with `window_width`".to_string()))
+                }
+                (None, Some(_)) => {
+                    return Err(error("browser.window_width", "must be set along with `window_height`".to_string()))
+                }
+                (Some(0), Some(_)) => {
+                    return Err(error("browser.window_width", "must be greater than 0".to_string()))
+                }
+                (Some(_), Some(0)) => {
+                    return Err(error("browser.window_height", "must be greater than 0".to_string()))
+                }
+                _ => (),
+            }
+            check_timeout(browser.request_timeout_ms)
+                .map_err(|message| error("browser.request_timeout_ms", message))?;
+        }
+        // - TIMEOUTS -
+        if let Some(timeouts) = self.timeouts.as_ref() {
+            check_timeout(timeouts.navigation_ms)
+                .map_err(|message| error("timeouts.navigation_ms", message))?;
+            check_timeout(timeouts.ready_ms)
+                .map_err(|message| error("timeouts.ready_ms", message))?;
+            check_timeout(timeouts.document_response_ms)
+                .map_err(|message| error("timeouts.document_response_ms", message))?;
+        }
+        if let Some(wait) = self.wait.as_ref() {
+            check_delay(wait.timeout_ms)
+                .map_err(|message| error("wait.timeout_ms", message))?;
+            check_delay(wait.quiet_window_ms)
+                .map_err(|message| error("wait.quiet_window_ms", message))?;
+            check_delay(wait.settle_delay_ms)
+                .map_err(|message| error("wait.settle_delay_ms", message))?;
+        }
+        Ok(())
+    }
+}
+
+/// A limit that nothing could finish within when `0`.
+fn check_timeout(value_ms: Option<u64>) -> Result<(), String> {
+    match value_ms {
+        Some(0) => Err("must be greater than 0".to_string()),
+        value_ms => check_delay(value_ms),
+    }
+}
+
+fn check_delay(value_ms: Option<u64>) -> Result<(), String> {
+    match value_ms {
+        Some(value_ms) if value_ms > MAX_DURATION_MS => {
+            Err(format!("{value_ms} ms is more than the maximum of {MAX_DURATION_MS} ms (one day)"))
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn project_error(source: &str) -> String {
+        ManifestSpec::from_toml_str(source).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn applies_defaults_field_by_field() {
+        let manifest = ManifestSpec::from_toml_str(r#"
+            [defaults]
+            max_concurrent_tabs = 8
+            budget = { max_pages = 1000, max_depth = 3 }
+
+            [[projects]]
+            id = "a"
+            seed_urls = ["https://a.com/"]
+            budget = { max_depth = 5 }
+
+            [[projects]]
+            id = "b"
+            seed_urls = ["https://b.com/"]
+            max_concurrent_tabs = 2
+        "#).unwrap();
+        let [a, b] = manifest.projects.as_slice() else {
+            panic!("expected two projects")
+        };
+        assert_eq!(a.max_concurrent_tabs, Some(8));
+        let budget = a.budget.clone().unwrap();
+        assert_eq!((budget.max_pages, budget.max_depth), (Some(1000), Some(5)));
+        assert_eq!(b.max_concurrent_tabs, Some(2));
+    }
+
+    #[test]
+    fn type_errors_name_the_project_and_field() {
+        let message = project_error(r#"
+            [[projects]]
+            id = "a"
+            seed_urls = ["https://a.com/"]
+            browser = { window_width = -5, window_height = 600 }
+        "#);
+        assert!(message.starts_with("project \"a\": "), "{message}");
+        assert!(message.contains("browser.window_width"), "{message}");
+    }
+
+    #[test]
+    fn rejects_unknown_normalization_rules() {
+        let message = project_error(r#"
+            [[projects]]
+            id = "a"
+            seed_urls = ["https://a.com/"]
+            canonicalization = { rules = ["strip-www", "strip-everything"] }
+        "#);
+        assert!(message.starts_with("project \"a\": "), "{message}");
+        assert!(message.contains("strip-everything"), "{message}");
+        assert!(message.contains("canonicalization.rules"), "{message}");
+    }
+
+    #[test]
+    fn rejects_a_single_window_dimension() {
+        let message = project_error(r#"
+            [[projects]]
+            id = "a"
+            seed_urls = ["https://a.com/"]
+            browser = { window_width = 1280 }
+        "#);
+        assert_eq!(message, "project \"a\", `browser.window_height`: must be set along with `window_width`");
+    }
+
+    #[test]
+    fn rejects_invalid_url_rule_patterns() {
+        let message = project_error(r#"
+            [[projects]]
+            id = "a"
+            seed_urls = ["https://a.com/"]
+
+            [[projects.url_rules]]
+            action = "exclude"
+            glob = "/cart/**"
+
+            [[projects.url_rules]]
+            action = "include"
+            regex = "^/(docs"
+        "#);
+        assert!(message.starts_with("project \"a\", `url_rules[1].regex`: invalid regex"), "{message}");
+        let message = project_error(r#"
+            [[projects]]
+            id = "a"
+            seed_urls = ["https://a.com/"]
+            url_rules = [{ action = "exclude", glob = "/a/**", regex = "^/a/" }]
+        "#);
+        assert_eq!(message, "project \"a\", `url_rules[0]`: needs exactly one of `glob` or `regex`");
+    }
+
+    #[test]
+    fn rejects_out_of_range_timeouts() {
+        let message = project_error(r#"
+            [[projects]]
+            id = "a"
+            seed_urls = ["https://a.com/"]
+            timeouts = { navigation_ms = 0 }
+        "#);
+        assert_eq!(message, "project \"a\", `timeouts.navigation_ms`: must be greater than 0");
+        let message = project_error(r#"
+            [defaults]
+            wait = { settle_delay_ms = 9223372036854775807 }
+
+            [[projects]]
+            id = "a"
+            seed_urls = ["https://a.com/"]
+        "#);
+        assert!(message.starts_with("project \"a\", `wait.settle_delay_ms`: "), "{message}");
+        // No settle delay at all is fine.
+        ManifestSpec::from_toml_str(r#"
+            [[projects]]
+            id = "a"
+            seed_urls = ["https://a.com/"]
+            wait = { settle_delay_ms = 0 }
+        "#).unwrap();
+    }
+
+    #[test]
+    fn rejects_seed_urls_without_a_host() {
+        let message = project_error(r#"
+            [[projects]]
+            id = "a"
+            seed_urls = ["https://a.com/", "a.com/docs"]
+        "#);
+        assert!(message.starts_with("project \"a\", `seed_urls[1]`: invalid URL"), "{message}");
+    }
+
+    #[test]
+    fn names_projects_without_an_id_by_position() {
+        let message = project_error(r#"
+            [[projects]]
+            id = "a"
+            seed_urls = ["https://a.com/"]
+
+            [[projects]]
+            seed_urls = ["https://b.com/"]
+        "#);
+        assert!(message.starts_with("project \"#2\": "), "{message}");
+        assert!(message.contains("`id`"), "{message}");
+    }
+}
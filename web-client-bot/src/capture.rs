@@ -0,0 +1,88 @@
+use chromiumoxide::cdp::browser_protocol::page::{CaptureScreenshotFormat, PrintToPdfParams};
+use chromiumoxide::page::ScreenshotParams;
+
+use crate::{ClientError, LiveWebpage};
+
+// ————————————————————————————————————————————————————————————————————————————
+// OPTIONS
+// ————————————————————————————————————————————————————————————————————————————
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ScreenshotFormat {
+    #[default]
+    Png,
+    Jpeg,
+}
+
+impl ScreenshotFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::Jpeg => "jpg",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ScreenshotOptions {
+    pub format: ScreenshotFormat,
+    /// JPEG quality from 0 to 100; ignored for PNG.
+    pub quality: Option<u8>,
+    /// Capture the whole scrollable page rather than just the viewport.
+    pub full_page: bool,
+}
+
+impl Default for ScreenshotOptions {
+    fn default() -> Self {
+        Self {
+            format: ScreenshotFormat::Png,
+            quality: None,
+            full_page: true,
+        }
+    }
+}
+
+/// Print-to-PDF options. Printing only works in headless mode.
+#[derive(Debug, Clone, Copy)]
+pub struct PdfOptions {
+    pub landscape: bool,
+    pub print_background: bool,
+}
+
+impl Default for PdfOptions {
+    fn default() -> Self {
+        Self {
+            landscape: false,
+            print_background: true,
+        }
+    }
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// CAPTURE
+// ————————————————————————————————————————————————————————————————————————————
+
+impl LiveWebpage {
+    /// Encoded image of the page as currently rendered.
+    pub async fn screenshot(&self, options: &ScreenshotOptions) -> Result<Vec<u8>, ClientError> {
+        let mut params = ScreenshotParams::builder()
+            .full_page(options.full_page)
+            .format(match options.format {
+                ScreenshotFormat::Png => CaptureScreenshotFormat::Png,
+                ScreenshotFormat::Jpeg => CaptureScreenshotFormat::Jpeg,
+            });
+        if let (ScreenshotFormat::Jpeg, Some(quality)) = (options.format, options.quality) {
+            params = params.quality(i64::from(quality.min(100)));
+        }
+        Ok(self.page.screenshot(params.build()).await?)
+    }
+    /// The page printed to a PDF document.
+    pub async fn print_to_pdf(&self, options: &PdfOptions) -> Result<Vec<u8>, ClientError> {
+        let params = PrintToPdfParams {
+            landscape: Some(options.landscape),
+            print_background: Some(options.print_background),
+            ..Default::default()
+        };
+        Ok(self.page.pdf(params).await?)
+    }
+}
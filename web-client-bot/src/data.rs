@@ -13,7 +13,7 @@ impl Link {
     pub fn parse_list(source: impl AsRef<str>) -> Result<Vec<Link>, serde_json::Error> {
         serde_json::from_str::<Vec<Link>>(source.as_ref())
     }
-    pub async fn scrape_all(page: &chromiumoxide::Page) -> Result<Vec<Link>, serde_json::Error> {
+    pub async fn scrape_all(page: &chromiumoxide::Page) -> Result<Vec<Link>, crate::ClientError> {
         let result = crate::utils::retry_async(
             || async {
                 page
@@ -23,18 +23,9 @@ impl Link {
             },
             5,
             std::time::Duration::from_secs(5),
-        ).await.unwrap();
-        let json_value = result.clone().into_value::<serde_json::Value>().unwrap();
-        // let json_value = serde_json::to_string(&json_value).unwrap();
-        // eprintln!("GIVEN: {}", json_value);
-
-        // let result = page
-        //     .evaluate(SCRAPE_ANCHORS)
-        //     .await
-        //     .unwrap();
-        // result.into_value::<Vec<Link>>()
-        let result = serde_json::from_value::<Vec<Link>>(json_value).unwrap();
-        // let result = serde_json::from_str::<Vec<Link>>(&json_value).unwrap();
+        ).await?;
+        let json_value = result.into_value::<serde_json::Value>()?;
+        let result = serde_json::from_value::<Vec<Link>>(json_value)?;
         Ok(result)
     }
 }
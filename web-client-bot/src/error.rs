@@ -0,0 +1,58 @@
+use crate::utils::TimeoutError;
+
+/// Everything that can go wrong while driving the browser.
+///
+/// Variants carry rendered messages rather than the underlying errors so the
+/// error can be cloned into logs and crawl events.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClientError {
+    /// The browser could not be configured or launched.
+    Launch(String),
+    /// A DevTools protocol command failed, e.g. because the tab crashed.
+    Cdp(String),
+    /// A script evaluated in the page failed or returned unexpected data.
+    Script(String),
+    /// An operation did not finish in time.
+    Timeout(String),
+}
+
+impl std::fmt::Display for ClientError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Launch(message) => write!(f, "failed to launch browser: {message}"),
+            Self::Cdp(message) => write!(f, "browser protocol error: {message}"),
+            Self::Script(message) => write!(f, "page script error: {message}"),
+            Self::Timeout(message) => write!(f, "timed out: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {}
+
+impl From<chromiumoxide::error::CdpError> for ClientError {
+    fn from(error: chromiumoxide::error::CdpError) -> Self {
+        Self::Cdp(error.to_string())
+    }
+}
+
+impl From<TimeoutError> for ClientError {
+    fn from(error: TimeoutError) -> Self {
+        Self::Timeout(error.0)
+    }
+}
+
+impl From<serde_json::Error> for ClientError {
+    fn from(error: serde_json::Error) -> Self {
+        Self::Script(error.to_string())
+    }
+}
+
+/// Errors from [`crate::LiveWebpage::evaluate`] and the retry helpers.
+impl From<Box<dyn std::error::Error + Send + Sync>> for ClientError {
+    fn from(error: Box<dyn std::error::Error + Send + Sync>) -> Self {
+        match error.downcast::<TimeoutError>() {
+            Ok(timeout) => Self::Timeout(timeout.0),
+            Err(error) => Self::Script(error.to_string()),
+        }
+    }
+}
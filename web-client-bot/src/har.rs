@@ -0,0 +1,796 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use chromiumoxide::Page;
+use chromiumoxide::cdp::browser_protocol::network::{
+    EventLoadingFailed,
+    EventLoadingFinished,
+    EventRequestWillBeSent,
+    EventResponseReceived,
+    GetResponseBodyParams,
+    Headers,
+    RequestId,
+    ResourceTiming,
+    Response,
+};
+use chromiumoxide::cdp::browser_protocol::page::{EventDomContentEventFired, EventLoadEventFired};
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+
+use crate::ClientError;
+
+// ————————————————————————————————————————————————————————————————————————————
+// OPTIONS
+// ————————————————————————————————————————————————————————————————————————————
+
+/// Which response bodies end up in `content.text`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HarBodies {
+    #[default]
+    None,
+    /// Text, JSON, JavaScript and XML responses.
+    Text,
+    /// Everything; binary bodies are base64-encoded.
+    All,
+}
+
+#[derive(Debug, Clone)]
+pub struct HarOptions {
+    pub bodies: HarBodies,
+    /// Larger bodies are left out. `None` keeps everything.
+    pub max_body_size: Option<usize>,
+}
+
+impl Default for HarOptions {
+    fn default() -> Self {
+        Self {
+            bodies: HarBodies::None,
+            max_body_size: Some(1024 * 1024),
+        }
+    }
+}
+
+impl HarOptions {
+    fn wants_body(&self, mime_type: &str) -> bool {
+        match self.bodies {
+            HarBodies::None => false,
+            HarBodies::All => true,
+            HarBodies::Text => {
+                let mime_type = mime_type.to_ascii_lowercase();
+                mime_type.starts_with("text/")
+                    || mime_type.contains("json")
+                    || mime_type.contains("javascript")
+                    || mime_type.contains("xml")
+            }
+        }
+    }
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// HAR 1.2
+// ————————————————————————————————————————————————————————————————————————————
+
+/// An HTTP Archive, see <http://www.softwareishard.com/blog/har-12-spec/>.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Har {
+    pub log: HarLog,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HarLog {
+    pub version: String,
+    pub creator: HarCreator,
+    pub pages: Vec<HarPage>,
+    pub entries: Vec<HarEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HarCreator {
+    pub name: String,
+    pub version: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarPage {
+    pub started_date_time: String,
+    pub id: String,
+    pub title: String,
+    pub page_timings: HarPageTimings,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarPageTimings {
+    pub on_content_load: f64,
+    pub on_load: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarEntry {
+    pub pageref: String,
+    pub started_date_time: String,
+    /// Total time in milliseconds.
+    pub time: f64,
+    pub request: HarRequest,
+    pub response: HarResponse,
+    pub cache: HarCache,
+    pub timings: HarTimings,
+    #[serde(rename = "serverIPAddress", skip_serializing_if = "Option::is_none")]
+    pub server_ip_address: Option<String>,
+    #[serde(rename = "_resourceType", skip_serializing_if = "Option::is_none")]
+    pub resource_type: Option<String>,
+    /// Why the request failed, e.g. `net::ERR_BLOCKED_BY_CLIENT`.
+    #[serde(rename = "_error", skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarRequest {
+    pub method: String,
+    pub url: String,
+    pub http_version: String,
+    pub cookies: Vec<HarNameValue>,
+    pub headers: Vec<HarNameValue>,
+    pub query_string: Vec<HarNameValue>,
+    pub headers_size: i64,
+    pub body_size: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarResponse {
+    pub status: i64,
+    pub status_text: String,
+    pub http_version: String,
+    pub cookies: Vec<HarNameValue>,
+    pub headers: Vec<HarNameValue>,
+    pub content: HarContent,
+    #[serde(rename = "redirectURL")]
+    pub redirect_url: String,
+    pub headers_size: i64,
+    pub body_size: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarContent {
+    pub size: i64,
+    pub mime_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HarCache {}
+
+/// Phases in milliseconds; `-1` if a phase does not apply.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HarTimings {
+    pub blocked: f64,
+    pub dns: f64,
+    pub connect: f64,
+    pub send: f64,
+    pub wait: f64,
+    pub receive: f64,
+    pub ssl: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HarNameValue {
+    pub name: String,
+    pub value: String,
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// RECORDER
+// ————————————————————————————————————————————————————————————————————————————
+
+/// A request as far as its events have arrived.
+#[derive(Debug, Clone)]
+struct PendingEntry {
+    request_id: RequestId,
+    request: EventRequestWillBeSent,
+    response: Option<Response>,
+    resource_type: Option<String>,
+    /// The redirect that ended this entry, if any.
+    redirect_url: Option<String>,
+    finished: Option<f64>,
+    encoded_data_length: Option<f64>,
+    error: Option<String>,
+}
+
+#[derive(Debug, Default)]
+struct Recording {
+    entries: Vec<PendingEntry>,
+    /// Index of the open entry for each request ID. Redirects reuse the ID,
+    /// so earlier hops are closed when the next request is sent.
+    open: HashMap<String, usize>,
+    dom_content_loaded: Option<f64>,
+    load: Option<f64>,
+}
+
+enum HarEvent {
+    Request(Arc<EventRequestWillBeSent>),
+    Response(Arc<EventResponseReceived>),
+    Finished(Arc<EventLoadingFinished>),
+    Failed(Arc<EventLoadingFailed>),
+    DomContentLoaded(Arc<EventDomContentEventFired>),
+    Load(Arc<EventLoadEventFired>),
+}
+
+/// Collects the network events of a page from before its navigation until
+/// it is closed.
+#[derive(Debug, Clone)]
+pub struct HarRecorder {
+    options: HarOptions,
+    recording: Arc<Mutex<Recording>>,
+}
+
+impl HarRecorder {
+    /// Start listening. Call before navigating, while `Network` is not yet
+    /// enabled, so that the first request is not missed.
+    pub async fn start(page: &Page, options: HarOptions) -> Result<Self, ClientError> {
+        let events = futures::stream::select_all([
+            page.event_listener::<EventRequestWillBeSent>().await?.map(HarEvent::Request).boxed(),
+            page.event_listener::<EventResponseReceived>().await?.map(HarEvent::Response).boxed(),
+            page.event_listener::<EventLoadingFinished>().await?.map(HarEvent::Finished).boxed(),
+            page.event_listener::<EventLoadingFailed>().await?.map(HarEvent::Failed).boxed(),
+            page.event_listener::<EventDomContentEventFired>().await?.map(HarEvent::DomContentLoaded).boxed(),
+            page.event_listener::<EventLoadEventFired>().await?.map(HarEvent::Load).boxed(),
+        ]);
+        let recording = Arc::new(Mutex::new(Recording::default()));
+        let sink = Arc::clone(&recording);
+        tokio::spawn(async move {
+            let mut events = events;
+            while let Some(event) = events.next().await {
+                match sink.lock() {
+                    Ok(mut recording) => recording.apply(event),
+                    Err(_) => break,
+                }
+            }
+        });
+        Ok(Self { options, recording })
+    }
+    /// The HAR of everything recorded so far. Response bodies are fetched
+    /// now, so call this before the page is closed.
+    pub async fn build(&self, page: &Page) -> Har {
+        let (entries, dom_content_loaded, load) = match self.recording.lock() {
+            Ok(recording) => (recording.entries.clone(), recording.dom_content_loaded, recording.load),
+            Err(_) => (Vec::new(), None, None),
+        };
+        let title = page
+            .evaluate("document.title")
+            .await
+            .ok()
+            .and_then(|title| title.into_value::<String>().ok())
+            .unwrap_or_default();
+        let page_id = String::from("page_1");
+        let first = entries.first();
+        let started = first.map(|entry| *entry.request.timestamp.inner());
+        let since_start = |timestamp: Option<f64>| match (started, timestamp) {
+            (Some(started), Some(timestamp)) => (timestamp - started) * 1000.0,
+            _ => -1.0,
+        };
+        let har_page = HarPage {
+            started_date_time: first
+                .map(|entry| date_time(*entry.request.wall_time.inner()))
+                .unwrap_or_default(),
+            id: page_id.clone(),
+            title,
+            page_timings: HarPageTimings {
+                on_content_load: since_start(dom_content_loaded),
+                on_load: since_start(load),
+            },
+        };
+        let mut har_entries = Vec::with_capacity(entries.len());
+        for entry in entries.iter() {
+            let body = match entry.wants_body(&self.options) {
+                true => fetch_body(page, entry, &self.options).await,
+                false => None,
+            };
+            har_entries.push(entry.to_har(&page_id, body));
+        }
+        Har {
+            log: HarLog {
+                version: String::from("1.2"),
+                creator: HarCreator {
+                    name: String::from(env!("CARGO_PKG_NAME")),
+                    version: String::from(env!("CARGO_PKG_VERSION")),
+                },
+                pages: vec![har_page],
+                entries: har_entries,
+            },
+        }
+    }
+}
+
+impl Recording {
+    fn apply(&mut self, event: HarEvent) {
+        match event {
+            HarEvent::Request(event) => {
+                let request_id = event.request_id.inner().clone();
+                // The redirect response arrives with the request it caused.
+                if let (Some(redirect), Some(previous)) = (event.redirect_response.as_ref(), self.open_entry(&event.request_id)) {
+                    previous.response = Some(redirect.clone());
+                    previous.redirect_url = Some(event.request.url.clone());
+                    previous.finished = Some(*event.timestamp.inner());
+                }
+                self.open.insert(request_id, self.entries.len());
+                self.entries.push(PendingEntry {
+                    request_id: event.request_id.clone(),
+                    request: event.as_ref().clone(),
+                    response: None,
+                    resource_type: event.r#type.as_ref().map(|kind| kind.as_ref().to_string()),
+                    redirect_url: None,
+                    finished: None,
+                    encoded_data_length: None,
+                    error: None,
+                });
+            }
+            HarEvent::Response(event) => {
+                if let Some(entry) = self.open_entry(&event.request_id) {
+                    entry.response = Some(event.response.clone());
+                    entry.resource_type = Some(event.r#type.as_ref().to_string());
+                }
+            }
+            HarEvent::Finished(event) => {
+                if let Some(entry) = self.open_entry(&event.request_id) {
+                    entry.finished = Some(*event.timestamp.inner());
+                    entry.encoded_data_length = Some(event.encoded_data_length);
+                }
+            }
+            HarEvent::Failed(event) => {
+                if let Some(entry) = self.open_entry(&event.request_id) {
+                    entry.finished = Some(*event.timestamp.inner());
+                    entry.error = Some(event.error_text.clone());
+                }
+            }
+            HarEvent::DomContentLoaded(event) => {
+                self.dom_content_loaded.get_or_insert(*event.timestamp.inner());
+            }
+            HarEvent::Load(event) => {
+                self.load.get_or_insert(*event.timestamp.inner());
+            }
+        }
+    }
+    fn open_entry(&mut self, request_id: &RequestId) -> Option<&mut PendingEntry> {
+        let index = *self.open.get(request_id.inner())?;
+        self.entries.get_mut(index)
+    }
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// ENTRIES
+// ————————————————————————————————————————————————————————————————————————————
+
+impl PendingEntry {
+    fn wants_body(&self, options: &HarOptions) -> bool {
+        let Some(response) = self.response.as_ref() else {
+            return false
+        };
+        let too_large = match (options.max_body_size, self.encoded_data_length) {
+            (Some(max_body_size), Some(length)) => length > max_body_size as f64,
+            _ => false,
+        };
+        self.finished.is_some()
+            && self.error.is_none()
+            && self.redirect_url.is_none()
+            && !too_large
+            && options.wants_body(&response.mime_type)
+    }
+    fn to_har(&self, page_id: &str, body: Option<(String, bool)>) -> HarEntry {
+        let request = &self.request.request;
+        let url = match request.url_fragment.as_ref() {
+            Some(fragment) => format!("{}{fragment}", request.url),
+            None => request.url.clone(),
+        };
+        let http_version = self.response
+            .as_ref()
+            .and_then(|response| response.protocol.as_deref())
+            .map(http_version)
+            .unwrap_or_else(|| String::from("HTTP/1.1"));
+        let timings = self.timings();
+        let time = [timings.blocked, timings.dns, timings.connect, timings.send, timings.wait, timings.receive]
+            .into_iter()
+            .filter(|phase| *phase > 0.0)
+            .sum();
+        let body_size = self.encoded_data_length
+            .map(|length| length as i64)
+            .unwrap_or(-1);
+        let response = match self.response.as_ref() {
+            Some(response) => {
+                let (text, encoding) = match body {
+                    Some((text, true)) => (Some(text), Some(String::from("base64"))),
+                    Some((text, false)) => (Some(text), None),
+                    None => (None, None),
+                };
+                let size = match (text.as_ref(), encoding.as_ref()) {
+                    (Some(text), None) => text.len() as i64,
+                    (Some(text), Some(_)) => (text.len() / 4 * 3) as i64 - text.chars().rev().take_while(|c| *c == '=').count() as i64,
+                    (None, _) => body_size.max(0),
+                };
+                HarResponse {
+                    status: response.status,
+                    status_text: response.status_text.clone(),
+                    http_version: http_version.clone(),
+                    cookies: Vec::new(),
+                    headers: name_values(&response.headers),
+                    content: HarContent {
+                        size,
+                        mime_type: response.mime_type.clone(),
+                        text,
+                        encoding,
+                    },
+                    redirect_url: self.redirect_url.clone().unwrap_or_default(),
+                    headers_size: -1,
+                    body_size,
+                }
+            }
+            // Failed before any response arrived.
+            None => HarResponse {
+                status: 0,
+                status_text: String::new(),
+                http_version: http_version.clone(),
+                cookies: Vec::new(),
+                headers: Vec::new(),
+                content: HarContent {
+                    size: 0,
+                    mime_type: String::from("x-unknown"),
+                    text: None,
+                    encoding: None,
+                },
+                redirect_url: String::new(),
+                headers_size: -1,
+                body_size: -1,
+            },
+        };
+        HarEntry {
+            pageref: page_id.to_string(),
+            started_date_time: date_time(*self.request.wall_time.inner()),
+            time,
+            request: HarRequest {
+                method: request.method.clone(),
+                url,
+                http_version,
+                cookies: Vec::new(),
+                headers: name_values(&request.headers),
+                query_string: query_string(&request.url),
+                headers_size: -1,
+                body_size: match request.has_post_data {
+                    Some(true) => -1,
+                    _ => 0,
+                },
+            },
+            response,
+            cache: HarCache::default(),
+            timings,
+            server_ip_address: self.response
+                .as_ref()
+                .and_then(|response| response.remote_ip_address.as_ref())
+                .map(|ip_address| ip_address.trim_matches(['[', ']']).to_string()),
+            resource_type: self.resource_type.clone(),
+            error: self.error.clone(),
+        }
+    }
+    /// HAR phases from the CDP resource timing, whose offsets are relative
+    /// to `request_time` with `-1` for phases that did not happen.
+    fn timings(&self) -> HarTimings {
+        let timing = self.response.as_ref().and_then(|response| response.timing.as_ref());
+        let Some(timing) = timing else {
+            let total = match self.finished {
+                Some(finished) => ((finished - *self.request.timestamp.inner()) * 1000.0).max(0.0),
+                None => 0.0,
+            };
+            return HarTimings { blocked: -1.0, dns: -1.0, connect: -1.0, send: 0.0, wait: total, receive: 0.0, ssl: -1.0 }
+        };
+        let span = |start: f64, end: f64| match start >= 0.0 && end >= start {
+            true => end - start,
+            false => -1.0,
+        };
+        let ResourceTiming { dns_start, dns_end, connect_start, connect_end, ssl_start, ssl_end, send_start, send_end, receive_headers_end, .. } = *timing;
+        let blocked = [dns_start, connect_start, send_start]
+            .into_iter()
+            .find(|start| *start >= 0.0)
+            .unwrap_or(-1.0);
+        let receive = match self.finished {
+            Some(finished) => ((finished - timing.request_time) * 1000.0 - receive_headers_end).max(0.0),
+            None => 0.0,
+        };
+        HarTimings {
+            blocked,
+            dns: span(dns_start, dns_end),
+            connect: span(connect_start, connect_end),
+            send: span(send_start, send_end).max(0.0),
+            wait: span(send_end, receive_headers_end).max(0.0),
+            receive,
+            ssl: span(ssl_start, ssl_end),
+        }
+    }
+}
+
+async fn fetch_body(page: &Page, entry: &PendingEntry, options: &HarOptions) -> Option<(String, bool)> {
+    let returns = page
+        .execute(GetResponseBodyParams::new(entry.request_id.clone()))
+        .await
+        .ok()?
+        .result;
+    let within_limit = options.max_body_size.is_none_or(|max_body_size| returns.body.len() <= max_body_size);
+    within_limit.then_some((returns.body, returns.base64_encoded))
+}
+
+/// `h2` → `HTTP/2`, `http/1.1` → `HTTP/1.1`.
+fn http_version(protocol: &str) -> String {
+    match protocol {
+        "h2" => String::from("HTTP/2"),
+        "h3" | "h3-29" => String::from("HTTP/3"),
+        protocol => protocol.to_ascii_uppercase(),
+    }
+}
+
+/// CDP joins repeated headers with `\n`; HAR lists them one by one.
+fn name_values(headers: &Headers) -> Vec<HarNameValue> {
+    headers
+        .inner()
+        .as_object()
+        .into_iter()
+        .flatten()
+        .flat_map(|(name, value)| {
+            let value = match value {
+                serde_json::Value::String(value) => value.clone(),
+                value => value.to_string(),
+            };
+            value
+                .split('\n')
+                .map(|value| HarNameValue { name: name.clone(), value: value.to_string() })
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+fn query_string(url: &str) -> Vec<HarNameValue> {
+    let Some((_, query)) = url.split_once('?') else {
+        return Vec::new()
+    };
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            HarNameValue { name: name.to_string(), value: value.to_string() }
+        })
+        .collect()
+}
+
+/// ISO 8601 with milliseconds, from seconds since the epoch.
+fn date_time(seconds: f64) -> String {
+    chrono::DateTime::from_timestamp_millis((seconds * 1000.0) as i64)
+        .unwrap_or_default()
+        .to_rfc3339_opts(chrono::SecondsFormat::Millis, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn headers(value: serde_json::Value) -> Headers {
+        Headers::new(value)
+    }
+
+    fn pairs(name_values: &[HarNameValue]) -> Vec<(&str, &str)> {
+        name_values
+            .iter()
+            .map(|pair| (pair.name.as_str(), pair.value.as_str()))
+            .collect()
+    }
+
+    fn request_event(url: &str, timestamp: f64) -> HarEvent {
+        let event = json!({
+            "requestId": "1",
+            "loaderId": "loader",
+            "documentURL": url,
+            "request": {
+                "url": url,
+                "method": "GET",
+                "headers": { "accept": "text/html" },
+                "initialPriority": "VeryHigh",
+                "referrerPolicy": "strict-origin-when-cross-origin",
+            },
+            "timestamp": timestamp,
+            "wallTime": 1_700_000_000.5,
+            "initiator": { "type": "other" },
+            "redirectHasExtraInfo": false,
+            "type": "Document",
+        });
+        HarEvent::Request(Arc::new(serde_json::from_value(event).unwrap()))
+    }
+
+    fn response_event(timing: Option<serde_json::Value>) -> HarEvent {
+        let mut response = json!({
+            "url": "https://example.com/",
+            "status": 200,
+            "statusText": "OK",
+            "headers": { "content-type": "text/html", "set-cookie": "a=1\nb=2" },
+            "mimeType": "text/html",
+            "charset": "utf-8",
+            "connectionReused": false,
+            "connectionId": 1.0,
+            "remoteIPAddress": "[2001:db8::1]",
+            "encodedDataLength": 100.0,
+            "protocol": "h2",
+            "securityState": "secure",
+        });
+        if let Some(timing) = timing {
+            response["timing"] = timing;
+        }
+        let event = json!({
+            "requestId": "1",
+            "loaderId": "loader",
+            "timestamp": 100.05,
+            "type": "Document",
+            "response": response,
+            "hasExtraInfo": false,
+        });
+        HarEvent::Response(Arc::new(serde_json::from_value(event).unwrap()))
+    }
+
+    fn finished_event(timestamp: f64) -> HarEvent {
+        let event = json!({ "requestId": "1", "timestamp": timestamp, "encodedDataLength": 1234.0 });
+        HarEvent::Finished(Arc::new(serde_json::from_value(event).unwrap()))
+    }
+
+    /// Offsets in milliseconds from `requestTime`, `-1` for phases that did
+    /// not happen.
+    fn resource_timing(dns: (f64, f64), connect: (f64, f64), ssl: (f64, f64)) -> serde_json::Value {
+        json!({
+            "requestTime": 100.0,
+            "proxyStart": -1.0,
+            "proxyEnd": -1.0,
+            "dnsStart": dns.0,
+            "dnsEnd": dns.1,
+            "connectStart": connect.0,
+            "connectEnd": connect.1,
+            "sslStart": ssl.0,
+            "sslEnd": ssl.1,
+            "workerStart": -1.0,
+            "workerReady": -1.0,
+            "workerFetchStart": -1.0,
+            "workerRespondWithSettled": -1.0,
+            "sendStart": 31.0,
+            "sendEnd": 32.0,
+            "pushStart": 0.0,
+            "pushEnd": 0.0,
+            "receiveHeadersStart": 80.0,
+            "receiveHeadersEnd": 82.0,
+        })
+    }
+
+    fn record(events: impl IntoIterator<Item=HarEvent>) -> HarEntry {
+        let mut recording = Recording::default();
+        for event in events {
+            recording.apply(event);
+        }
+        let [entry] = recording.entries.as_slice() else {
+            panic!("expected one entry")
+        };
+        entry.to_har("page_1", None)
+    }
+
+    #[test]
+    fn maps_protocols_to_http_versions() {
+        assert_eq!(http_version("h2"), "HTTP/2");
+        assert_eq!(http_version("h3"), "HTTP/3");
+        assert_eq!(http_version("h3-29"), "HTTP/3");
+        assert_eq!(http_version("http/1.1"), "HTTP/1.1");
+        assert_eq!(http_version("http/1.0"), "HTTP/1.0");
+    }
+
+    #[test]
+    fn lists_repeated_headers_one_by_one() {
+        let listed = name_values(&headers(json!({
+            "content-type": "text/html",
+            "set-cookie": "a=1\nb=2",
+            "x-count": 3,
+        })));
+        assert_eq!(
+            pairs(&listed),
+            [("content-type", "text/html"), ("set-cookie", "a=1"), ("set-cookie", "b=2"), ("x-count", "3")],
+        );
+        assert!(name_values(&headers(json!({}))).is_empty());
+    }
+
+    #[test]
+    fn splits_the_query_string() {
+        assert_eq!(
+            pairs(&query_string("https://example.com/search?q=a+b&path=%2Fdocs&flag&&empty=")),
+            [("q", "a+b"), ("path", "%2Fdocs"), ("flag", ""), ("empty", "")],
+        );
+        assert_eq!(pairs(&query_string("https://example.com/?a=1=2")), [("a", "1=2")]);
+        assert!(query_string("https://example.com/").is_empty());
+        assert!(query_string("https://example.com/?").is_empty());
+    }
+
+    #[test]
+    fn formats_wall_times() {
+        assert_eq!(date_time(1_700_000_000.5), "2023-11-14T22:13:20.500Z");
+        assert_eq!(date_time(0.0), "1970-01-01T00:00:00.000Z");
+    }
+
+    #[test]
+    fn splits_a_new_connection_into_phases() {
+        let timing = resource_timing((1.0, 5.0), (5.0, 30.0), (10.0, 30.0));
+        let entry = record([
+            request_event("https://example.com/", 100.0),
+            response_event(Some(timing)),
+            finished_event(100.125),
+        ]);
+        let timings = entry.timings;
+        assert_eq!(
+            [timings.blocked, timings.dns, timings.connect, timings.ssl, timings.send, timings.wait, timings.receive],
+            [1.0, 4.0, 25.0, 20.0, 1.0, 50.0, 43.0],
+        );
+        // `ssl` is part of `connect` and not counted twice.
+        assert_eq!(entry.time, 124.0);
+    }
+
+    #[test]
+    fn leaves_out_phases_of_a_reused_connection() {
+        let timing = resource_timing((-1.0, -1.0), (-1.0, -1.0), (-1.0, -1.0));
+        let entry = record([
+            request_event("https://example.com/", 100.0),
+            response_event(Some(timing)),
+            finished_event(100.125),
+        ]);
+        let timings = entry.timings;
+        assert_eq!(
+            [timings.blocked, timings.dns, timings.connect, timings.ssl, timings.send, timings.wait, timings.receive],
+            [31.0, -1.0, -1.0, -1.0, 1.0, 50.0, 43.0],
+        );
+        assert_eq!(entry.time, 125.0);
+    }
+
+    #[test]
+    fn counts_everything_as_waiting_without_resource_timing() {
+        let entry = record([
+            request_event("https://example.com/", 100.0),
+            response_event(None),
+            finished_event(100.25),
+        ]);
+        let timings = entry.timings;
+        assert_eq!(
+            [timings.blocked, timings.dns, timings.connect, timings.ssl, timings.send, timings.wait, timings.receive],
+            [-1.0, -1.0, -1.0, -1.0, 0.0, 250.0, 0.0],
+        );
+        assert_eq!(entry.time, 250.0);
+    }
+
+    #[test]
+    fn builds_an_entry_from_cdp_events() {
+        let entry = record([
+            request_event("https://example.com/search?q=a", 100.0),
+            response_event(None),
+            finished_event(100.25),
+        ]);
+        assert_eq!(entry.started_date_time, "2023-11-14T22:13:20.500Z");
+        assert_eq!(entry.request.url, "https://example.com/search?q=a");
+        assert_eq!(pairs(&entry.request.query_string), [("q", "a")]);
+        assert_eq!(pairs(&entry.request.headers), [("accept", "text/html")]);
+        assert_eq!(entry.request.http_version, "HTTP/2");
+        assert_eq!(entry.response.status, 200);
+        assert_eq!(entry.response.http_version, "HTTP/2");
+        assert_eq!(
+            pairs(&entry.response.headers),
+            [("content-type", "text/html"), ("set-cookie", "a=1"), ("set-cookie", "b=2")],
+        );
+        assert_eq!(entry.response.body_size, 1234);
+        assert_eq!(entry.response.content.size, 1234);
+        assert_eq!(entry.server_ip_address.as_deref(), Some("2001:db8::1"));
+        assert_eq!(entry.resource_type.as_deref(), Some("Document"));
+    }
+}
@@ -1,8 +1,14 @@
 pub mod data;
 pub mod wait_framework;
 pub mod utils;
+pub mod error;
+pub mod capture;
+pub mod network;
+pub mod har;
 
 use std::i64;
+use std::path::PathBuf;
+use std::time::Duration;
 // use std::pin::Pin;
 
 use chromiumoxide::browser::Browser;
@@ -10,6 +16,8 @@ use chromiumoxide::Page;
 use futures::StreamExt;
 use colored::Colorize;
 
+pub use crate::error::ClientError;
+
 // ————————————————————————————————————————————————————————————————————————————
 // SETTINGS
 // ————————————————————————————————————————————————————————————————————————————
@@ -28,6 +36,19 @@ pub enum HeadlessMode {
 #[derive(Debug, Clone, Default)]
 pub struct WebClientSettings {
     pub headless_mode: Option<HeadlessMode>,
+    /// Defaults to the Chrome/Chromium found on the system.
+    pub chrome_executable: Option<PathBuf>,
+    /// Width and height of both the window and the page viewport. Defaults
+    /// to an 800×600 viewport.
+    pub window_size: Option<(u32, u32)>,
+    /// Defaults to a fresh temporary profile.
+    pub user_data_dir: Option<PathBuf>,
+    /// Required in most containers.
+    pub no_sandbox: bool,
+    /// Limit for a single DevTools command.
+    pub request_timeout: Option<Duration>,
+    /// Extra command line switches, e.g. `--lang=de`.
+    pub args: Vec<String>,
 }
 
 impl WebClientSettings {
@@ -41,6 +62,26 @@ impl WebClientSettings {
             };
             builder = builder.headless_mode(headless_mode);
         }
+        if let Some(chrome_executable) = self.chrome_executable.as_ref() {
+            builder = builder.chrome_executable(chrome_executable);
+        }
+        if let Some((width, height)) = self.window_size {
+            builder = builder
+                .window_size(width, height)
+                .viewport(chromiumoxide::handler::viewport::Viewport { width, height, ..Default::default() });
+        }
+        if let Some(user_data_dir) = self.user_data_dir.as_ref() {
+            builder = builder.user_data_dir(user_data_dir);
+        }
+        if self.no_sandbox {
+            builder = builder.no_sandbox();
+        }
+        if let Some(request_timeout) = self.request_timeout {
+            builder = builder.request_timeout(request_timeout);
+        }
+        for arg in self.args.iter() {
+            builder = builder.arg(arg);
+        }
         builder
     }
 }
@@ -55,12 +96,14 @@ pub struct WebClient {
 }
 
 impl WebClient {
-    pub async fn start(web_client_settings: WebClientSettings) -> WebClient {
+    pub async fn start(web_client_settings: WebClientSettings) -> Result<WebClient, ClientError> {
         let browser_config = web_client_settings
             .chrome_browser_config_builder()
             .build()
-            .unwrap();
-        let (browser, mut handler) = Browser::launch(browser_config).await.unwrap();
+            .map_err(ClientError::Launch)?;
+        let (browser, mut handler) = Browser::launch(browser_config)
+            .await
+            .map_err(|error| ClientError::Launch(error.to_string()))?;
         tokio::spawn(async move {
             while let Some(payload) = handler.next().await {
                 match payload {
@@ -75,24 +118,25 @@ impl WebClient {
                 }
             }
         });
-        WebClient { browser }
+        Ok(WebClient { browser })
     }
-    pub async fn close(mut self) {
-        let _ = self.browser.close().await.unwrap();
+    pub async fn close(mut self) -> Result<(), ClientError> {
+        self.browser.close().await?;
+        Ok(())
     }
 }
 
 
 
 impl WebClient {
-    pub async fn open_new_tab_at_url(&mut self, url: impl AsRef<str>) -> LiveWebpage {
+    pub async fn open_new_tab_at_url(&self, url: impl AsRef<str>) -> Result<LiveWebpage, ClientError> {
         let requested_url = url.as_ref().to_string();
 
-        let page = self.browser.new_page(requested_url.clone()).await.unwrap();
-        page.wait_for_navigation().await.unwrap();
+        let page = self.browser.new_page(requested_url.clone()).await?;
+        page.wait_for_navigation().await?;
 
-        let actual_url = page.evaluate("window.location.href").await.unwrap();
-        let actual_url = actual_url.value().unwrap().as_str();
+        let actual_url = page.evaluate("window.location.href").await?;
+        let actual_url = actual_url.value().and_then(|x| x.as_str());
 
         if let Some(final_url) = actual_url {
             if final_url != requested_url {
@@ -105,7 +149,7 @@ impl WebClient {
             }
         }
 
-        LiveWebpage { page, status_code: None }
+        Ok(LiveWebpage { page, response: None, redirect_chain: Vec::new(), har: None })
     }
 }
 
@@ -135,79 +179,189 @@ impl WebClient {
 //     }
 // }
 
+// —— TAB OPTIONS —————————————————————————————————————————————————————————————
+
+/// How long to wait for a tab to load and what to record besides its main
+/// document.
+#[derive(Debug, Clone, Default)]
+pub struct TabOptions {
+    /// Record every request of the page for [`LiveWebpage::har`].
+    pub har: Option<har::HarOptions>,
+    pub timeouts: NavigationTimeouts,
+}
+
+/// Limits of the navigation in [`WebClient::open_new_tab_with_options`].
+/// Running into one is not an error; the page is taken as far as it loaded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NavigationTimeouts {
+    /// For the navigation request, including one retry.
+    pub navigation: Duration,
+    /// For the page to become interactive after the navigation.
+    pub ready: Duration,
+    /// For the main document response to arrive.
+    pub document_response: Duration,
+}
+
+impl Default for NavigationTimeouts {
+    fn default() -> Self {
+        Self {
+            navigation: Duration::from_secs(10),
+            ready: Duration::from_secs(10),
+            document_response: Duration::from_secs(5),
+        }
+    }
+}
+
 // —— WEB CLIENT TAB ——————————————————————————————————————————————————————————
 
 #[derive(Debug)]
 pub struct LiveWebpage {
     page: Page,
-    status_code: Option<i64>,
+    /// The main document response, if one was seen.
+    response: Option<network::DocumentResponse>,
+    redirect_chain: Vec<network::RedirectHop>,
+    /// Set when the tab was opened with [`TabOptions::har`].
+    har: Option<har::HarRecorder>,
 }
 
 impl LiveWebpage {
     /// This resolves once the navigation finished and the page is loaded.
-    pub async fn wait_for_navigation(&self) {
-        let _ = self.page.wait_for_navigation().await.unwrap();
+    pub async fn wait_for_navigation(&self) -> Result<(), ClientError> {
+        self.page.wait_for_navigation().await?;
+        Ok(())
     }
     /// Returns the HTML content of the page
-    pub async fn html_content(&self) -> String {
-        self.page.content().await.unwrap()
+    pub async fn html_content(&self) -> Result<String, ClientError> {
+        Ok(self.page.content().await?)
     }
     /// Scrape all anchor links in the DOM tree.
-    pub async fn scrape_all_anchor_links(&self) -> Result<Vec<crate::data::Link>, Box<dyn std::error::Error>> {
-        let links = crate::data::Link::scrape_all(&self.page).await?;
-        Ok(links)
+    pub async fn scrape_all_anchor_links(&self) -> Result<Vec<crate::data::Link>, ClientError> {
+        crate::data::Link::scrape_all(&self.page).await
     }
     /// Returns the current url of the page
-    pub async fn url(&self) -> Option<String> {
-        self.page.url().await.unwrap()
+    pub async fn url(&self) -> Result<Option<String>, ClientError> {
+        Ok(self.page.url().await?)
     }
     /// Close this page.
-    pub async fn close(self) {
-        self.page.close().await.unwrap()
+    pub async fn close(self) -> Result<(), ClientError> {
+        self.page.close().await?;
+        Ok(())
+    }
+    pub async fn is_text_html_document(&self) -> Result<bool, ClientError> {
+        let content_type = self.content_type().await?;
+        Ok(content_type.eq_ignore_ascii_case("text/html"))
     }
-    pub async fn is_text_html_document(&self) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+    /// The `document.contentType` of the loaded page.
+    pub async fn content_type(&self) -> Result<String, ClientError> {
         let js = "document.contentType";
         let value = self.evaluate(js).await?;
-    
-        Ok(value
-            .as_str()
-            .map(|s| s.eq_ignore_ascii_case("text/html"))
-            .unwrap_or(false))
+        Ok(value.as_str().unwrap_or_default().to_string())
     }
     pub fn status_code(&self) -> Option<i64> {
-        self.status_code.clone()
+        self.response.as_ref().map(|response| response.status)
+    }
+    /// MIME type of the main document response. Unlike
+    /// [`Self::content_type`] this is also known for downloads, which never
+    /// replace the blank page.
+    pub fn mime_type(&self) -> Option<&str> {
+        self.response.as_ref().map(|response| response.mime_type.as_str())
+    }
+    /// Headers, remote address, protocol and timing of the main document
+    /// response.
+    pub fn response(&self) -> Option<&network::DocumentResponse> {
+        self.response.as_ref()
+    }
+    /// Every HTTP and client-side redirect seen while navigating, in order.
+    /// Empty if the requested URL was served directly.
+    pub fn redirect_chain(&self) -> &[network::RedirectHop] {
+        &self.redirect_chain
     }
-    pub async fn actual_url(&self) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+    /// Every request the page made so far, or `None` if the tab was not
+    /// opened with HAR recording.
+    pub async fn har(&self) -> Option<har::Har> {
+        let recorder = self.har.as_ref()?;
+        Some(recorder.build(&self.page).await)
+    }
+    pub async fn actual_url(&self) -> Result<String, ClientError> {
         let actual_url = self.page.evaluate("window.location.href").await?;
-        let actual_url = actual_url.value().unwrap().as_str().unwrap_or("").to_string();
-        Ok(actual_url)
+        let actual_url = actual_url
+            .value()
+            .and_then(|x| x.as_str())
+            .ok_or_else(|| ClientError::Script(String::from("`window.location.href` is not a string")))?;
+        Ok(actual_url.to_string())
     }
 }
 
 
 impl WebClient {
+    /// Open `url` in a new tab and record the response of its main document
+    /// and the redirects that led to it.
+    ///
+    /// Navigation problems short of a broken tab are tolerated: the page is
+    /// returned as far as it loaded, with `response` left empty if no
+    /// document response was seen.
     pub async fn open_new_tab_at_url_with_network_tracking(
-        &mut self,
+        &self,
         url: impl AsRef<str>,
-    ) -> LiveWebpage {
-        use chromiumoxide::cdp::browser_protocol::network::{EnableParams, EventResponseReceived, ResourceType};
-        use futures::StreamExt;
-
+    ) -> Result<LiveWebpage, ClientError> {
+        self.open_new_tab_with_options(url, &TabOptions::default()).await
+    }
+    /// [`Self::open_new_tab_at_url_with_network_tracking`], with optional
+    /// recording set up before the navigation starts.
+    pub async fn open_new_tab_with_options(
+        &self,
+        url: impl AsRef<str>,
+        options: &TabOptions,
+    ) -> Result<LiveWebpage, ClientError> {
         let requested_url = url.as_ref().to_string();
 
         // Create a new blank page
-        let page = self.browser.new_page("about:blank").await.unwrap();
+        let page = self.browser.new_page("about:blank").await?;
+
+        let har = match options.har.as_ref() {
+            Some(har_options) => match har::HarRecorder::start(&page, har_options.clone()).await {
+                Ok(recorder) => Some(recorder),
+                Err(error) => {
+                    let _ = page.close().await;
+                    return Err(error)
+                }
+            },
+            None => None,
+        };
+
+        match Self::navigate_with_network_tracking(&page, &requested_url, &options.timeouts).await {
+            Ok(( response, redirect_chain )) => Ok(LiveWebpage { page, response, redirect_chain, har }),
+            Err(error) => {
+                let _ = page.close().await;
+                Err(error)
+            }
+        }
+    }
+    async fn navigate_with_network_tracking(
+        page: &Page,
+        requested_url: &str,
+        timeouts: &NavigationTimeouts,
+    ) -> Result<( Option<network::DocumentResponse>, Vec<network::RedirectHop> ), ClientError> {
+        use chromiumoxide::cdp::browser_protocol::network::{EnableParams, EventRequestWillBeSent, EventResponseReceived, ResourceType};
+        use chromiumoxide::cdp::browser_protocol::page::{EventFrameRequestedNavigation, EventLoadEventFired};
+        use futures::{FutureExt, StreamExt};
 
-        page.enable_stealth_mode().await.unwrap();
+        page.enable_stealth_mode().await?;
 
         // Get the main frame ID (used to identify top-level responses)
-        let main_frame_id = page.mainframe().await.unwrap().unwrap();
+        let main_frame_id = page
+            .mainframe()
+            .await?
+            .ok_or_else(|| ClientError::Cdp(String::from("page has no main frame")))?;
 
         // Enable network tracking
-        page.execute(EnableParams::default()).await.unwrap();
+        page.execute(EnableParams::default()).await?;
 
         // Start listening to response events BEFORE navigation
-        let mut responses = page.event_listener::<EventResponseReceived>().await.unwrap();
+        let mut responses = page.event_listener::<EventResponseReceived>().await?;
+        let mut requests = page.event_listener::<EventRequestWillBeSent>().await?;
+        let mut navigations = page.event_listener::<EventFrameRequestedNavigation>().await?;
+        let mut load_events = page.event_listener::<EventLoadEventFired>().await?;
 
         // Start navigation, and allow it to fail without panic
         {
@@ -215,7 +369,7 @@ impl WebClient {
                 || async {
                     utils::retry_async(
                         || async {
-                            page.goto(&requested_url)
+                            page.goto(requested_url)
                                 .await
                                 .map_err(|e| Box::new(e) as Box<dyn std::error::Error + Send + Sync>)
                         },
@@ -223,7 +377,7 @@ impl WebClient {
                         std::time::Duration::from_secs(3),
                     ).await
                 },
-                std::time::Duration::from_secs(10),
+                timeouts.navigation,
             ).await;
             match nav_result {
                 Ok(Ok(_)) => (),
@@ -243,37 +397,21 @@ impl WebClient {
                 },
                 std::time::Duration::from_secs(3),
             ).await;
-            match result {
-                Ok(Ok(_)) => {}
-                Ok(Err(error)) => {
-                    eprintln!("\t ⚠️ `wait_for_navigation` failed: {error} — falling back to JS polling.");
-                    loop {
-                        let ready = page.evaluate("document.readyState").await.unwrap();
-                        let state = ready.value().unwrap().as_str().unwrap_or("");
-                        if state == "complete" || state == "interactive" {
-                            break;
-                        }
-                        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
-                    }
-                }
-                Err(error) => {
-                    eprintln!("\t ⚠️ `wait_for_navigation` failed: {error} — falling back to JS polling.");
-                    loop {
-                        let ready = page.evaluate("document.readyState").await.unwrap();
-                        let state = ready.value().unwrap().as_str().unwrap_or("");
-                        if state == "complete" || state == "interactive" {
-                            break;
-                        }
-                        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
-                    }
-                }
+            let error = match result {
+                Ok(Ok(_)) => None,
+                Ok(Err(error)) => Some(error.to_string()),
+                Err(error) => Some(error.to_string()),
+            };
+            if let Some(error) = error {
+                eprintln!("\t ⚠️ `wait_for_navigation` failed: {error} — falling back to JS polling.");
+                Self::poll_until_ready(page, timeouts.ready).await?;
             }
         }
 
-        // Extract the HTTP status code for the main document
-        let mut status_code: Option<i64> = None;
+        // Extract the response of the main document
+        let mut response = None;
         {
-            let deadline = tokio::time::sleep(std::time::Duration::from_secs(5));
+            let deadline = tokio::time::sleep(timeouts.document_response);
             tokio::pin!(deadline);
 
             loop {
@@ -285,10 +423,10 @@ impl WebClient {
 
                         let frame_match = event.frame_id.as_ref() == Some(&main_frame_id);
                         let url_match = event.response.url == requested_url
-                            || event.response.url.starts_with(&requested_url);
+                            || event.response.url.starts_with(requested_url);
 
                         if frame_match || url_match {
-                            status_code = Some(event.response.status);
+                            response = Some(event);
                             break;
                         }
                     }
@@ -300,6 +438,41 @@ impl WebClient {
             }
         }
 
+        // Client-side redirects may have replaced the first document by now;
+        // the last one that arrived is what the tab shows.
+        while let Some(Some(event)) = responses.next().now_or_never() {
+            if event.r#type == ResourceType::Document && event.frame_id.as_ref() == Some(&main_frame_id) {
+                response = Some(event);
+            }
+        }
+        let mut load_event = None;
+        while let Some(Some(event)) = load_events.next().now_or_never() {
+            load_event = Some(*event.timestamp.inner());
+        }
+        let response = response
+            .map(|event| network::DocumentResponse::from_cdp(&event.response, load_event));
+
+        // Every document request of the main frame, in order
+        let redirect_chain = {
+            let mut document_requests = Vec::new();
+            while let Some(Some(event)) = requests.next().now_or_never() {
+                let is_document = event.r#type.as_ref() == Some(&ResourceType::Document);
+                if is_document && event.frame_id.as_ref() == Some(&main_frame_id) {
+                    document_requests.push(event);
+                }
+            }
+            let mut frame_navigations = Vec::new();
+            while let Some(Some(event)) = navigations.next().now_or_never() {
+                if event.frame_id == main_frame_id {
+                    frame_navigations.push(event);
+                }
+            }
+            network::redirect_chain(
+                &document_requests.iter().map(|event| event.as_ref()).collect::<Vec<_>>(),
+                &frame_navigations.iter().map(|event| event.as_ref()).collect::<Vec<_>>(),
+            )
+        };
+
         // Confirm where we landed
         {
             let actual_url = crate::utils::retry_async(
@@ -310,9 +483,12 @@ impl WebClient {
                 },
                 3,
                 std::time::Duration::from_secs(1),
-            ).await;
-            let actual_url = actual_url.unwrap();
-            let actual_url = actual_url.value().unwrap().as_str().unwrap_or("").to_string();
+            ).await?;
+            let actual_url = actual_url
+                .value()
+                .and_then(|x| x.as_str())
+                .unwrap_or("")
+                .to_string();
             if actual_url != requested_url {
                 eprintln!("{}", format!(
                     "\t ⓘ Redirected: {} => {}",
@@ -322,9 +498,23 @@ impl WebClient {
             }
         }
 
-        LiveWebpage {
-            page,
-            status_code,
+        Ok(( response, redirect_chain ))
+    }
+    /// Poll `document.readyState` until the page is interactive.
+    async fn poll_until_ready(page: &Page, limit: std::time::Duration) -> Result<(), ClientError> {
+        let started = tokio::time::Instant::now();
+        loop {
+            let ready = page.evaluate("document.readyState").await?;
+            let state = ready.value().and_then(|x| x.as_str()).unwrap_or("");
+            if state == "complete" || state == "interactive" {
+                return Ok(())
+            }
+            if started.elapsed() >= limit {
+                return Err(ClientError::Timeout(format!(
+                    "page not ready after {limit:?} (readyState: {state:?})"
+                )))
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
         }
     }
 }
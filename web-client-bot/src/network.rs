@@ -0,0 +1,179 @@
+use std::collections::{BTreeMap, HashMap};
+
+use chromiumoxide::cdp::browser_protocol::network::{EventRequestWillBeSent, Response, ResourceTiming};
+use chromiumoxide::cdp::browser_protocol::page::{ClientNavigationReason, EventFrameRequestedNavigation};
+use serde::{Deserialize, Serialize};
+
+// ————————————————————————————————————————————————————————————————————————————
+// DOCUMENT RESPONSE
+// ————————————————————————————————————————————————————————————————————————————
+
+/// The response of a page's main document, as seen by the browser.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DocumentResponse {
+    pub url: String,
+    pub status: i64,
+    #[serde(default)]
+    pub status_text: String,
+    pub mime_type: String,
+    /// E.g. `http/1.1`, `h2` or `h3`.
+    #[serde(default)]
+    pub protocol: Option<String>,
+    #[serde(default)]
+    pub remote_ip_address: Option<String>,
+    #[serde(default)]
+    pub remote_port: Option<i64>,
+    #[serde(default)]
+    pub timing: Option<ResponseTiming>,
+    /// Header names are lowercased. Repeated headers are joined with `\n`.
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+}
+
+/// Phases of the main document request, in milliseconds. A phase is `None`
+/// when it did not happen, e.g. DNS and connect on a reused connection.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct ResponseTiming {
+    #[serde(default)]
+    pub dns_ms: Option<f64>,
+    /// TCP connect, including the TLS handshake.
+    #[serde(default)]
+    pub connect_ms: Option<f64>,
+    #[serde(default)]
+    pub tls_ms: Option<f64>,
+    /// From sending the request to the first byte of the response headers.
+    #[serde(default)]
+    pub ttfb_ms: Option<f64>,
+    /// From the start of the request to the `load` event.
+    #[serde(default)]
+    pub load_ms: Option<f64>,
+}
+
+impl DocumentResponse {
+    pub(crate) fn from_cdp(response: &Response, load_event: Option<f64>) -> Self {
+        let headers = response.headers
+            .inner()
+            .as_object()
+            .into_iter()
+            .flatten()
+            .map(|(name, value)| {
+                let value = match value {
+                    serde_json::Value::String(value) => value.clone(),
+                    value => value.to_string(),
+                };
+                (name.to_ascii_lowercase(), value)
+            })
+            .collect();
+        Self {
+            url: response.url.clone(),
+            status: response.status,
+            status_text: response.status_text.clone(),
+            mime_type: response.mime_type.clone(),
+            protocol: response.protocol.clone(),
+            remote_ip_address: response.remote_ip_address.clone(),
+            remote_port: response.remote_port,
+            timing: response.timing
+                .as_ref()
+                .map(|timing| ResponseTiming::from_cdp(timing, load_event)),
+            headers,
+        }
+    }
+}
+
+impl ResponseTiming {
+    /// CDP reports phases as offsets from `request_time`, with `-1` for
+    /// phases that did not happen. `load_event` is on the same monotonic
+    /// clock as `request_time`, in seconds.
+    fn from_cdp(timing: &ResourceTiming, load_event: Option<f64>) -> Self {
+        let span = |start: f64, end: f64| (start >= 0.0 && end >= start).then_some(end - start);
+        let first_byte = match timing.receive_headers_start {
+            start if start >= 0.0 => start,
+            _ => timing.receive_headers_end,
+        };
+        Self {
+            dns_ms: span(timing.dns_start, timing.dns_end),
+            connect_ms: span(timing.connect_start, timing.connect_end),
+            tls_ms: span(timing.ssl_start, timing.ssl_end),
+            ttfb_ms: span(timing.send_start, first_byte),
+            load_ms: load_event
+                .map(|load_event| (load_event - timing.request_time) * 1000.0)
+                .filter(|load_ms| *load_ms >= 0.0),
+        }
+    }
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// REDIRECTS
+// ————————————————————————————————————————————————————————————————————————————
+
+/// One step from `from` to `to` on the way to the final document.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct RedirectHop {
+    pub from: String,
+    pub to: String,
+    /// Status of the redirect response; `None` for client-side redirects.
+    #[serde(default)]
+    pub status: Option<i64>,
+    pub kind: RedirectKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RedirectKind {
+    /// A 3xx response with a `Location` header.
+    Http,
+    /// A `Refresh` response header.
+    HeaderRefresh,
+    /// `<meta http-equiv="refresh">`.
+    MetaRefresh,
+    /// `window.location` and friends.
+    Script,
+    /// Any other navigation of the main frame, e.g. a form submission.
+    Other,
+}
+
+impl From<&ClientNavigationReason> for RedirectKind {
+    fn from(reason: &ClientNavigationReason) -> Self {
+        match reason {
+            ClientNavigationReason::HttpHeaderRefresh => Self::HeaderRefresh,
+            ClientNavigationReason::MetaTagRefresh => Self::MetaRefresh,
+            ClientNavigationReason::ScriptInitiated => Self::Script,
+            _ => Self::Other,
+        }
+    }
+}
+
+/// The hops between the main-frame document requests in `requests`, in the
+/// order they were sent. Client-side hops are classified by the matching
+/// `frameRequestedNavigation` event.
+pub(crate) fn redirect_chain(
+    requests: &[&EventRequestWillBeSent],
+    navigations: &[&EventFrameRequestedNavigation],
+) -> Vec<RedirectHop> {
+    let reasons = navigations
+        .iter()
+        .map(|navigation| (navigation.url.as_str(), RedirectKind::from(&navigation.reason)))
+        .collect::<HashMap<_, _>>();
+    let mut chain = Vec::new();
+    let mut previous: Option<&str> = None;
+    for request in requests {
+        let to = request.request.url.as_str();
+        match (request.redirect_response.as_ref(), previous) {
+            (Some(redirect), _) => chain.push(RedirectHop {
+                from: redirect.url.clone(),
+                to: to.to_string(),
+                status: Some(redirect.status),
+                kind: RedirectKind::Http,
+            }),
+            (None, Some(from)) if from != to => chain.push(RedirectHop {
+                from: from.to_string(),
+                to: to.to_string(),
+                status: None,
+                kind: reasons.get(to).copied().unwrap_or(RedirectKind::Other),
+            }),
+            (None, _) => (),
+        }
+        previous = Some(to);
+    }
+    chain
+}
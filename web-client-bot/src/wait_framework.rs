@@ -28,6 +28,47 @@ impl Default for WaitOptions {
     }
 }
 
+// ======================= WaitStrategy =========================
+/// What to wait for once a page navigated, before reading it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WaitStrategy {
+    /// Only the navigation itself.
+    Load,
+    /// `document.readyState === 'complete'`.
+    DomReady,
+    /// DOM ready, and no resource finished within the quiet window.
+    NetworkIdle,
+    /// DOM ready, no fetch/XHR in flight and network idle.
+    #[default]
+    FullySettled,
+}
+
+/// See [`LiveWebpage::wait_with`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WaitSettings {
+    pub strategy: WaitStrategy,
+    /// For the strategy's condition to hold.
+    pub timeout: Duration,
+    /// How long no resource may finish for the network to count as idle.
+    pub quiet_window: Duration,
+    /// For the network to go idle; `None` allows all of `timeout`.
+    pub network_idle_timeout: Option<Duration>,
+    /// Extra delay once the condition holds, to let rendering settle.
+    pub settle_delay: Duration,
+}
+
+impl Default for WaitSettings {
+    fn default() -> Self {
+        Self {
+            strategy: WaitStrategy::default(),
+            timeout: Duration::from_secs(5),
+            quiet_window: Duration::from_millis(500),
+            network_idle_timeout: None,
+            settle_delay: Duration::from_secs(1),
+        }
+    }
+}
+
 // ======================= WaitCondition Trait ==================
 #[async_trait]
 pub trait WaitCondition: Send + Sync {
@@ -367,27 +408,46 @@ impl LiveWebpage {
     /// - Waits for network resources to finish
     /// - Sleeps a bit more to let rendering settle
     pub async fn wait_until_fully_settled(&self) -> Result<(), WaitError> {
-        self.wait_for_navigation().await;
-
-        let condition = All(vec![
-            Box::new(DomReady),
-            Box::new(FetchIdle),
-            Box::new(NetworkIdle {
-                quiet_window: Duration::from_millis(500),
-                total_timeout: Duration::from_secs(15),
-                poll_interval: Duration::from_millis(200),
-            }),
-        ]);
-
-        let options = WaitOptions {
+        let settings = WaitSettings {
+            strategy: WaitStrategy::FullySettled,
             timeout: Duration::from_secs(20),
-            interval: Duration::from_millis(200),
+            network_idle_timeout: Some(Duration::from_secs(15)),
+            ..WaitSettings::default()
         };
+        self.wait_with(&settings).await
+    }
 
-        let () = self.wait_until(condition, options).await?;
+    /// Wait for whatever `settings.strategy` asks for, then sleep for
+    /// `settings.settle_delay`.
+    pub async fn wait_with(&self, settings: &WaitSettings) -> Result<(), WaitError> {
+        self.wait_for_navigation()
+            .await
+            .map_err(|error| WaitError::Other(Box::new(error)))?;
+
+        let network_idle = || NetworkIdle {
+            quiet_window: settings.quiet_window,
+            total_timeout: settings.network_idle_timeout.unwrap_or(settings.timeout),
+            poll_interval: Duration::from_millis(200),
+        };
+        let condition: Option<Vec<Box<dyn WaitCondition>>> = match settings.strategy {
+            WaitStrategy::Load => None,
+            WaitStrategy::DomReady => Some(vec![Box::new(DomReady)]),
+            WaitStrategy::NetworkIdle => Some(vec![Box::new(DomReady), Box::new(network_idle())]),
+            WaitStrategy::FullySettled => Some(vec![Box::new(DomReady), Box::new(FetchIdle), Box::new(network_idle())]),
+        };
+
+        if let Some(condition) = condition {
+            let options = WaitOptions {
+                timeout: settings.timeout,
+                interval: Duration::from_millis(200),
+            };
+            let () = self.wait_until(All(condition), options).await?;
+        }
 
         // Optional: delay after all activity has settled
-        tokio::time::sleep(Duration::from_secs(1)).await;
+        if !settings.settle_delay.is_zero() {
+            tokio::time::sleep(settings.settle_delay).await;
+        }
 
         Ok(())
     }
@@ -0,0 +1,15 @@
+pub use web_client_bot::{HeadlessMode, NavigationTimeouts, WebClientSettings};
+pub use web_client_bot::wait_framework::{WaitSettings, WaitStrategy};
+
+// ————————————————————————————————————————————————————————————————————————————
+// SETTINGS
+// ————————————————————————————————————————————————————————————————————————————
+
+/// How pages are loaded: the browser that is launched, how long a navigation
+/// may take and what to wait for before a page is read.
+#[derive(Debug, Clone, Default)]
+pub struct BrowserSettings {
+    pub client: WebClientSettings,
+    pub timeouts: NavigationTimeouts,
+    pub wait: WaitSettings,
+}
@@ -0,0 +1,96 @@
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+
+/// Per-project limits on how much a single crawl run may do.
+///
+/// Every limit is optional; `CrawlBudget::default()` is unlimited.
+#[derive(Debug, Clone, Default)]
+pub struct CrawlBudget {
+    /// Maximum number of links followed away from a seed (seeds are depth 0,
+    /// sitemap entries depth 1).
+    pub max_depth: Option<usize>,
+    /// Maximum number of pages visited in this run.
+    pub max_pages: Option<usize>,
+    /// Maximum number of pages visited per host in this run.
+    pub max_pages_per_host: Option<usize>,
+    /// Wall-clock limit for this run.
+    pub max_duration: Option<Duration>,
+}
+
+impl CrawlBudget {
+    pub fn exceeds_depth(&self, depth: usize) -> bool {
+        self.max_depth.is_some_and(|max_depth| depth > max_depth)
+    }
+    /// Whether a host that already had `host_pages` visited may not get
+    /// another one.
+    pub fn exhausts_host(&self, host_pages: usize) -> bool {
+        self.max_pages_per_host.is_some_and(|max_pages| host_pages >= max_pages)
+    }
+    /// The run-wide limit reached after visiting `pages_visited` pages in
+    /// `elapsed`, if any.
+    pub fn stop_reason(&self, pages_visited: usize, elapsed: Duration) -> Option<StopReason> {
+        if self.max_pages.is_some_and(|max_pages| pages_visited >= max_pages) {
+            return Some(StopReason::MaxPages)
+        }
+        if self.max_duration.is_some_and(|max_duration| elapsed >= max_duration) {
+            return Some(StopReason::MaxDuration)
+        }
+        None
+    }
+}
+
+/// Why a crawl run ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum StopReason {
+    /// Nothing left to visit.
+    QueueDrained,
+    /// `CrawlBudget::max_pages` was reached.
+    MaxPages,
+    /// `CrawlBudget::max_duration` elapsed.
+    MaxDuration,
+    /// The run was stopped with Ctrl+C.
+    Interrupted,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_default_budget_is_unlimited() {
+        let budget = CrawlBudget::default();
+        assert!(!budget.exceeds_depth(usize::MAX));
+        assert!(!budget.exhausts_host(usize::MAX));
+        assert_eq!(budget.stop_reason(usize::MAX, Duration::MAX), None);
+    }
+
+    #[test]
+    fn depth_is_limited_inclusively() {
+        let budget = CrawlBudget { max_depth: Some(2), ..Default::default() };
+        assert!(!budget.exceeds_depth(0));
+        assert!(!budget.exceeds_depth(2));
+        assert!(budget.exceeds_depth(3));
+    }
+
+    #[test]
+    fn page_limits_stop_once_reached() {
+        let budget = CrawlBudget { max_pages: Some(10), max_pages_per_host: Some(3), ..Default::default() };
+        assert_eq!(budget.stop_reason(9, Duration::ZERO), None);
+        assert_eq!(budget.stop_reason(10, Duration::ZERO), Some(StopReason::MaxPages));
+        assert!(!budget.exhausts_host(2));
+        assert!(budget.exhausts_host(3));
+    }
+
+    #[test]
+    fn the_page_limit_is_reported_before_the_time_limit() {
+        let budget = CrawlBudget {
+            max_pages: Some(10),
+            max_duration: Some(Duration::from_secs(60)),
+            ..Default::default()
+        };
+        assert_eq!(budget.stop_reason(0, Duration::from_secs(59)), None);
+        assert_eq!(budget.stop_reason(0, Duration::from_secs(60)), Some(StopReason::MaxDuration));
+        assert_eq!(budget.stop_reason(10, Duration::from_secs(60)), Some(StopReason::MaxPages));
+    }
+}
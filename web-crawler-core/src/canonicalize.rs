@@ -0,0 +1,373 @@
+use std::str::FromStr;
+use url::Url;
+
+use crate::metadata::common::CanonicalUrl;
+
+// ————————————————————————————————————————————————————————————————————————————
+// RULES
+// ————————————————————————————————————————————————————————————————————————————
+
+/// A single URL rewrite applied while canonicalizing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NormalizationRule {
+    /// `https://a.com/x#top` → `https://a.com/x`
+    StripFragment,
+    /// `https://A.com/` → `https://a.com/`
+    LowercaseHost,
+    /// `https://a.com:443/` → `https://a.com/`
+    RemoveDefaultPort,
+    /// `https://www.a.com/` → `https://a.com/`
+    StripWww,
+    /// `https://a.com/x/` → `https://a.com/x` (the root path is kept)
+    StripTrailingSlash,
+    /// `https://a.com/x/index.html` → `https://a.com/x/`
+    StripIndexFile(Vec<String>),
+    /// Remove query parameters whose name matches one of the patterns.
+    /// Patterns may contain `*` wildcards, e.g. `utm_*`. The remaining
+    /// parameters are kept byte for byte.
+    DropQueryParams(Vec<String>),
+    /// `?b=2&a=1` → `?a=1&b=2`
+    ///
+    /// Not part of [`UrlNormalizer::standard`]: some servers read parameters
+    /// in order, so a sorted query may name a different resource.
+    SortQuery,
+    /// `https://a.com/x?` → `https://a.com/x`
+    RemoveEmptyQuery,
+}
+
+impl NormalizationRule {
+    pub fn default_index_files() -> Vec<String> {
+        ["index.html", "index.htm", "index.php", "default.aspx", "default.asp"]
+            .into_iter()
+            .map(String::from)
+            .collect()
+    }
+    pub fn default_tracking_params() -> Vec<String> {
+        ["utm_*", "gclid", "dclid", "fbclid", "msclkid", "mc_cid", "mc_eid", "_ga", "_gl"]
+            .into_iter()
+            .map(String::from)
+            .collect()
+    }
+    pub fn apply(&self, url: &mut Url) {
+        match self {
+            Self::StripFragment => {
+                url.set_fragment(None);
+            }
+            Self::LowercaseHost => {
+                if let Some(host) = url.host_str() {
+                    let lowercase = host.to_ascii_lowercase();
+                    if lowercase != host {
+                        let _ = url.set_host(Some(&lowercase));
+                    }
+                }
+            }
+            Self::RemoveDefaultPort => {
+                if url.port().is_some() && url.port() == default_port_for_scheme(url.scheme()) {
+                    let _ = url.set_port(None);
+                }
+            }
+            Self::StripWww => {
+                if let Some(host) = url.domain().and_then(|x| x.strip_prefix("www.")).map(String::from) {
+                    let _ = url.set_host(Some(&host));
+                }
+            }
+            Self::StripTrailingSlash => {
+                let path = url.path();
+                if path.len() > 1 && path.ends_with('/') {
+                    let trimmed = path.trim_end_matches('/').to_string();
+                    url.set_path(if trimmed.is_empty() { "/" } else { &trimmed });
+                }
+            }
+            Self::StripIndexFile(file_names) => {
+                let path = url.path().to_string();
+                let (parent, last) = path.rsplit_once('/').unwrap_or(("", path.as_str()));
+                if file_names.iter().any(|name| name.eq_ignore_ascii_case(last)) {
+                    url.set_path(&format!("{parent}/"));
+                }
+            }
+            Self::DropQueryParams(patterns) => {
+                let Some(query) = url.query().map(String::from) else {
+                    return
+                };
+                let pairs = query.split('&').collect::<Vec<_>>();
+                let kept = pairs
+                    .iter()
+                    .copied()
+                    .filter(|pair| {
+                        let name = query_pair_name(pair);
+                        !patterns.iter().any(|pattern| wildcard_match(pattern, &name))
+                    })
+                    .collect::<Vec<_>>();
+                if kept.len() < pairs.len() {
+                    set_raw_query_pairs(url, &kept);
+                }
+            }
+            Self::SortQuery => {
+                let Some(query) = url.query().map(String::from) else {
+                    return
+                };
+                let pairs = query.split('&').collect::<Vec<_>>();
+                let mut sorted = pairs.clone();
+                sorted.sort_by_cached_key(|pair| (query_pair_name(pair), *pair));
+                if sorted != pairs {
+                    set_raw_query_pairs(url, &sorted);
+                }
+            }
+            Self::RemoveEmptyQuery => {
+                if url.query() == Some("") {
+                    url.set_query(None);
+                }
+            }
+        }
+    }
+}
+
+impl FromStr for NormalizationRule {
+    type Err = String;
+    /// Parse the manifest name of a rule, e.g. `strip-www`.
+    ///
+    /// `drop-tracking-params` and `strip-index-file` use the built-in lists;
+    /// custom query parameter patterns are configured separately.
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        match source {
+            "strip-fragment" => Ok(Self::StripFragment),
+            "lowercase-host" => Ok(Self::LowercaseHost),
+            "remove-default-port" => Ok(Self::RemoveDefaultPort),
+            "strip-www" => Ok(Self::StripWww),
+            "strip-trailing-slash" => Ok(Self::StripTrailingSlash),
+            "strip-index-file" => Ok(Self::StripIndexFile(Self::default_index_files())),
+            "drop-tracking-params" => Ok(Self::DropQueryParams(Self::default_tracking_params())),
+            "sort-query" => Ok(Self::SortQuery),
+            "remove-empty-query" => Ok(Self::RemoveEmptyQuery),
+            _ => Err(format!("unknown URL normalization rule {source:?}")),
+        }
+    }
+}
+
+fn default_port_for_scheme(scheme: &str) -> Option<u16> {
+    match scheme {
+        "http" | "ws" => Some(80),
+        "https" | "wss" => Some(443),
+        "ftp" => Some(21),
+        _ => None,
+    }
+}
+
+/// The decoded name of a raw `name=value` query pair.
+fn query_pair_name(pair: &str) -> String {
+    let name = pair.split_once('=').map_or(pair, |(name, _)| name);
+    url::form_urlencoded::parse(name.as_bytes())
+        .next()
+        .map(|(name, _)| name.into_owned())
+        .unwrap_or_default()
+}
+
+/// Set the query to `pairs` as they are, without re-encoding them.
+fn set_raw_query_pairs(url: &mut Url, pairs: &[&str]) {
+    match pairs {
+        [] => url.set_query(None),
+        pairs => url.set_query(Some(&pairs.join("&"))),
+    }
+}
+
+/// Full match of `text` against `pattern`, where `*` matches any run of
+/// characters.
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let parts = pattern.split('*').collect::<Vec<_>>();
+    if parts.len() == 1 {
+        return pattern == text
+    }
+    let Some(mut rest) = text.strip_prefix(parts[0]) else {
+        return false
+    };
+    let last_index = parts.len() - 1;
+    for part in &parts[1..last_index] {
+        match rest.find(part) {
+            Some(position) => rest = &rest[position + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(parts[last_index])
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// NORMALIZER
+// ————————————————————————————————————————————————————————————————————————————
+
+/// An ordered list of [`NormalizationRule`]s turning any URL into its
+/// [`CanonicalUrl`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UrlNormalizer {
+    pub rules: Vec<NormalizationRule>,
+}
+
+impl Default for UrlNormalizer {
+    fn default() -> Self {
+        Self::standard()
+    }
+}
+
+impl UrlNormalizer {
+    /// Rules that never change which resource a URL points to.
+    pub fn standard() -> Self {
+        Self {
+            rules: vec![
+                NormalizationRule::StripFragment,
+                NormalizationRule::LowercaseHost,
+                NormalizationRule::RemoveDefaultPort,
+                NormalizationRule::DropQueryParams(NormalizationRule::default_tracking_params()),
+                NormalizationRule::RemoveEmptyQuery,
+            ],
+        }
+    }
+    /// Only strip the fragment, which is how canonical URLs used to be built.
+    pub fn fragment_only() -> Self {
+        Self { rules: vec![NormalizationRule::StripFragment] }
+    }
+    pub fn with_rule(mut self, rule: NormalizationRule) -> Self {
+        if !self.rules.contains(&rule) {
+            self.rules.push(rule);
+        }
+        self
+    }
+    pub fn normalize(&self, url: &Url) -> Url {
+        let mut url = url.clone();
+        for rule in self.rules.iter() {
+            rule.apply(&mut url);
+        }
+        url
+    }
+    pub fn canonicalize(&self, url: &Url) -> CanonicalUrl {
+        CanonicalUrl(self.normalize(url))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(rule: NormalizationRule, source: &str) -> String {
+        let mut url = Url::parse(source).unwrap();
+        rule.apply(&mut url);
+        url.to_string()
+    }
+
+    fn tracking_params() -> NormalizationRule {
+        NormalizationRule::DropQueryParams(NormalizationRule::default_tracking_params())
+    }
+
+    #[test]
+    fn strip_fragment() {
+        assert_eq!(apply(NormalizationRule::StripFragment, "https://a.com/x#top"), "https://a.com/x");
+        assert_eq!(apply(NormalizationRule::StripFragment, "https://a.com/x?q=1#"), "https://a.com/x?q=1");
+    }
+
+    #[test]
+    fn lowercase_host() {
+        // The URL parser lowercases special schemes already; other schemes
+        // keep their host as written.
+        assert_eq!(apply(NormalizationRule::LowercaseHost, "https://A.com/X"), "https://a.com/X");
+        assert_eq!(apply(NormalizationRule::LowercaseHost, "foo://A.com/X"), "foo://a.com/X");
+    }
+
+    #[test]
+    fn remove_default_port() {
+        assert_eq!(apply(NormalizationRule::RemoveDefaultPort, "ftp://a.com:21/"), "ftp://a.com/");
+        assert_eq!(apply(NormalizationRule::RemoveDefaultPort, "https://a.com:8443/"), "https://a.com:8443/");
+        assert_eq!(apply(NormalizationRule::RemoveDefaultPort, "foo://a.com:80/"), "foo://a.com:80/");
+    }
+
+    #[test]
+    fn strip_www() {
+        assert_eq!(apply(NormalizationRule::StripWww, "https://www.a.com/x"), "https://a.com/x");
+        assert_eq!(apply(NormalizationRule::StripWww, "https://www2.a.com/x"), "https://www2.a.com/x");
+        assert_eq!(apply(NormalizationRule::StripWww, "http://127.0.0.1/"), "http://127.0.0.1/");
+    }
+
+    #[test]
+    fn strip_trailing_slash() {
+        assert_eq!(apply(NormalizationRule::StripTrailingSlash, "https://a.com/x/"), "https://a.com/x");
+        assert_eq!(apply(NormalizationRule::StripTrailingSlash, "https://a.com/x//?q=1"), "https://a.com/x?q=1");
+        assert_eq!(apply(NormalizationRule::StripTrailingSlash, "https://a.com/"), "https://a.com/");
+    }
+
+    #[test]
+    fn strip_index_file() {
+        let rule = || NormalizationRule::StripIndexFile(NormalizationRule::default_index_files());
+        assert_eq!(apply(rule(), "https://a.com/x/index.html"), "https://a.com/x/");
+        assert_eq!(apply(rule(), "https://a.com/Default.ASPX?id=2"), "https://a.com/?id=2");
+        assert_eq!(apply(rule(), "https://a.com/x/index.html.bak"), "https://a.com/x/index.html.bak");
+    }
+
+    #[test]
+    fn drop_query_params() {
+        assert_eq!(
+            apply(tracking_params(), "https://a.com/x?utm_source=mail&id=7&gclid=abc"),
+            "https://a.com/x?id=7",
+        );
+        assert_eq!(apply(tracking_params(), "https://a.com/x?utm_source=mail"), "https://a.com/x");
+        // Names are matched decoded.
+        assert_eq!(apply(tracking_params(), "https://a.com/x?utm%5Fmedium=1&b=2"), "https://a.com/x?b=2");
+        let custom = NormalizationRule::DropQueryParams(vec![String::from("session*id")]);
+        assert_eq!(apply(custom, "https://a.com/?sessionid=1&session_x_id=2&sid=3"), "https://a.com/?sid=3");
+    }
+
+    #[test]
+    fn drop_query_params_keeps_the_remaining_encoding() {
+        assert_eq!(
+            apply(tracking_params(), "https://a.com/x?path=%2Fa%2Fb&q=a+b&page&fbclid=1"),
+            "https://a.com/x?path=%2Fa%2Fb&q=a+b&page",
+        );
+        // Nothing to drop, nothing rewritten.
+        assert_eq!(
+            apply(tracking_params(), "https://a.com/x?page&q=a+b&&path=%2F"),
+            "https://a.com/x?page&q=a+b&&path=%2F",
+        );
+    }
+
+    #[test]
+    fn sort_query() {
+        assert_eq!(apply(NormalizationRule::SortQuery, "https://a.com/?b=2&a=1"), "https://a.com/?a=1&b=2");
+        assert_eq!(
+            apply(NormalizationRule::SortQuery, "https://a.com/?q=a+b&page&a=%2F"),
+            "https://a.com/?a=%2F&page&q=a+b",
+        );
+        // Repeated names are ordered by their raw pair.
+        assert_eq!(apply(NormalizationRule::SortQuery, "https://a.com/?a=2&a=1"), "https://a.com/?a=1&a=2");
+    }
+
+    #[test]
+    fn remove_empty_query() {
+        assert_eq!(apply(NormalizationRule::RemoveEmptyQuery, "https://a.com/x?"), "https://a.com/x");
+        assert_eq!(apply(NormalizationRule::RemoveEmptyQuery, "https://a.com/x?page"), "https://a.com/x?page");
+    }
+
+    #[test]
+    fn standard_rules_keep_the_query_as_written() {
+        let normalizer = UrlNormalizer::standard();
+        let normalize = |source: &str| normalizer.normalize(&Url::parse(source).unwrap()).to_string();
+        assert_eq!(normalize("https://A.com:443/x?b=2&a=1#top"), "https://a.com/x?b=2&a=1");
+        assert_eq!(normalize("https://a.com/x?page"), "https://a.com/x?page");
+        assert_eq!(normalize("https://a.com/x?path=%2F&q=a+b&utm_source=x"), "https://a.com/x?path=%2F&q=a+b");
+        assert_eq!(normalize("https://a.com/x?utm_source=x"), "https://a.com/x");
+    }
+
+    #[test]
+    fn rules_parse_from_manifest_names() {
+        assert_eq!("strip-www".parse::<NormalizationRule>(), Ok(NormalizationRule::StripWww));
+        assert_eq!("sort-query".parse::<NormalizationRule>(), Ok(NormalizationRule::SortQuery));
+        assert_eq!("drop-tracking-params".parse::<NormalizationRule>(), Ok(tracking_params()));
+        assert!("strip_www".parse::<NormalizationRule>().is_err());
+    }
+
+    #[test]
+    fn wildcards() {
+        assert!(wildcard_match("utm_*", "utm_source"));
+        assert!(wildcard_match("utm_*", "utm_"));
+        assert!(!wildcard_match("utm_*", "xutm_source"));
+        assert!(wildcard_match("*id", "gclid"));
+        assert!(wildcard_match("a*b*c", "aXbYc"));
+        assert!(!wildcard_match("a*b*c", "aXcYb"));
+        assert!(!wildcard_match("gclid", "gclid2"));
+    }
+}
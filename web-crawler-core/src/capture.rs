@@ -0,0 +1,134 @@
+use std::path::Path;
+
+use colored::Colorize;
+pub use web_client_bot::capture::{PdfOptions, ScreenshotFormat, ScreenshotOptions};
+pub use web_client_bot::har::{HarBodies, HarOptions};
+
+use crate::metadata::common::RelativeFilePath;
+
+// ————————————————————————————————————————————————————————————————————————————
+// SETTINGS
+// ————————————————————————————————————————————————————————————————————————————
+
+/// Renders and recordings stored next to each HTML snapshot. All are off by
+/// default.
+#[derive(Debug, Clone, Default)]
+pub struct CaptureSettings {
+    pub screenshot: Option<ScreenshotOptions>,
+    /// Needs a headless browser.
+    pub pdf: Option<PdfOptions>,
+    /// Every request the page made, as `page.har`.
+    pub har: Option<HarOptions>,
+}
+
+impl CaptureSettings {
+    pub fn is_enabled(&self) -> bool {
+        self.screenshot.is_some() || self.pdf.is_some() || self.har.is_some()
+    }
+    /// What the tab has to record from before its navigation.
+    pub fn tab_options(&self) -> web_client_bot::TabOptions {
+        web_client_bot::TabOptions {
+            har: self.har.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// CAPTURE
+// ————————————————————————————————————————————————————————————————————————————
+
+/// Encoded renders of a page, taken before its snapshot is written.
+#[derive(Debug, Default)]
+pub struct PageCaptures {
+    pub screenshot: Option<Vec<u8>>,
+    pub pdf: Option<Vec<u8>>,
+    /// HAR 1.2 as JSON.
+    pub har: Option<Vec<u8>>,
+}
+
+/// Where the captures of a snapshot were written.
+#[derive(Debug, Clone, Default)]
+pub struct CapturePaths {
+    pub screenshot: Option<RelativeFilePath>,
+    pub pdf: Option<RelativeFilePath>,
+    pub har: Option<RelativeFilePath>,
+}
+
+/// Render the page loaded in `tab`. A render that fails is left out with a
+/// warning; it never fails the snapshot.
+pub async fn capture_page(
+    tab: &web_client_bot::LiveWebpage,
+    settings: &CaptureSettings,
+) -> PageCaptures {
+    let mut captures = PageCaptures::default();
+    if let Some(options) = settings.screenshot.as_ref() {
+        match tab.screenshot(options).await {
+            Ok(bytes) => captures.screenshot = Some(bytes),
+            Err(error) => {
+                eprintln!("{}", format!("\t ⚠️ Failed to take a screenshot: {error}").yellow());
+            }
+        }
+    }
+    if let Some(options) = settings.pdf.as_ref() {
+        match tab.print_to_pdf(options).await {
+            Ok(bytes) => captures.pdf = Some(bytes),
+            Err(error) => {
+                eprintln!("{}", format!("\t ⚠️ Failed to print to PDF: {error}").yellow());
+            }
+        }
+    }
+    if let Some(har) = tab.har().await {
+        match serde_json::to_vec_pretty(&har) {
+            Ok(bytes) => captures.har = Some(bytes),
+            Err(error) => {
+                eprintln!("{}", format!("\t ⚠️ Failed to encode the HAR: {error}").yellow());
+            }
+        }
+    }
+    captures
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// FILES
+// ————————————————————————————————————————————————————————————————————————————
+
+/// `source.screenshot.<ext>` next to the HTML snapshot.
+pub fn screenshot_path(rel_html_path: &RelativeFilePath, options: &ScreenshotOptions) -> RelativeFilePath {
+    let file_name = format!("source.screenshot.{}", options.format.extension());
+    RelativeFilePath(rel_html_path.0.with_file_name(file_name))
+}
+
+/// `source.print.pdf` next to the HTML snapshot.
+pub fn pdf_path(rel_html_path: &RelativeFilePath) -> RelativeFilePath {
+    RelativeFilePath(rel_html_path.0.with_file_name("source.print.pdf"))
+}
+
+/// `page.har` next to the HTML snapshot.
+pub fn har_path(rel_html_path: &RelativeFilePath) -> RelativeFilePath {
+    RelativeFilePath(rel_html_path.0.with_file_name("page.har"))
+}
+
+/// Write the captures below `project_directory`, overwriting those of
+/// earlier snapshots, and return their paths.
+pub fn write_captures(
+    project_directory: &Path,
+    rel_html_path: &RelativeFilePath,
+    settings: &CaptureSettings,
+    captures: &PageCaptures,
+) -> std::io::Result<CapturePaths> {
+    let write = |path: RelativeFilePath, bytes: &[u8]| {
+        std::fs::write(project_directory.join(&path.0), bytes).map(|_| path)
+    };
+    let mut paths = CapturePaths::default();
+    if let (Some(options), Some(bytes)) = (settings.screenshot.as_ref(), captures.screenshot.as_ref()) {
+        paths.screenshot = Some(write(screenshot_path(rel_html_path, options), bytes)?);
+    }
+    if let Some(bytes) = captures.pdf.as_ref() {
+        paths.pdf = Some(write(pdf_path(rel_html_path), bytes)?);
+    }
+    if let Some(bytes) = captures.har.as_ref() {
+        paths.har = Some(write(har_path(rel_html_path), bytes)?);
+    }
+    Ok(paths)
+}
@@ -70,7 +70,9 @@ impl SnapshotManifest {
     pub fn write(&self, file_path: impl AsRef<Path>) -> Result<(), Box<dyn std::error::Error>> {
         let file_path = file_path.as_ref();
         let toml_str = toml::to_string_pretty(self)?;
-        std::fs::create_dir_all(file_path.parent().unwrap()).unwrap();
+        if let Some(parent) = file_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
         std::fs::write(file_path, toml_str)?;
         Ok(())
     }
@@ -0,0 +1,395 @@
+use std::collections::HashMap;
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::metadata::common::RelativeFilePath;
+use crate::metadata::snapshot::SnapshotLog;
+
+// ————————————————————————————————————————————————————————————————————————————
+// SETTINGS
+// ————————————————————————————————————————————————————————————————————————————
+
+/// Detecting pages whose text is (almost) the same as an existing snapshot,
+/// e.g. the same article behind session parameters, print views or sort
+/// orders.
+#[derive(Debug, Clone)]
+pub struct DedupSettings {
+    pub enabled: bool,
+    /// Largest number of differing SimHash bits (out of 64) that still counts
+    /// as a duplicate.
+    pub max_distance: u32,
+    /// Whether links found on a duplicate are followed. Its original's links
+    /// usually cover them.
+    pub expand_links: bool,
+}
+
+impl Default for DedupSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_distance: 3,
+            expand_links: true,
+        }
+    }
+}
+
+impl DedupSettings {
+    /// Pages with less text than this are never matched; their fingerprints
+    /// are too noisy.
+    pub const MIN_WORDS: usize = 20;
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// FINGERPRINTS
+// ————————————————————————————————————————————————————————————————————————————
+
+static HIDDEN_ELEMENTS: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?is)<script\b.*?</script\s*>|<style\b.*?</style\s*>|<noscript\b.*?</noscript\s*>|<!--.*?-->").unwrap()
+});
+
+static TAGS_AND_ENTITIES: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?s)<[^>]*>|&#?[a-zA-Z0-9]+;").unwrap()
+});
+
+/// The words of the visible text of an HTML document, lowercased.
+pub fn visible_words(html: &str) -> Vec<String> {
+    let text = HIDDEN_ELEMENTS.replace_all(html, " ");
+    let text = TAGS_AND_ENTITIES.replace_all(&text, " ");
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_lowercase())
+        .collect()
+}
+
+/// 64-bit SimHash over word 3-shingles, or `None` if there are fewer than
+/// [`DedupSettings::MIN_WORDS`] words.
+pub fn simhash(words: &[String]) -> Option<u64> {
+    if words.len() < DedupSettings::MIN_WORDS {
+        return None
+    }
+    let mut counts = [0i64; 64];
+    for shingle in words.windows(3) {
+        let hash = feature_hash(&shingle.join(" "));
+        for (bit, count) in counts.iter_mut().enumerate() {
+            match hash >> bit & 1 {
+                1 => *count += 1,
+                _ => *count -= 1,
+            }
+        }
+    }
+    let fingerprint = counts
+        .iter()
+        .enumerate()
+        .filter(|(_, count)| **count > 0)
+        .fold(0u64, |fingerprint, (bit, _)| fingerprint | 1 << bit);
+    Some(fingerprint)
+}
+
+/// FNV-1a with a SplitMix64 finalizer, so that fingerprints are stable across
+/// builds and short shingles still spread over all bits.
+fn feature_hash(feature: &str) -> u64 {
+    let mut hash = 0xcbf29ce484222325u64;
+    for byte in feature.bytes() {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d049bb133111eb);
+    hash ^ (hash >> 31)
+}
+
+/// `simhash:<hex>`, as stored in `SnapshotLog::simhash`.
+pub fn format_simhash(simhash: u64) -> String {
+    format!("simhash:{simhash:016x}")
+}
+
+pub fn parse_simhash(source: &str) -> Option<u64> {
+    let hex = source.strip_prefix("simhash:")?;
+    u64::from_str_radix(hex, 16).ok()
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// MATCHING
+// ————————————————————————————————————————————————————————————————————————————
+
+/// SimHash fingerprints of the original (not duplicate) snapshots of a
+/// project, indexed for near-duplicate lookups.
+///
+/// The 64 bits are cut into `max_distance + 1` bands. Two fingerprints at
+/// most `max_distance` bits apart agree on at least one whole band, so only
+/// snapshots sharing a band with the query are compared.
+#[derive(Debug, Clone)]
+pub struct SimHashIndex {
+    max_distance: u32,
+    fingerprints: HashMap<RelativeFilePath, u64>,
+    /// Per band, the snapshots by the bits they have in that band. Empty
+    /// when every fingerprint is a candidate.
+    bands: Vec<HashMap<u64, Vec<RelativeFilePath>>>,
+}
+
+impl SimHashIndex {
+    pub fn new(max_distance: u32) -> Self {
+        // From 64 bits on, bands can't narrow anything down and every
+        // fingerprint is compared.
+        let band_count = match max_distance {
+            0..=63 => max_distance as usize + 1,
+            _ => 0,
+        };
+        Self {
+            max_distance,
+            fingerprints: HashMap::default(),
+            bands: vec![HashMap::default(); band_count],
+        }
+    }
+    /// Index every snapshot log with a fingerprint that is not a duplicate.
+    pub fn from_snapshot_logs<'a>(
+        max_distance: u32,
+        snapshot_logs: impl IntoIterator<Item=&'a SnapshotLog>,
+    ) -> Self {
+        let mut index = Self::new(max_distance);
+        for log in snapshot_logs {
+            if log.duplicate_of.is_some() {
+                continue
+            }
+            if let Some(simhash) = log.simhash.as_deref().and_then(parse_simhash) {
+                index.insert(log.snapshot_path.clone(), simhash);
+            }
+        }
+        index
+    }
+    pub fn len(&self) -> usize {
+        self.fingerprints.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.fingerprints.is_empty()
+    }
+    /// The indexed fingerprint of `snapshot_path`.
+    pub fn fingerprint(&self, snapshot_path: &RelativeFilePath) -> Option<u64> {
+        self.fingerprints.get(snapshot_path).copied()
+    }
+    /// Add or replace the fingerprint of `snapshot_path`.
+    pub fn insert(&mut self, snapshot_path: RelativeFilePath, simhash: u64) {
+        self.remove(&snapshot_path);
+        let band_count = self.bands.len();
+        for (band, keys) in self.bands.iter_mut().enumerate() {
+            let key = band_key(simhash, band, band_count);
+            keys.entry(key).or_default().push(snapshot_path.clone());
+        }
+        self.fingerprints.insert(snapshot_path, simhash);
+    }
+    pub fn remove(&mut self, snapshot_path: &RelativeFilePath) {
+        let Some(simhash) = self.fingerprints.remove(snapshot_path) else {
+            return
+        };
+        let band_count = self.bands.len();
+        for (band, keys) in self.bands.iter_mut().enumerate() {
+            let key = band_key(simhash, band, band_count);
+            if let Some(paths) = keys.get_mut(&key) {
+                paths.retain(|path| path != snapshot_path);
+                if paths.is_empty() {
+                    keys.remove(&key);
+                }
+            }
+        }
+    }
+    /// The closest snapshot within `max_distance` bits of `simhash`, other
+    /// than `own_snapshot_path`, with its distance.
+    pub fn find(&self, simhash: u64, own_snapshot_path: &RelativeFilePath) -> Option<(&RelativeFilePath, u32)> {
+        let band_count = self.bands.len();
+        let candidates: Box<dyn Iterator<Item=&RelativeFilePath>> = match band_count {
+            0 => Box::new(self.fingerprints.keys()),
+            _ => Box::new(
+                self.bands
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(band, keys)| keys.get(&band_key(simhash, band, band_count)))
+                    .flatten()
+            ),
+        };
+        candidates
+            .filter(|path| *path != own_snapshot_path)
+            .filter_map(|path| {
+                let distance = (simhash ^ self.fingerprints[path]).count_ones();
+                (distance <= self.max_distance).then_some((path, distance))
+            })
+            .min_by_key(|(_, distance)| *distance)
+    }
+    /// Look `simhash` up and, if it is not a near-duplicate, index it as the
+    /// fingerprint of `snapshot_path` in the same step, so that concurrent
+    /// pages see each other. A page found to be a duplicate is dropped from
+    /// the index.
+    pub fn find_or_insert(
+        &mut self,
+        simhash: u64,
+        snapshot_path: &RelativeFilePath,
+    ) -> Option<(RelativeFilePath, u32)> {
+        match self.find(simhash, snapshot_path) {
+            Some((original, distance)) => {
+                let original = original.clone();
+                self.remove(snapshot_path);
+                Some((original, distance))
+            }
+            None => {
+                self.insert(snapshot_path.clone(), simhash);
+                None
+            }
+        }
+    }
+}
+
+/// The bits of `simhash` in `band` out of `band_count` near-equal bands.
+fn band_key(simhash: u64, band: usize, band_count: usize) -> u64 {
+    let start = band * 64 / band_count;
+    let end = (band + 1) * 64 / band_count;
+    match end - start {
+        64 => simhash,
+        width => simhash >> start & ((1u64 << width) - 1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    const ARTICLE: &str = "The quick brown fox jumps over the lazy dog while the farmer watches from the porch \
+        and the cat sleeps in the warm afternoon sun near the old red barn by the river";
+
+    fn page(text: &str) -> String {
+        format!(
+            "<html><head><title>Fox</title><style>p {{ color: red }}</style></head>\
+            <body><script>var tracking = 1;</script><!-- nav --><p>{text}</p></body></html>"
+        )
+    }
+
+    fn path(source: &str) -> RelativeFilePath {
+        RelativeFilePath(PathBuf::from(source))
+    }
+
+    #[test]
+    fn visible_words_skip_markup_scripts_and_entities() {
+        let words = visible_words("<p class=\"x\">Hello&nbsp;<b>World</b>!</p><script>let hidden = 1;</script><!-- nope -->");
+        assert_eq!(words, ["hello", "world"]);
+        assert!(visible_words(&page(ARTICLE)).iter().all(|word| word != "tracking" && word != "color"));
+    }
+
+    #[test]
+    fn short_pages_have_no_fingerprint() {
+        assert_eq!(simhash(&visible_words("<p>Too few words to tell anything apart</p>")), None);
+        assert!(simhash(&visible_words(&page(ARTICLE))).is_some());
+    }
+
+    #[test]
+    fn similar_pages_have_close_fingerprints() {
+        let original = simhash(&visible_words(&page(ARTICLE))).unwrap();
+        // Same text, different markup.
+        let restyled = format!("<html><title>Fox</title><div><h1>{ARTICLE}</h1></div></html>");
+        assert_eq!(simhash(&visible_words(&restyled)), Some(original));
+        let edited = simhash(&visible_words(&page(&ARTICLE.replace("warm", "hot")))).unwrap();
+        let unrelated = simhash(&visible_words(&page(
+            "Quarterly revenue grew by twelve percent as the company expanded into new markets across \
+            Europe and Asia while reducing operating costs through automation and better logistics",
+        ))).unwrap();
+        assert!((original ^ edited).count_ones() < (original ^ unrelated).count_ones());
+        assert!((original ^ unrelated).count_ones() > 10);
+    }
+
+    #[test]
+    fn simhash_round_trips_through_the_log_format() {
+        assert_eq!(format_simhash(0x00ff_0000_0000_0001), "simhash:00ff000000000001");
+        assert_eq!(parse_simhash("simhash:00ff000000000001"), Some(0x00ff_0000_0000_0001));
+        assert_eq!(parse_simhash("00ff000000000001"), None);
+        assert_eq!(parse_simhash("simhash:xyz"), None);
+    }
+
+    #[test]
+    fn bands_cover_every_bit_once() {
+        for band_count in [1, 3, 4, 7, 64] {
+            let mut covered = 0u64;
+            for band in 0..band_count {
+                let start = band * 64 / band_count;
+                let bits = band_key(u64::MAX, band, band_count);
+                assert_eq!(covered & bits << start, 0);
+                covered |= bits << start;
+            }
+            assert_eq!(covered, u64::MAX);
+        }
+        assert_eq!(band_key(0xabcd_0000_0000_1234, 0, 4), 0x1234);
+        assert_eq!(band_key(0xabcd_0000_0000_1234, 3, 4), 0xabcd);
+    }
+
+    #[test]
+    fn index_finds_the_closest_fingerprint_within_the_distance() {
+        let mut index = SimHashIndex::new(3);
+        let base = 0x0123_4567_89ab_cdefu64;
+        // Differs in one bit of every 16-bit band, so no band matches
+        // exactly; at distance 4 it is out of reach anyway.
+        index.insert(path("far"), base ^ 0x0001_0001_0001_0001);
+        index.insert(path("near"), base ^ 0b11);
+        index.insert(path("nearest"), base ^ 0b1);
+        assert_eq!(index.len(), 3);
+        assert_eq!(index.find(base, &path("own")), Some((&path("nearest"), 1)));
+        assert_eq!(index.find(base ^ 0b1, &path("nearest")), Some((&path("near"), 1)));
+        assert_eq!(index.find(!base, &path("own")), None);
+        index.remove(&path("nearest"));
+        index.remove(&path("near"));
+        assert_eq!(index.find(base, &path("own")), None);
+    }
+
+    #[test]
+    fn index_matches_differences_spread_over_every_band() {
+        // Three differing bits in three of the four bands; the last band
+        // still matches exactly.
+        let mut index = SimHashIndex::new(3);
+        let base = 0xdead_beef_0bad_f00du64;
+        index.insert(path("original"), base);
+        let spread = base ^ (1 << 2 | 1 << 20 | 1 << 40);
+        assert_eq!(index.find(spread, &path("own")), Some((&path("original"), 3)));
+    }
+
+    #[test]
+    fn find_or_insert_indexes_originals_only() {
+        let mut index = SimHashIndex::new(3);
+        assert_eq!(index.find_or_insert(0xff00, &path("a")), None);
+        assert_eq!(index.find_or_insert(0xff01, &path("b")), Some((path("a"), 1)));
+        assert_eq!(index.len(), 1);
+        assert_eq!(index.fingerprint(&path("a")), Some(0xff00));
+        assert_eq!(index.fingerprint(&path("b")), None);
+        // A page re-fingerprinted as a duplicate leaves the index.
+        assert_eq!(index.find_or_insert(0xff00_0000, &path("c")), None);
+        assert_eq!(index.find_or_insert(0xff00_0001, &path("c")), None);
+        assert_eq!(index.find_or_insert(0xff03, &path("c")), Some((path("a"), 2)));
+        assert_eq!(index.len(), 1);
+    }
+
+    #[test]
+    fn index_loads_originals_from_snapshot_logs() {
+        let log = |snapshot_path: &str, simhash: Option<u64>, duplicate_of: Option<&str>| SnapshotLog {
+            simhash: simhash.map(format_simhash),
+            duplicate_of: duplicate_of.map(path),
+            ..toml::from_str::<SnapshotLog>(&format!(
+                "original_url = \"https://a.com/{snapshot_path}\"\n\
+                canonical_url = \"https://a.com/{snapshot_path}\"\n\
+                snapshot_path = \"file://./{snapshot_path}\"\n\
+                snapshot_date = \"2024-05-01T10:00:00+00:00\"\n\
+                outgoing_links = []\n\
+                incoming_links = []\n"
+            )).unwrap()
+        };
+        let logs = [
+            log("a", Some(1), None),
+            log("b", Some(3), Some("a")),
+            log("c", None, None),
+        ];
+        let index = SimHashIndex::from_snapshot_logs(3, logs.iter());
+        assert_eq!(index.len(), 1);
+        assert_eq!(index.find(1, &path("own")), Some((&path("a"), 0)));
+    }
+
+    #[test]
+    fn distances_beyond_the_fingerprint_match_everything() {
+        let mut index = SimHashIndex::new(64);
+        index.insert(path("a"), 0);
+        assert_eq!(index.find(u64::MAX, &path("own")), Some((&path("a"), 64)));
+    }
+}
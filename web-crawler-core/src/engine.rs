@@ -1,23 +1,86 @@
-use std::collections::{HashSet, LinkedList};
-use std::path::PathBuf;
-use std::{collections::VecDeque, str::FromStr};
-use indexmap::IndexSet;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use indexmap::{IndexMap, IndexSet};
+use tokio::sync::Mutex;
 use url::Url;
-use colored::Colorize;
 
-use crate::metadata::common::{CanonicalUrl, OriginalUrl, RelativeFilePath, SnapshotDate, SnapshotDirectory, Status};
+use crate::budget::StopReason;
+use crate::dedup::SimHashIndex;
+use crate::error::CrawlError;
+use crate::frontier::{Frontier, FrontierEntry, FrontierLog};
+use crate::http::{DownloadError, HttpFetcher};
+use crate::metadata::common::{CanonicalUrl, DiscoverySource, OriginalUrl, RelativeFilePath, SnapshotDate, SnapshotDirectory, Status};
 use crate::metadata::project::ProjectLog;
-use crate::metadata::snapshot::{SnapshotLog, TaskLog};
+use crate::metadata::run::RunLog;
+use crate::metadata::snapshot::{SnapshotLog, SnapshotVersion, TaskLog};
+use crate::observer::{ConsoleObserver, CrawlObserver, CrawlWarning, PageVisited};
+use crate::politeness::{HostKey, HostScheduler};
+use crate::retry::CrawlMode;
+use crate::robots::RobotsCache;
 // use crate::db::{SnapshotManifest, VisitedPage, WebpageSnapshotMetadata};
 use crate::settings::{FailedFilterReason, SkipUrlReason};
+use crate::warc::{WarcPage, WarcSettings, WarcWriter};
 
 pub use crate::settings::CrawlerSettings;
 
 pub struct WebCrawler {
     crawler_settings: CrawlerSettings,
+    state: Mutex<CrawlerState>,
+    host_scheduler: HostScheduler,
+    robots: RobotsCache,
+    fetcher: HttpFetcher,
+    observers: Vec<Box<dyn CrawlObserver>>,
+    /// Fingerprints of the original snapshots, when near-duplicate detection
+    /// is enabled. Locked on its own so lookups don't hold up `state`.
+    simhashes: std::sync::Mutex<SimHashIndex>,
+    /// Open when snapshots are written to WARC files. Locked on its own so
+    /// that writing records doesn't hold up `state`.
+    warc: std::sync::Mutex<Option<WarcWriter>>,
+}
+
+/// Everything the workers read and write while crawling.
+///
+/// Guarded by a single lock so that the frontier, the resolved set and the
+/// project log never disagree with each other.
+struct CrawlerState {
     project: ProjectLog,
-    queue: VecDeque<Url>,
+    queue: Box<dyn Frontier>,
+    /// Failed entries waiting out their retry backoff before going back on
+    /// `queue`.
+    delayed: DelayedEntries,
+    /// On-disk journal mirroring `queue`, so an interrupted crawl can resume.
+    frontier_log: FrontierLog,
     fully_resolved: HashSet<Url>,
+    /// URLs (original and canonical) currently claimed by a worker.
+    in_progress: HashSet<Url>,
+    /// Number of workers currently processing a URL.
+    active_workers: usize,
+    /// Pages claimed during this run, in total and per host.
+    pages_visited: usize,
+    pages_per_host: HashMap<HostKey, usize>,
+    started: std::time::Instant,
+    /// Set once a budget limit ends the run early.
+    stop_reason: Option<StopReason>,
+}
+
+/// What became of a frontier entry handed to a worker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum UrlOutcome {
+    /// Visited or skipped for good; done in the frontier journal.
+    Resolved,
+    /// Put back, deferred or scheduled for a retry; still pending.
+    Pending,
+    /// Another worker is visiting the same URL right now. Only that worker
+    /// marks it done, so a crash mid-visit leaves it pending.
+    OwnedElsewhere,
+}
+
+/// Where the snapshot of a URL is stored, derived from its canonical form.
+struct SnapshotPaths {
+    html_output_path: PathBuf,
+    rel_html_path: RelativeFilePath,
+    snapshot_directory: SnapshotDirectory,
 }
 
 // fn get_actual_url<'a>(
@@ -46,55 +109,350 @@ pub struct WebCrawler {
 // ————————————————————————————————————————————————————————————————————————————
 
 impl WebCrawler {
-    pub fn new(crawler_settings: CrawlerSettings) -> Self {
+    pub fn new(crawler_settings: CrawlerSettings) -> Result<Self, CrawlError> {
         let project_directory = crawler_settings.project_directory.as_path();
-        let project = ProjectLog::load(project_directory).unwrap();
+        let project = ProjectLog::load(project_directory)
+            .map_err(|error| CrawlError::Metadata(error.to_string()))?;
+        let fetcher = HttpFetcher::new(&crawler_settings.robots.user_agent)?;
+        let robots = RobotsCache::new(crawler_settings.robots.clone(), fetcher.clone());
         // - -
-        let mut all_outbound_links = LinkedList::<Url>::new();
-        all_outbound_links.extend(crawler_settings.seed_urls.clone());
-        for entry in project.snapshot_logs.iter() {
-            all_outbound_links.extend({
-                entry.outgoing_links
-                    .iter()
-                    .map(|x| x.0.clone())
-            });
+        let mut all_outbound_links = IndexMap::<Url, FrontierEntry>::new();
+        // Only the default observer exists this early.
+        let journal = FrontierLog::load(project_directory).unwrap_or_else(|error| {
+            ConsoleObserver.warning(&CrawlWarning::FrontierJournal { operation: "read", error: error.to_string() });
+            None
+        });
+        for entry in journal.iter().flatten() {
+            all_outbound_links.insert(entry.url.clone(), entry.clone());
         }
-        let all_outbound_links = IndexSet::<Url>::from_iter(all_outbound_links);
-        let queue = all_outbound_links
-            .into_iter()
-            .filter(|url| {
-                let canonical_url = CanonicalUrl::from_url(url.clone());
-                let ( html_output_path, _ ) = Self::snapshot_file_path(url, &crawler_settings);
-                let snapshot_directory = SnapshotDirectory(html_output_path.parent().unwrap().to_path_buf());
-                let should_visit_original_url = Self::should_visit(url, &snapshot_directory, &crawler_settings, &project).is_ok();
-                let should_visit_canonical_url = Self::should_visit(&canonical_url.0, &snapshot_directory, &crawler_settings, &project).is_ok();
-                should_visit_original_url && should_visit_canonical_url
-            })
-            .collect::<VecDeque<_>>();
+        for url in crawler_settings.seed_urls.iter() {
+            all_outbound_links
+                .entry(url.clone())
+                .or_insert_with(|| FrontierEntry::seed(url.clone()));
+        }
+        // Projects crawled before the journal existed rebuild their frontier
+        // from the outgoing links of every snapshot.
+        let snapshot_logs = match journal {
+            Some(_) => None,
+            None => Some(project.snapshot_logs().map_err(|error| CrawlError::Metadata(error.to_string()))?),
+        };
+        for entry in snapshot_logs.into_iter().flatten() {
+            // Logs written before depth tracking count as one hop from a seed.
+            let depth = entry.depth.unwrap_or(0) + 1;
+            for link in entry.outgoing_links.iter() {
+                all_outbound_links
+                    .entry(link.0.clone())
+                    .or_insert_with(|| FrontierEntry {
+                        url: link.0.clone(),
+                        source: DiscoverySource::Link { referrer: entry.original_url.clone() },
+                        depth,
+                        anchor_text: None,
+                    });
+            }
+        }
+        let is_visitable = |entry: &FrontierEntry| {
+            if crawler_settings.budget.exceeds_depth(entry.depth) {
+                return false
+            }
+            Self::should_visit(&entry.url, &crawler_settings, &project, &robots, &DelayedEntries::default()).is_ok()
+        };
+        let mut queue = crawler_settings.frontier_order.build();
+        let mut delayed = DelayedEntries::default();
+        let pending = match crawler_settings.mode {
+            CrawlMode::Full => {
+                let now = std::time::Instant::now();
+                let retry = &crawler_settings.retry;
+                let mut pending = Vec::new();
+                for entry in all_outbound_links.into_values() {
+                    // Failed entries still backing off wait in `delayed`, and
+                    // are checked once they are due.
+                    let backoff = project
+                        .task_log_for(&entry.url)
+                        .or_else(|| project.task_log_for(&crawler_settings.url_normalizer.canonicalize(&entry.url).0))
+                        .filter(|log| retry.should_retry(log))
+                        .map(|log| retry.remaining_backoff(&log))
+                        .filter(|delay| !delay.is_zero());
+                    match backoff {
+                        Some(delay) => delayed.push(now + delay, entry.clone()),
+                        None if is_visitable(&entry) => queue.push(entry.clone()),
+                        None => continue,
+                    }
+                    pending.push(entry);
+                }
+                pending
+            }
+            // Everything else stays pending in the journal for the next full
+            // run.
+            CrawlMode::RetryFailed => {
+                let now = std::time::Instant::now();
+                let retry = &crawler_settings.retry;
+                let task_logs = project.task_logs().map_err(|error| CrawlError::Metadata(error.to_string()))?;
+                for log in task_logs.iter().filter(|log| retry.should_retry(log)) {
+                    let Some(url) = log.url() else {
+                        continue
+                    };
+                    let entry = FrontierEntry {
+                        url: url.clone(),
+                        source: log.discovered_via.clone().unwrap_or(DiscoverySource::Seed),
+                        depth: log.depth.unwrap_or(0),
+                        anchor_text: None,
+                    };
+                    // Entries still backing off are checked once they are due.
+                    match retry.remaining_backoff(log) {
+                        std::time::Duration::ZERO if !is_visitable(&entry) => continue,
+                        std::time::Duration::ZERO => queue.push(entry.clone()),
+                        delay => delayed.push(now + delay, entry.clone()),
+                    }
+                    all_outbound_links.insert(url.clone(), entry);
+                }
+                all_outbound_links.into_values().collect::<Vec<_>>()
+            }
+        };
         // let queue = VecDeque::from_iter(all_outbound_links);
+        let frontier_log = FrontierLog::open(project_directory, pending.iter())
+            .map_err(|error| CrawlError::Metadata(error.to_string()))?;
         // - -
-        Self {
-            queue,
-            project,
+        let host_scheduler = HostScheduler::new(crawler_settings.politeness.clone());
+        let max_distance = crawler_settings.dedup.max_distance;
+        let simhashes = match crawler_settings.dedup.enabled {
+            true => {
+                let snapshot_logs = project.snapshot_logs().map_err(|error| CrawlError::Metadata(error.to_string()))?;
+                SimHashIndex::from_snapshot_logs(max_distance, snapshot_logs.iter())
+            }
+            false => SimHashIndex::new(max_distance),
+        };
+        let warc = crawler_settings.warc.output.writes_warc().then(|| {
+            let prefix = project_directory
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| String::from("crawl"));
+            WarcWriter::new(
+                project_directory.join(WarcSettings::DIRECTORY_NAME),
+                prefix,
+                crawler_settings.warc.clone(),
+                crawler_settings.robots.user_agent.clone(),
+            )
+        });
+        Ok(Self {
             crawler_settings,
-            fully_resolved: Default::default(),
-        }
+            host_scheduler,
+            robots,
+            fetcher,
+            observers: vec![Box::new(ConsoleObserver)],
+            simhashes: std::sync::Mutex::new(simhashes),
+            warc: std::sync::Mutex::new(warc),
+            state: Mutex::new(CrawlerState {
+                project,
+                queue,
+                delayed,
+                frontier_log,
+                fully_resolved: Default::default(),
+                in_progress: Default::default(),
+                active_workers: 0,
+                pages_visited: 0,
+                pages_per_host: Default::default(),
+                started: std::time::Instant::now(),
+                stop_reason: None,
+            }),
+        })
+    }
+    /// Register an additional observer. A [`ConsoleObserver`] is registered
+    /// by default; see [`Self::clear_observers`].
+    pub fn add_observer(&mut self, observer: impl CrawlObserver + 'static) {
+        self.observers.push(Box::new(observer));
+    }
+    pub fn with_observer(mut self, observer: impl CrawlObserver + 'static) -> Self {
+        self.add_observer(observer);
+        self
+    }
+    /// Remove every observer, including the default console output.
+    pub fn clear_observers(&mut self) {
+        self.observers.clear();
     }
     // pub fn write_snapshot_manifest(&self) -> Result<(), Box<dyn std::error::Error>> {
     //     let file_path = self.crawler_settings.file_system_paths.manifest_path.as_path();
     //     let _ = self.project.write(file_path)?;
     //     Ok(())
     // }
-    pub async fn execute(&mut self) {
-        let mut client = web_client_bot::WebClient::start(Default::default()).await;
-        while let Some(task) = self.queue.pop_front() {
-            self.process_url(&task, &mut client).await;
+    /// Crawl until the frontier is drained or a budget limit is reached.
+    ///
+    /// Only failing to launch the browser is an error; failures on
+    /// individual pages are recorded in their task logs instead.
+    pub async fn execute(&mut self) -> Result<RunLog, CrawlError> {
+        let started = SnapshotDate::now();
+        self.state.get_mut().started = std::time::Instant::now();
+        self.seed_from_sitemaps().await;
+        let client = web_client_bot::WebClient::start(self.crawler_settings.browser.client.clone()).await?;
+        {
+            let this = &*self;
+            let max_concurrent_tabs = this.crawler_settings.max_concurrent_tabs.max(1);
+            let workers = (0..max_concurrent_tabs)
+                .map(|_| this.run_worker(&client))
+                .collect::<Vec<_>>();
+            tokio::select! {
+                _ = futures::future::join_all(workers) => (),
+                _ = this.handle_interrupts() => (),
+            }
+        }
+        if let Err(error) = client.close().await {
+            self.notify(|observer| observer.warning(&CrawlWarning::CloseBrowser(error.to_string())));
         }
-        client.close().await;
         // self.write_snapshot_manifest().unwrap();
+        // - RUN SUMMARY -
+        let state = self.state.get_mut();
+        let mut warnings = Vec::new();
+        if let Err(error) = state.frontier_log.flush() {
+            warnings.push(CrawlWarning::FrontierJournal { operation: "flush", error: error.to_string() });
+        }
+        let warc = self.warc.get_mut().unwrap_or_else(std::sync::PoisonError::into_inner);
+        if let Some(Err(error)) = warc.as_mut().map(WarcWriter::flush) {
+            warnings.push(CrawlWarning::WarcOutput { url: None, error: error.to_string() });
+        }
+        let run_log = RunLog {
+            started,
+            finished: SnapshotDate::now(),
+            stop_reason: state.stop_reason.unwrap_or(StopReason::QueueDrained),
+            pages_visited: state.pages_visited,
+            remaining_in_queue: state.queue.len() + state.delayed.len(),
+            pages_per_host: state.pages_per_host
+                .iter()
+                .map(|(host, count)| (host.0.clone(), *count))
+                .collect(),
+        };
+        for warning in warnings.iter() {
+            self.notify(|observer| observer.warning(warning));
+        }
+        self.notify(|observer| observer.crawl_finished(&run_log));
+        if let Err(error) = run_log.write(&self.crawler_settings.project_directory) {
+            self.notify(|observer| observer.warning(&CrawlWarning::RunLog(error.to_string())));
+        }
+        Ok(run_log)
     }
     pub fn finalize(self) -> (CrawlerSettings, ProjectLog) {
-        (self.crawler_settings, self.project)
+        (self.crawler_settings, self.state.into_inner().project)
+    }
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// SITEMAPS
+// ————————————————————————————————————————————————————————————————————————————
+
+impl WebCrawler {
+    /// Enqueue the pages listed in the sitemaps of every seed origin.
+    ///
+    /// Sitemaps are found via `Sitemap:` lines in robots.txt and the
+    /// conventional `/sitemap.xml` location.
+    async fn seed_from_sitemaps(&self) {
+        if !self.crawler_settings.sitemaps.enabled || self.crawler_settings.mode == CrawlMode::RetryFailed {
+            return
+        }
+        let mut sitemap_urls = IndexSet::<Url>::new();
+        for seed_url in self.crawler_settings.seed_urls.iter() {
+            if let Some(robots) = self.load_robots(seed_url).await {
+                sitemap_urls.extend(robots.sitemaps().iter().cloned());
+            }
+            if let Ok(default_sitemap) = seed_url.join("/sitemap.xml") {
+                sitemap_urls.insert(default_sitemap);
+            }
+        }
+        let pages = crate::sitemap::collect_sitemap_urls(
+            &self.fetcher,
+            sitemap_urls,
+            &self.crawler_settings.sitemaps,
+        ).await;
+        let entries = pages
+            .into_iter()
+            .map(|(url, sitemap)| FrontierEntry {
+                url,
+                source: DiscoverySource::Sitemap { sitemap: OriginalUrl(sitemap) },
+                depth: 1,
+                anchor_text: None,
+            })
+            .collect::<Vec<_>>();
+        let mut state = self.state.lock().await;
+        self.enqueue_urls(&mut state, entries);
+    }
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// WORKERS
+// ————————————————————————————————————————————————————————————————————————————
+
+impl WebCrawler {
+    /// Pull URLs off the shared frontier until it is drained.
+    ///
+    /// An empty queue only means we are done once no other worker is still
+    /// processing a page, since that page may enqueue more URLs.
+    async fn run_worker(&self, client: &web_client_bot::WebClient) {
+        loop {
+            let next = {
+                let mut state = self.state.lock().await;
+                if state.stop_reason.is_some() {
+                    return
+                }
+                if let Some(stop_reason) = self.exhausted_budget(&state) {
+                    self.notify(|observer| observer.budget_exhausted(&stop_reason));
+                    state.stop_reason = Some(stop_reason);
+                    return
+                }
+                if !state.delayed.is_empty() {
+                    for entry in state.delayed.take_due(std::time::Instant::now()) {
+                        state.queue.push(entry);
+                    }
+                }
+                match state.queue.pop() {
+                    Some(entry) => {
+                        state.active_workers += 1;
+                        Some(entry)
+                    }
+                    None if state.active_workers == 0 && state.delayed.is_empty() => return,
+                    None => None,
+                }
+            };
+            match next {
+                Some(entry) => {
+                    let outcome = self.process_url(&entry, client).await;
+                    let mut state = self.state.lock().await;
+                    state.active_workers -= 1;
+                    if outcome == UrlOutcome::Resolved
+                        && let Err(error) = state.frontier_log.record_done(&entry.url)
+                    {
+                        let warning = CrawlWarning::FrontierJournal { operation: "update", error: error.to_string() };
+                        self.notify(|observer| observer.warning(&warning));
+                    }
+                }
+                None => {
+                    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+                }
+            }
+        }
+    }
+}
+
+impl WebCrawler {
+    /// Stop handing out work on the first Ctrl+C so in-flight pages can
+    /// finish; exit immediately on the second. The frontier journal is
+    /// flushed in both cases and pages still in flight stay pending.
+    async fn handle_interrupts(&self) {
+        let mut interrupted = false;
+        loop {
+            if tokio::signal::ctrl_c().await.is_err() {
+                return std::future::pending().await
+            }
+            let mut state = self.state.lock().await;
+            let _ = state.frontier_log.flush();
+            if interrupted {
+                self.notify(|observer| observer.interrupted_again());
+                std::process::exit(130);
+            }
+            interrupted = true;
+            state.stop_reason = Some(StopReason::Interrupted);
+            self.notify(|observer| observer.interrupted(state.frontier_log.path()));
+        }
+    }
+    /// The run-wide budget limit that has been reached, if any.
+    fn exhausted_budget(&self, state: &CrawlerState) -> Option<StopReason> {
+        self.crawler_settings.budget.stop_reason(state.pages_visited, state.started.elapsed())
     }
 }
 
@@ -103,63 +461,119 @@ impl WebCrawler {
 // ————————————————————————————————————————————————————————————————————————————
 
 impl WebCrawler {
+    /// Load the robots.txt for the origin of `url` and apply its Crawl-delay.
+    async fn load_robots(&self, url: &Url) -> Option<std::sync::Arc<crate::robots::RobotsTxt>> {
+        if let Some(robots) = self.robots.get(url) {
+            return Some(robots)
+        }
+        let robots = self.robots.load(url).await?;
+        if let Some(crawl_delay) = robots.crawl_delay(&self.crawler_settings.robots.user_agent) {
+            self.host_scheduler.set_crawl_delay(url, crawl_delay);
+        }
+        Some(robots)
+    }
     fn should_visit(
         url: &Url,
-        snapshot_directory: &SnapshotDirectory,
         crawler_settings: &CrawlerSettings,
         project: &ProjectLog,
+        robots: &RobotsCache,
+        delayed: &DelayedEntries,
     ) -> Result<(), SkipUrlReason> {
-        let already_visited = !project.should_visit(url, snapshot_directory);
-        let filtered_out = crawler_settings.url_visitor_settings.should_visit(url);
+        let canonical_url = crawler_settings.url_normalizer.canonicalize(url);
+        let already_visited = !project.should_visit(url) || !project.should_visit(&canonical_url.0);
+        let due_for_recrawl = project
+            .snapshot_log_for(url)
+            .or_else(|| project.snapshot_log_for(&canonical_url.0))
+            .is_some_and(|log| crawler_settings.recrawl.is_due(&log));
+        // A retry waits out its backoff, and is enqueued once, by whoever
+        // scheduled it.
+        let due_for_retry = project
+            .task_log_for(url)
+            .or_else(|| project.task_log_for(&canonical_url.0))
+            .is_some_and(|log| {
+                crawler_settings.retry.should_retry(&log)
+                    && crawler_settings.retry.remaining_backoff(&log).is_zero()
+            })
+            && !delayed.contains(url)
+            && !delayed.contains(&canonical_url.0);
+        let already_visited = already_visited && !due_for_recrawl && !due_for_retry;
+        let filtered_out = crawler_settings.url_visitor_settings.should_visit(&canonical_url.0);
         if already_visited {
             return Err(SkipUrlReason::AlreadyVisited)
         }
         if let Err(rejected_url_reason) = filtered_out {
             return Err(SkipUrlReason::FailedFilter(rejected_url_reason))
         }
+        if !robots.is_allowed(&canonical_url.0) {
+            return Err(SkipUrlReason::FailedFilter(FailedFilterReason::DisallowedByRobotsTxt))
+        }
         Ok(())
     }
     fn should_visit_with_debug_log(
         &self,
+        state: &CrawlerState,
         url: &Url,
-        snapshot_directory: &SnapshotDirectory,
     ) -> Result<(), SkipUrlReason> {
-        if let Err(skip_url_reason) = Self::should_visit(url, snapshot_directory, &self.crawler_settings, &self.project) {
-            let msg = match skip_url_reason.clone() {
-                SkipUrlReason::AlreadyVisited => {
-                    format!("\t ⓘ Skipping [already-visited] {:?}", url.to_string())
-                    // return false
-                }
-                SkipUrlReason::FailedFilter(FailedFilterReason::FailedWhitelistedSchemeCheck) => {
-                    format!("\t ⓘ Skipping [failed-whitelisted-scheme-check] {:?}", url.to_string())
-                }
-                SkipUrlReason::FailedFilter(FailedFilterReason::FailedWhitelistedDomainCheck) => {
-                    format!("\t ⓘ Skipping [failed-whitelisted-domain-check] {:?}", url.to_string())
-                }
-                SkipUrlReason::FailedFilter(FailedFilterReason::IsBlacklistedScheme) => {
-                    format!("\t ⓘ Skipping [is-blacklisted-scheme] {:?}", url.to_string())
-                }
-            };
-            eprintln!("{}", msg.cyan());
+        if let Err(skip_url_reason) = Self::should_visit(url, &self.crawler_settings, &state.project, &self.robots, &state.delayed) {
+            self.log_skipped_url(url, &skip_url_reason);
             return Err(skip_url_reason)
         }
         Ok(())
     }
-    fn snapshot_file_path(url: &Url, crawler_settings: &CrawlerSettings) -> ( PathBuf, RelativeFilePath ) {
-        let relative_snapshot_path = crate::path_utils::build_rel_html_snapshot_file_path(url.as_str()).unwrap();
-        let html_path = crawler_settings.project_directory.join(&relative_snapshot_path.0);
-        ( html_path, relative_snapshot_path )
+    fn notify(&self, event: impl Fn(&dyn CrawlObserver)) {
+        for observer in self.observers.iter() {
+            event(observer.as_ref());
+        }
+    }
+    fn log_skipped_url(&self, url: &Url, skip_url_reason: &SkipUrlReason) {
+        self.notify(|observer| observer.page_skipped(url, skip_url_reason));
+    }
+    /// Snapshots are stored under the canonical form of `url`, so every
+    /// variant of a URL maps to the same directory.
+    ///
+    /// A URL that already has a snapshot keeps its path, which keeps projects
+    /// crawled with an earlier path mapping consistent. A new URL whose path
+    /// holds the snapshot of another URL is moved aside.
+    fn snapshot_paths(
+        url: &Url,
+        crawler_settings: &CrawlerSettings,
+        project: &ProjectLog,
+    ) -> Result<SnapshotPaths, CrawlError> {
+        let canonical_url = crawler_settings.url_normalizer.canonicalize(url);
+        let existing = project
+            .snapshot_log_for(&canonical_url.0)
+            .map(|log| log.snapshot_path);
+        let rel_html_path = existing
+            .or_else(|| {
+                crate::path_utils::resolve_snapshot_file_path(canonical_url.0.as_str(), |path| {
+                    project.snapshot_log_at(path).is_some_and(|log| {
+                        log.canonical_url.0 != canonical_url.0 && log.original_url.0 != canonical_url.0
+                    })
+                })
+            })
+            .ok_or_else(|| CrawlError::InvalidUrl(format!("no snapshot path for {:?}", canonical_url.0.as_str())))?;
+        let html_output_path = crawler_settings.project_directory.join(&rel_html_path.0);
+        let snapshot_directory = SnapshotDirectory(
+            html_output_path
+                .parent()
+                .map(Path::to_path_buf)
+                .unwrap_or_else(|| crawler_settings.project_directory.clone())
+        );
+        Ok(SnapshotPaths { html_output_path, rel_html_path, snapshot_directory })
     }
     fn enqueue_urls(
-        &mut self,
-        urls: impl IntoIterator<Item=Url>,
-        snapshot_directory: &SnapshotDirectory,
+        &self,
+        state: &mut CrawlerState,
+        entries: impl IntoIterator<Item=FrontierEntry>,
     ) {
-        let enqueue = urls
+        let enqueue = entries
             .into_iter()
-            .map(|x| x.into())
-            .filter(|url| {
-                let status = self.should_visit_with_debug_log(url, &snapshot_directory);
+            .filter(|FrontierEntry { url, depth, .. }| {
+                if self.crawler_settings.budget.exceeds_depth(*depth) {
+                    self.log_skipped_url(url, &SkipUrlReason::ExceedsMaxDepth);
+                    return false
+                }
+                let status = self.should_visit_with_debug_log(state, url);
                 // let is_fully_resolved = self.fully_resolved.contains(url);
                 // let is_already_visited = &status == &Err(SkipUrlReason::AlreadyVisited);
                 // if !skip_already_visited && is_already_visited && !is_fully_resolved {
@@ -169,62 +583,156 @@ impl WebCrawler {
             })
             .collect::<Vec<_>>();
         for next in enqueue {
-            self.queue.push_back(next.clone());
+            if let Err(error) = state.frontier_log.record_push(&next) {
+                let warning = CrawlWarning::FrontierJournal { operation: "update", error: error.to_string() };
+                self.notify(|observer| observer.warning(&warning));
+            }
+            // Journaled only, for the next full run.
+            if self.crawler_settings.mode == CrawlMode::RetryFailed {
+                continue
+            }
+            state.queue.push(next);
         }
     }
-    async fn process_url(&mut self, url: &Url, client: &mut web_client_bot::WebClient) {
-        let canonical_url = CanonicalUrl::from_url(url.clone());
-        let ( html_output_path, rel_html_path ) = Self::snapshot_file_path(url, &self.crawler_settings);
-        let snapshot_directory = SnapshotDirectory(html_output_path.parent().unwrap().to_path_buf());
-        let mut task_log = TaskLog::default();
-        // - CHECK SHOULD SKIP -
+    /// A failed visit scheduled for a retry is [`UrlOutcome::Pending`].
+    async fn process_url(&self, entry: &FrontierEntry, client: &web_client_bot::WebClient) -> UrlOutcome {
+        let url = &entry.url;
+        let canonical_url = self.crawler_settings.url_normalizer.canonicalize(url);
+        let paths = Self::snapshot_paths(url, &self.crawler_settings, &self.state.lock().await.project);
+        let paths = match paths {
+            Ok(paths) => paths,
+            Err(error) => {
+                // Nowhere to record it, so it is only reported.
+                self.notify(|observer| observer.page_failed(url, &error));
+                return UrlOutcome::Resolved
+            }
+        };
+        // - ROBOTS.TXT -
+        self.load_robots(&canonical_url.0).await;
+        // - CHECK SHOULD SKIP & CLAIM -
         {
-            let mut status: Result<(), SkipUrlReason> = Ok(());
-            if self.fully_resolved.contains(url) || self.fully_resolved.contains(&canonical_url.0) {
-                return
+            let mut state = self.state.lock().await;
+            if state.fully_resolved.contains(url) || state.fully_resolved.contains(&canonical_url.0) {
+                return UrlOutcome::Resolved
             }
-            if let Err(error) = self.should_visit_with_debug_log(&url, &snapshot_directory) {
-                status = Err(error);
+            if state.in_progress.contains(url) || state.in_progress.contains(&canonical_url.0) {
+                return UrlOutcome::OwnedElsewhere
             }
-            if let Err(error) = self.should_visit_with_debug_log(&canonical_url.0, &snapshot_directory) {
-                status = Err(error);
+            // A copy of an entry waiting out its retry backoff; the waiting
+            // one visits it.
+            if state.delayed.contains(url) || state.delayed.contains(&canonical_url.0) {
+                return UrlOutcome::OwnedElsewhere
             }
-            if status.is_err() {
+            if self.should_visit_with_debug_log(&state, url).is_err() {
                 // - TERMINATE -
-                return
+                return UrlOutcome::Resolved
             }
+            // - BUDGET -
+            if self.exhausted_budget(&state).is_some() {
+                state.queue.put_back(entry.clone());
+                return UrlOutcome::Pending
+            }
+            let host_key = HostKey::from_url(&canonical_url.0);
+            let host_pages = state.pages_per_host.get(&host_key).copied().unwrap_or(0);
+            if self.crawler_settings.budget.exhausts_host(host_pages) {
+                // Stays pending in the frontier journal for the next run.
+                self.log_skipped_url(url, &SkipUrlReason::HostBudgetExhausted);
+                return UrlOutcome::Pending
+            }
+            state.pages_visited += 1;
+            *state.pages_per_host.entry(host_key).or_default() += 1;
+            // - CLAIM -
+            state.in_progress.insert(url.to_owned());
+            state.in_progress.insert(canonical_url.0.clone());
         }
-        eprintln!("{}", format!("🔎 Visiting: {}", canonical_url.0).bright_magenta());
+        let host_key = self.host_scheduler.acquire(&canonical_url.0).await;
+        let (status_code, retrying) = self.visit_url(entry, &canonical_url, &paths, client).await;
+        self.host_scheduler.release(&host_key, status_code);
+        if matches!(status_code, Some(429) | Some(503)) {
+            let backoff = self.host_scheduler.backoff_for(&canonical_url.0);
+            self.notify(|observer| observer.throttled(&host_key.0, status_code, backoff));
+        }
+        // - RELEASE -
+        {
+            let mut state = self.state.lock().await;
+            state.in_progress.remove(url);
+            state.in_progress.remove(&canonical_url.0);
+        }
+        match retrying {
+            true => UrlOutcome::Pending,
+            false => UrlOutcome::Resolved,
+        }
+    }
+    /// Visit a claimed URL and return the HTTP status of its main document,
+    /// and whether it was scheduled for a retry.
+    ///
+    /// Whatever goes wrong is recorded as a `Status::Failure` in the task log
+    /// of the URL; the crawl carries on.
+    async fn visit_url(
+        &self,
+        entry: &FrontierEntry,
+        canonical_url: &CanonicalUrl,
+        paths: &SnapshotPaths,
+        client: &web_client_bot::WebClient,
+    ) -> ( Option<i64>, bool ) {
+        let url = &entry.url;
+        let mut task_log = self.start_task_log(entry, canonical_url).await;
         // - -
-        let tab = client.open_new_tab_at_url_with_network_tracking(canonical_url.0.as_str()).await;
-        let status_code = tab.status_code();
-        if status_code != Some(200) {
-            let should_terminate = status_code.is_none();
-            if should_terminate {
-                eprintln!("{}", format!(
-                    "\t Skipping {:?} » status: {status_code:?}",
-                    url.to_string(),
-                ).bright_red());
-            } else {
-                eprintln!("{}", format!(
-                    "\t Error {:?} » status: {status_code:?}",
-                    url.to_string(),
-                ).red());
+        let tab_options = web_client_bot::TabOptions {
+            timeouts: self.crawler_settings.browser.timeouts,
+            ..self.crawler_settings.capture.tab_options()
+        };
+        let tab = match client.open_new_tab_with_options(canonical_url.0.as_str(), &tab_options).await {
+            Ok(tab) => tab,
+            Err(error) => {
+                let retrying = self.record_failure(entry, canonical_url, task_log, None, error.into()).await;
+                return ( None, retrying )
             }
-            // - -
-            task_log.entries.push(Status::Failure {
-                url: OriginalUrl(url.clone()),
-                http_status: status_code,
-            });
-            // - -
-            if should_terminate {
-                self.project.persist_task_log(&snapshot_directory, task_log).unwrap();
-                self.fully_resolved.insert(url.to_owned());
-                self.fully_resolved.insert(canonical_url.0.clone());
-                // - TERMINATE -
-                tab.close().await;
-                return
+        };
+        let status_code = tab.status_code();
+        self.notify(|observer| observer.page_visited(&PageVisited {
+            url,
+            canonical_url,
+            depth: entry.depth,
+            http_status: status_code,
+        }));
+        let result = self.snapshot_page(entry, canonical_url, paths, &tab, &mut task_log).await;
+        let retrying = match result {
+            Ok(()) => self.finish_task(entry, canonical_url, task_log).await,
+            Err(error) => self.record_failure(entry, canonical_url, task_log, status_code, error).await,
+        };
+        // - CLOSE -
+        if let Err(error) = tab.close().await {
+            let warning = CrawlWarning::CloseTab { url, error: error.to_string() };
+            self.notify(|observer| observer.warning(&warning));
+        }
+        ( status_code, retrying )
+    }
+    /// Snapshot the page loaded in `tab` and enqueue its links.
+    async fn snapshot_page(
+        &self,
+        entry: &FrontierEntry,
+        canonical_url: &CanonicalUrl,
+        paths: &SnapshotPaths,
+        tab: &web_client_bot::LiveWebpage,
+        task_log: &mut TaskLog,
+    ) -> Result<(), CrawlError> {
+        let url = &entry.url;
+        let SnapshotPaths { html_output_path, rel_html_path, snapshot_directory } = paths;
+        let status_code = tab.status_code();
+        match status_code {
+            Some(200) => (),
+            // Error pages are still snapshotted.
+            Some(http_status) => {
+                let error = CrawlError::HttpStatus(http_status);
+                self.notify(|observer| observer.page_failed(url, &error));
+                task_log.entries.push(Status::Failure {
+                    url: OriginalUrl(url.clone()),
+                    http_status: status_code,
+                    reason: Some(error.to_failure_reason()),
+                });
             }
+            None => return Err(CrawlError::NoResponse),
         }
         // - -
         // {
@@ -237,146 +745,558 @@ impl WebCrawler {
         let actual_url = {
             let url = web_client_bot::utils::retry_async(
                 || async {
-                    tab.actual_url().await
+                    tab .actual_url()
+                        .await
+                        .map_err(|e| Box::new(e) as Box<dyn std::error::Error + Send + Sync>)
                 },
                 3,
                 std::time::Duration::from_secs(3),
             ).await;
-            let url = url.unwrap();
-            Url::from_str(&url).unwrap()
+            let url = url.map_err(web_client_bot::ClientError::from)?;
+            Url::from_str(&url)?
         };
-        let did_redirect = &actual_url != url;
-        if did_redirect {
+        // - REDIRECTS -
+        let redirect_chain = tab.redirect_chain();
+        for hop in redirect_chain {
+            let (Ok(from), Ok(to)) = (Url::from_str(&hop.from), Url::from_str(&hop.to)) else {
+                continue
+            };
+            self.notify(|observer| observer.redirected(&from, &to, hop.status));
+            task_log.entries.push(Status::Redirected {
+                from: OriginalUrl::from(from),
+                to: OriginalUrl::from(to),
+                http_status: hop.status,
+                kind: Some(hop.kind),
+            });
+        }
+        // Downloads leave the tab on `about:blank`; that is not a redirect.
+        let did_redirect = &actual_url != url && actual_url.scheme() != "about";
+        if did_redirect && redirect_chain.is_empty() {
+            self.notify(|observer| observer.redirected(url, &actual_url, status_code));
             task_log.entries.push(Status::Redirected {
                 from: OriginalUrl::from(url.clone()),
                 to: OriginalUrl::from(actual_url.clone()),
                 http_status: status_code,
+                kind: None,
             });
         }
         // - -
         {
-            let result = web_client_bot::utils::with_timeout_lazy(
-                || async {
-                    tab.is_text_html_document().await
-                },
-                std::time::Duration::from_secs(3),
-            ).await;
-            match result {
-                Ok(Ok(true)) => (),
-                Ok(Ok(false)) => {
-                    eprintln!("{}", format!(
-                        "\t ⓘ Skipping {:?} : NOT HTML DOCUMENT",
-                        url.to_string()
-                    ).red());
-                    // - UPDATE -
-                    self.fully_resolved.insert(url.to_owned());
-                    self.fully_resolved.insert(canonical_url.0.clone());
-                    task_log.entries.push(Status::Failure {
-                        url: OriginalUrl(url.clone()),
-                        http_status: status_code,
-                    });
-                    self.project.persist_task_log(&snapshot_directory, task_log).unwrap();
-                    // - TERMINATE -
-                    tab.close().await;
-                    return
-                },
-                Ok(Err(error)) => {
-                    eprintln!("{}", format!(
-                        "\t ⓘ Skipping {:?} : ERROR CHECKING FOR HTML DOCUMENT : {error}",
-                        url.to_string()
-                    ).red());
-                    // - UPDATE -
-                    self.fully_resolved.insert(url.to_owned());
-                    self.fully_resolved.insert(canonical_url.0.clone());
-                    task_log.entries.push(Status::Failure {
-                        url: OriginalUrl(url.clone()),
-                        http_status: status_code,
-                    });
-                    self.project.persist_task_log(&snapshot_directory, task_log).unwrap();
-                    // - TERMINATE -
-                    tab.close().await;
-                    return
-                }
-                Err(timeout_error) => {
-                    unimplemented!("TODO: {timeout_error}")
-                }
+            let content_type = match tab.mime_type() {
+                // Downloads never replace the blank page, so only the
+                // response knows what they are.
+                Some(mime_type) => mime_type.to_string(),
+                None => web_client_bot::utils::with_timeout_lazy(
+                    || async {
+                        tab.content_type().await
+                    },
+                    std::time::Duration::from_secs(3),
+                ).await??,
+            };
+            if !crate::resources::is_html_mime_type(&content_type) {
+                return self.capture_resource(entry, canonical_url, paths, &content_type, task_log).await
             }
         }
         {
+            let wait = &self.crawler_settings.browser.wait;
             let maybe_fully_settled = web_client_bot::utils::retry_on_timeout(
-                "wait_until_fully_settled",
+                "wait_with",
                 || async {
-                    tab .wait_until_fully_settled()
+                    tab .wait_with(wait)
                         .await
                         .map_err(|e| Box::new(e) as Box<dyn std::error::Error + Send + Sync>)
                 },
                 1,
                 std::time::Duration::from_secs(1),
-                std::time::Duration::from_secs(5),
+                wait.timeout + wait.settle_delay,
             ).await;
+            // The page is snapshotted as it is.
             if let Err(error) = maybe_fully_settled {
-                eprintln!("{}", format!(
-                    "\t ❌ Failed to settle: {:?} » {error}",
-                    url.as_str()
-                ).red());
+                let error = CrawlError::Settle(error.to_string());
+                self.notify(|observer| observer.page_failed(url, &error));
             }
         }
         // - DOM SNAPSHOT -
-        let html = tab.html_content().await;
-        let outgoing_anchors_links = tab.scrape_all_anchor_links().await.unwrap();
-        let outgoing_anchors = outgoing_anchors_links
-            .iter()
-            .filter_map(|link| {
-                match Url::from_str(&link.href) {
-                    Err(error) => {
-                        eprintln!("{}", format!(
-                            "\t ⓘ Skipping {:?} : {error}",
-                            link.href,
-                        ));
-                        None
+        let html = tab.html_content().await?;
+        let outgoing_anchors_links = tab.scrape_all_anchor_links().await?;
+        // Link targets with the first non-empty anchor text found for each.
+        let mut outgoing_anchors = IndexMap::<Url, Option<String>>::new();
+        for link in outgoing_anchors_links.iter() {
+            match Url::from_str(&link.href) {
+                Err(error) => {
+                    let warning = CrawlWarning::InvalidLink { href: &link.href, error: error.to_string() };
+                    self.notify(|observer| observer.warning(&warning));
+                }
+                Ok(target) => {
+                    let anchor_text = outgoing_anchors.entry(target).or_default();
+                    let text = link.text.trim();
+                    if anchor_text.is_none() && !text.is_empty() {
+                        *anchor_text = Some(text.to_string());
                     }
-                    Ok(x) => Some(x),
                 }
-            })
-            .collect::<IndexSet<_>>();
-        // - UPDATE QUEUE -
-        self.enqueue_urls(outgoing_anchors.clone(), &snapshot_directory);
+            }
+        }
+        let dedup = &self.crawler_settings.dedup;
+        let simhash = match dedup.enabled {
+            true => crate::dedup::simhash(&crate::dedup::visible_words(&html)),
+            false => None,
+        };
+        let captures = crate::capture::capture_page(tab, &self.crawler_settings.capture).await;
         // - FINALIZE -
-        {
-            std::fs::create_dir_all(html_output_path.parent().unwrap()).unwrap();
-            std::fs::write(html_output_path, &html).unwrap();
+        // The state lock is only held for lookups and for recording the
+        // result; the files are written without it.
+        // - NEAR-DUPLICATES -
+        // A worker that panicked mid-update leaves at worst a stale
+        // fingerprint; the others carry on.
+        let simhashes = || self.simhashes.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+        // Indexed right away so that concurrent pages see each other, and
+        // put back as it was if the snapshot can't be recorded.
+        let (duplicate_of, indexed_before) = match simhash {
+            Some(simhash) => {
+                let mut simhashes = simhashes();
+                let indexed_before = simhashes.fingerprint(rel_html_path);
+                (simhashes.find_or_insert(simhash, rel_html_path), indexed_before)
+            }
+            None => (None, None),
+        };
+        let snapshot_log = async {
+            let (duplicate_of, previous) = {
+                let state = self.state.lock().await;
+                let duplicate_of = duplicate_of.map(|(original, distance)| {
+                    if let Some(original) = state.project.snapshot_log_at(&original) {
+                        self.notify(|observer| observer.duplicate_found(url, &original, distance));
+                    }
+                    original
+                });
+                (duplicate_of, state.project.snapshot_log_at(rel_html_path))
+            };
+            // - VERSIONING -
+            let now = SnapshotDate::now();
+            let content_hash = crate::recrawl::content_hash(html.as_bytes());
+            let previous_hash = previous.as_ref().and_then(|previous| {
+                previous.content_hash.clone().or_else(|| {
+                    std::fs::read(html_output_path)
+                        .ok()
+                        .map(|contents| crate::recrawl::content_hash(&contents))
+                })
+            });
+            let output = self.crawler_settings.warc.output;
+            let unchanged = previous_hash.as_ref() == Some(&content_hash)
+                && (html_output_path.exists() || !output.writes_directory());
+            let mut versions = previous
+                .as_ref()
+                .map(|previous| previous.versions.clone())
+                .unwrap_or_default();
+            // Filled in by the link-graph pass; keep them until it runs again.
+            let incoming_links = previous
+                .as_ref()
+                .map(|previous| previous.incoming_links.clone())
+                .unwrap_or_default();
+            let project_directory = &self.crawler_settings.project_directory;
+            // Moves the previous snapshot into the versions directory and
+            // lists it; whether that worked.
+            let archive_previous = |previous: &SnapshotLog, versions: &mut Vec<SnapshotVersion>| {
+                match crate::recrawl::archive_snapshot(project_directory, rel_html_path, &previous.snapshot_date) {
+                    Ok(archived_path) => {
+                        versions.push(SnapshotVersion {
+                            snapshot_date: previous.snapshot_date.clone(),
+                            content_hash: previous_hash.clone(),
+                            snapshot_path: archived_path,
+                        });
+                        true
+                    }
+                    Err(error) => {
+                        let warning = CrawlWarning::ArchiveSnapshot { url, error: error.to_string() };
+                        self.notify(|observer| observer.warning(&warning));
+                        false
+                    }
+                }
+            };
+            let snapshot_date = match previous {
+                // Nothing is stored for a duplicate. A page that only now
+                // turned into one keeps its last snapshot as an earlier
+                // version, and its captures are dropped.
+                Some(previous) if duplicate_of.is_some() => {
+                    let retire = previous.duplicate_of.is_none()
+                        && output.writes_directory()
+                        && html_output_path.exists();
+                    if retire {
+                        archive_previous(&previous, &mut versions);
+                        let captures = [&previous.screenshot_path, &previous.pdf_path, &previous.har_path];
+                        for path in captures.into_iter().flatten() {
+                            match std::fs::remove_file(project_directory.join(&path.0)) {
+                                Err(error) if error.kind() != std::io::ErrorKind::NotFound => {
+                                    let warning = CrawlWarning::ArchiveSnapshot { url, error: error.to_string() };
+                                    self.notify(|observer| observer.warning(&warning));
+                                }
+                                _ => (),
+                            }
+                        }
+                    }
+                    now.clone()
+                }
+                Some(previous) if unchanged => {
+                    self.notify(|observer| observer.snapshot_unchanged(url, &previous.snapshot_date));
+                    previous.snapshot_date
+                }
+                // Earlier versions are already in the WARC files.
+                Some(_) if !output.writes_directory() => now.clone(),
+                Some(previous) => {
+                    if archive_previous(&previous, &mut versions) {
+                        self.notify(|observer| observer.snapshot_changed(url, &previous.snapshot_date));
+                    }
+                    now.clone()
+                }
+                None => now.clone(),
+            };
+            if !unchanged && duplicate_of.is_none() && output.writes_directory() {
+                std::fs::create_dir_all(&snapshot_directory.0)?;
+                std::fs::write(html_output_path, &html)?;
+            }
+            // - CAPTURES -
+            let capture_paths = match duplicate_of {
+                Some(_) => Default::default(),
+                None if !output.writes_directory() => Default::default(),
+                None => {
+                    std::fs::create_dir_all(&snapshot_directory.0)?;
+                    crate::capture::write_captures(
+                        &self.crawler_settings.project_directory,
+                        rel_html_path,
+                        &self.crawler_settings.capture,
+                        &captures,
+                    )?
+                }
+            };
             // - -
             let outgoing_links = outgoing_anchors
-                .iter()
+                .keys()
                 .map(|url| {
                     OriginalUrl::from(url.to_owned())
                 })
                 .collect::<IndexSet<_>>();
             // - SNAPSHOT LOG -
-            self.project.persist_snapshot_log(&snapshot_directory, {
-                SnapshotLog {
-                    http_status: status_code,
-                    original_url: OriginalUrl(url.clone()),
-                    canonical_url: canonical_url.clone(),
-                    snapshot_path: rel_html_path,
-                    snapshot_date: SnapshotDate::now(),
-                    outgoing_links,
-                    incoming_links: Default::default(),
+            let expand_links = duplicate_of.is_none() || dedup.expand_links;
+            let snapshot_log = SnapshotLog {
+                http_status: status_code,
+                original_url: OriginalUrl(url.clone()),
+                canonical_url: canonical_url.clone(),
+                snapshot_path: rel_html_path.clone(),
+                snapshot_date,
+                outgoing_links,
+                incoming_links,
+                discovered_via: Some(entry.source.clone()),
+                depth: Some(entry.depth),
+                content_hash: Some(content_hash),
+                last_checked: Some(now),
+                simhash: simhash.map(crate::dedup::format_simhash),
+                duplicate_of,
+                screenshot_path: capture_paths.screenshot,
+                pdf_path: capture_paths.pdf,
+                har_path: capture_paths.har,
+                response: tab.response().cloned(),
+                redirect_chain: redirect_chain.to_vec(),
+                versions,
+            };
+            // - WARC -
+            if let Some(warc) = self.warc.lock().unwrap_or_else(std::sync::PoisonError::into_inner).as_mut() {
+                let written = Self::write_warc_page(
+                    warc,
+                    &snapshot_log,
+                    unchanged,
+                    html.as_bytes(),
+                    &self.crawler_settings.capture,
+                    &captures,
+                );
+                if let Err(error) = written {
+                    let warning = CrawlWarning::WarcOutput { url: Some(url), error: error.to_string() };
+                    self.notify(|observer| observer.warning(&warning));
                 }
-            }).unwrap();
-            // - TASK LOG -
-            self.project.persist_task_log(&snapshot_directory, {
-                task_log.entries.push(Status::Success {
-                    url: OriginalUrl(url.clone()),
-                    http_status: status_code,
-                });
-                task_log
-            }).unwrap();
-            // - UPDATE -
-            self.fully_resolved.insert(url.to_owned());
-            self.fully_resolved.insert(canonical_url.0.clone());
+            }
+            {
+                let mut state = self.state.lock().await;
+                // - UPDATE QUEUE -
+                if expand_links {
+                    self.enqueue_urls(
+                        &mut state,
+                        outgoing_anchors
+                            .iter()
+                            .map(|(link, anchor_text)| FrontierEntry {
+                                url: link.clone(),
+                                source: DiscoverySource::Link { referrer: OriginalUrl(url.clone()) },
+                                depth: entry.depth + 1,
+                                anchor_text: anchor_text.clone(),
+                            }),
+                    );
+                }
+                state.project
+                    .persist_snapshot_log(snapshot_log.clone())
+                    .map_err(|error| CrawlError::Metadata(error.to_string()))?;
+            }
+            Ok::<_, CrawlError>(snapshot_log)
+        }.await;
+        let snapshot_log = match snapshot_log {
+            Ok(snapshot_log) => snapshot_log,
+            Err(error) => {
+                if simhash.is_some() {
+                    let mut simhashes = simhashes();
+                    match indexed_before {
+                        Some(fingerprint) => simhashes.insert(rel_html_path.clone(), fingerprint),
+                        None => simhashes.remove(rel_html_path),
+                    }
+                }
+                return Err(error)
+            }
+        };
+        self.notify(|observer| observer.snapshot_written(&snapshot_log));
+        task_log.entries.push(Status::Success {
+            url: OriginalUrl(url.clone()),
+            http_status: status_code,
+        });
+        Ok(())
+    }
+    /// Records for a page snapshot: a full capture, a revisit if its content
+    /// is unchanged, or only metadata for a near-duplicate.
+    fn write_warc_page(
+        warc: &mut WarcWriter,
+        snapshot_log: &SnapshotLog,
+        unchanged: bool,
+        html: &[u8],
+        capture: &crate::capture::CaptureSettings,
+        captures: &crate::capture::PageCaptures,
+    ) -> std::io::Result<()> {
+        let url = &snapshot_log.original_url.0;
+        let date = snapshot_log.last_checked.as_ref().unwrap_or(&snapshot_log.snapshot_date);
+        let metadata = crate::warc::snapshot_metadata(snapshot_log);
+        if snapshot_log.duplicate_of.is_some() {
+            return warc.write_metadata(url, date, None, &metadata)
         }
-        // - CLOSE -
-        tab.close().await;
+        let page = WarcPage {
+            url,
+            date,
+            http_status: snapshot_log.http_status,
+            content_type: "text/html; charset=utf-8",
+            body: html,
+            response: snapshot_log.response.as_ref(),
+            metadata,
+        };
+        if unchanged {
+            return warc.write_revisit(&page, &snapshot_log.snapshot_date)
+        }
+        warc.write_page(&page)?;
+        if let (Some(bytes), Some(options)) = (captures.screenshot.as_ref(), capture.screenshot.as_ref()) {
+            let content_type = match options.format {
+                crate::capture::ScreenshotFormat::Png => "image/png",
+                crate::capture::ScreenshotFormat::Jpeg => "image/jpeg",
+            };
+            warc.write_rendering("screenshot", url, date, content_type, bytes)?;
+        }
+        if let Some(bytes) = captures.pdf.as_ref() {
+            warc.write_rendering("pdf", url, date, "application/pdf", bytes)?;
+        }
+        if let Some(bytes) = captures.har.as_ref() {
+            warc.write_rendering("har", url, date, "application/json", bytes)?;
+        }
+        Ok(())
+    }
+    /// Download a non-HTML document into the snapshot directory, if the
+    /// project keeps documents of its type.
+    async fn capture_resource(
+        &self,
+        entry: &FrontierEntry,
+        canonical_url: &CanonicalUrl,
+        paths: &SnapshotPaths,
+        content_type: &str,
+        task_log: &mut TaskLog,
+    ) -> Result<(), CrawlError> {
+        let url = &entry.url;
+        let settings = &self.crawler_settings.resources;
+        if !settings.enabled {
+            return Err(CrawlError::NotHtml)
+        }
+        let check_allowed = |content_type: &str| {
+            match settings.is_allowed(content_type) {
+                true => Ok(()),
+                false => Err(CrawlError::ResourceRejected(format!("{content_type} is not in the allow-list"))),
+            }
+        };
+        check_allowed(content_type)?;
+        let response = self.fetcher
+            .download(&canonical_url.0, settings.max_size)
+            .await
+            .map_err(|error| match error {
+                DownloadError::TooLarge { .. } => CrawlError::ResourceRejected(error.to_string()),
+                DownloadError::Request(error) => CrawlError::Download(error.to_string()),
+            })?;
+        let http_status = i64::from(response.http_status);
+        if !response.is_success() {
+            return Err(CrawlError::HttpStatus(http_status))
+        }
+        // The server has the final say on what was downloaded.
+        let content_type = response.content_type
+            .clone()
+            .unwrap_or_else(|| content_type.to_string());
+        check_allowed(&content_type)?;
+        // - WRITE -
+        let file_name = crate::resources::resource_file_name(&content_type, &response.url);
+        if self.crawler_settings.warc.output.writes_directory() {
+            std::fs::create_dir_all(&paths.snapshot_directory.0)?;
+            std::fs::write(paths.snapshot_directory.join(&file_name), &response.body)?;
+        }
+        if let Some(warc) = self.warc.lock().unwrap_or_else(std::sync::PoisonError::into_inner).as_mut() {
+            warc.write_page(&WarcPage {
+                url,
+                date: &SnapshotDate::now(),
+                http_status: Some(http_status),
+                content_type: &content_type,
+                body: &response.body,
+                response: None,
+                metadata: Vec::new(),
+            })?;
+        }
+        let size = response.body.len() as u64;
+        self.notify(|observer| observer.resource_saved(url, &content_type, size));
+        task_log.entries.push(Status::Resource {
+            url: OriginalUrl(url.clone()),
+            http_status: Some(http_status),
+            content_type,
+            size,
+            file_path: RelativeFilePath(paths.rel_html_path.0.with_file_name(file_name)),
+        });
+        Ok(())
+    }
+    /// A task log for a new attempt at `entry`, continuing the attempt count
+    /// if the previous visit failed.
+    async fn start_task_log(&self, entry: &FrontierEntry, canonical_url: &CanonicalUrl) -> TaskLog {
+        let state = self.state.lock().await;
+        let previous = state.project
+            .task_log_for(&entry.url)
+            .or_else(|| state.project.task_log_for(&canonical_url.0));
+        let attempts = match previous {
+            Some(previous) if previous.contains_failures() => previous.attempts() + 1,
+            _ => 1,
+        };
+        TaskLog {
+            attempts,
+            last_attempt: Some(SnapshotDate::now()),
+            discovered_via: Some(entry.source.clone()),
+            depth: Some(entry.depth),
+            entries: Vec::new(),
+        }
+    }
+    /// Report `error`, append it to `task_log` and finish the task.
+    async fn record_failure(
+        &self,
+        entry: &FrontierEntry,
+        canonical_url: &CanonicalUrl,
+        mut task_log: TaskLog,
+        http_status: Option<i64>,
+        error: CrawlError,
+    ) -> bool {
+        let url = &entry.url;
+        self.notify(|observer| observer.page_failed(url, &error));
+        task_log.entries.push(Status::Failure {
+            url: OriginalUrl(url.clone()),
+            http_status,
+            reason: Some(error.to_failure_reason()),
+        });
+        self.finish_task(entry, canonical_url, task_log).await
+    }
+    /// Persist `task_log`, then either schedule a retry, if the retry policy
+    /// allows one, or mark the URL as resolved. Returns whether a retry was
+    /// scheduled.
+    async fn finish_task(
+        &self,
+        entry: &FrontierEntry,
+        canonical_url: &CanonicalUrl,
+        task_log: TaskLog,
+    ) -> bool {
+        let url = &entry.url;
+        let retry = &self.crawler_settings.retry;
+        let retrying = retry.should_retry(&task_log);
+        let attempts = task_log.attempts();
+        let mut state = self.state.lock().await;
+        if let Err(error) = state.project.persist_task_log(task_log) {
+            let warning = CrawlWarning::TaskLog { url, error: error.to_string() };
+            self.notify(|observer| observer.warning(&warning));
+        }
+        if retrying {
+            let delay = retry.backoff(attempts);
+            self.notify(|observer| observer.retry_scheduled(url, attempts + 1, delay));
+            state.delayed.push(std::time::Instant::now() + delay, entry.clone());
+            return true
+        }
+        state.fully_resolved.insert(url.to_owned());
+        state.fully_resolved.insert(canonical_url.0.clone());
+        false
+    }
+}
+
+/// Entries waiting out a retry backoff, indexed by URL so that the workers
+/// can check for one without scanning the whole list.
+///
+/// A URL waits here at most once: whoever schedules a retry owns it until it
+/// is due.
+#[derive(Default)]
+struct DelayedEntries {
+    entries: Vec<(std::time::Instant, FrontierEntry)>,
+    urls: HashSet<Url>,
+}
+
+impl DelayedEntries {
+    fn push(&mut self, due_at: std::time::Instant, entry: FrontierEntry) {
+        self.urls.insert(entry.url.clone());
+        self.entries.push((due_at, entry));
+    }
+    fn contains(&self, url: &Url) -> bool {
+        self.urls.contains(url)
+    }
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+    fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+    /// Removes and returns the entries whose backoff has run out by `now`.
+    fn take_due(&mut self, now: std::time::Instant) -> Vec<FrontierEntry> {
+        let (due, waiting) = std::mem::take(&mut self.entries)
+            .into_iter()
+            .partition::<Vec<_>, _>(|(due_at, _)| *due_at <= now);
+        self.entries = waiting;
+        due.into_iter()
+            .map(|(_, entry)| {
+                self.urls.remove(&entry.url);
+                entry
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    fn entry(url: &str) -> FrontierEntry {
+        FrontierEntry {
+            url: Url::parse(url).unwrap(),
+            source: DiscoverySource::Seed,
+            depth: 0,
+            anchor_text: None,
+        }
+    }
+
+    #[test]
+    fn delayed_entries_are_released_once_due() {
+        let now = Instant::now();
+        let mut delayed = DelayedEntries::default();
+        delayed.push(now, entry("https://example.com/due"));
+        delayed.push(now + Duration::from_secs(60), entry("https://example.com/waiting"));
+        assert!(delayed.contains(&Url::parse("https://example.com/due").unwrap()));
+        let due = delayed.take_due(now);
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].url.as_str(), "https://example.com/due");
+        assert!(!delayed.contains(&Url::parse("https://example.com/due").unwrap()));
+        assert!(delayed.contains(&Url::parse("https://example.com/waiting").unwrap()));
+        assert_eq!(delayed.len(), 1);
     }
 }
@@ -0,0 +1,108 @@
+use web_client_bot::ClientError;
+
+use crate::metadata::common::{FailureKind, FailureReason};
+
+/// Why crawling a single URL failed, or why the crawl could not start.
+///
+/// Per-URL errors never abort the crawl; they are recorded as a
+/// `Status::Failure` with a [`FailureReason`] and reported to observers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CrawlError {
+    /// No response was received for the main document.
+    NoResponse,
+    /// The main document returned a status other than 200. The page is still
+    /// snapshotted.
+    HttpStatus(i64),
+    /// The main document is not `text/html`.
+    NotHtml,
+    /// The page did not settle in time. It is still snapshotted as it was.
+    Settle(String),
+    /// Driving the browser failed.
+    Browser(ClientError),
+    /// An operation on the page did not finish in time.
+    Timeout(String),
+    /// The page reported a URL that could not be parsed.
+    InvalidUrl(String),
+    /// Reading or writing the project directory failed.
+    Io(String),
+    /// Reading or writing a metadata log failed.
+    Metadata(String),
+    /// A non-HTML document is not in the MIME allow-list or too large.
+    ResourceRejected(String),
+    /// Downloading a non-HTML document failed.
+    Download(String),
+    /// The HTTP client for robots.txt, sitemaps and downloads could not be
+    /// set up.
+    HttpClient(String),
+}
+
+impl CrawlError {
+    pub fn kind(&self) -> FailureKind {
+        match self {
+            Self::NoResponse => FailureKind::NoResponse,
+            Self::HttpStatus(_) => FailureKind::HttpStatus,
+            Self::NotHtml => FailureKind::NotHtml,
+            Self::Settle(_) => FailureKind::Settle,
+            Self::Browser(ClientError::Timeout(_)) => FailureKind::Timeout,
+            Self::Browser(_) => FailureKind::Browser,
+            Self::Timeout(_) => FailureKind::Timeout,
+            Self::InvalidUrl(_) => FailureKind::InvalidUrl,
+            Self::Io(_) => FailureKind::Io,
+            Self::Metadata(_) => FailureKind::Metadata,
+            Self::ResourceRejected(_) => FailureKind::ResourceRejected,
+            Self::Download(_) => FailureKind::Download,
+            Self::HttpClient(_) => FailureKind::Download,
+        }
+    }
+    pub fn to_failure_reason(&self) -> FailureReason {
+        FailureReason {
+            kind: self.kind(),
+            message: self.to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for CrawlError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NoResponse => write!(f, "no response for the main document"),
+            Self::HttpStatus(http_status) => write!(f, "main document returned HTTP {http_status}"),
+            Self::NotHtml => write!(f, "main document is not an HTML document"),
+            Self::Settle(message) => write!(f, "page did not settle: {message}"),
+            Self::Browser(error) => write!(f, "{error}"),
+            Self::Timeout(message) => write!(f, "timed out: {message}"),
+            Self::InvalidUrl(message) => write!(f, "invalid URL: {message}"),
+            Self::Io(message) => write!(f, "I/O error: {message}"),
+            Self::Metadata(message) => write!(f, "failed to persist metadata: {message}"),
+            Self::ResourceRejected(message) => write!(f, "resource rejected: {message}"),
+            Self::Download(message) => write!(f, "download failed: {message}"),
+            Self::HttpClient(message) => write!(f, "failed to set up the HTTP client: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for CrawlError {}
+
+impl From<ClientError> for CrawlError {
+    fn from(error: ClientError) -> Self {
+        Self::Browser(error)
+    }
+}
+
+impl From<web_client_bot::utils::TimeoutError> for CrawlError {
+    fn from(error: web_client_bot::utils::TimeoutError) -> Self {
+        Self::Timeout(error.0)
+    }
+}
+
+impl From<std::io::Error> for CrawlError {
+    fn from(error: std::io::Error) -> Self {
+        Self::Io(error.to_string())
+    }
+}
+
+impl From<url::ParseError> for CrawlError {
+    fn from(error: url::ParseError) -> Self {
+        Self::InvalidUrl(error.to_string())
+    }
+}
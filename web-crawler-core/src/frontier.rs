@@ -0,0 +1,455 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, VecDeque};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::metadata::common::DiscoverySource;
+
+/// A URL waiting in the crawl frontier.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FrontierEntry {
+    pub url: Url,
+    pub source: DiscoverySource,
+    /// Number of links followed from a seed to reach this URL.
+    pub depth: usize,
+    /// Text of the anchor this URL was found in, for scoring.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub anchor_text: Option<String>,
+}
+
+impl FrontierEntry {
+    pub fn seed(url: Url) -> Self {
+        Self { url, source: DiscoverySource::Seed, depth: 0, anchor_text: None }
+    }
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// ORDERING
+// ————————————————————————————————————————————————————————————————————————————
+
+/// The queue of URLs waiting to be visited; decides which one comes next.
+pub trait Frontier: Send + Sync {
+    fn push(&mut self, entry: FrontierEntry);
+    fn pop(&mut self) -> Option<FrontierEntry>;
+    /// Return an entry that was popped but not processed, so that it is
+    /// handed out again first.
+    fn put_back(&mut self, entry: FrontierEntry);
+    fn len(&self) -> usize;
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Visit URLs in the order they were found.
+#[derive(Debug, Default)]
+pub struct BreadthFirst(VecDeque<FrontierEntry>);
+
+impl Frontier for BreadthFirst {
+    fn push(&mut self, entry: FrontierEntry) {
+        self.0.push_back(entry);
+    }
+    fn pop(&mut self) -> Option<FrontierEntry> {
+        self.0.pop_front()
+    }
+    fn put_back(&mut self, entry: FrontierEntry) {
+        self.0.push_front(entry);
+    }
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+}
+
+/// Visit the most recently found URL first.
+#[derive(Debug, Default)]
+pub struct DepthFirst(Vec<FrontierEntry>);
+
+impl Frontier for DepthFirst {
+    fn push(&mut self, entry: FrontierEntry) {
+        self.0.push(entry);
+    }
+    fn pop(&mut self) -> Option<FrontierEntry> {
+        self.0.pop()
+    }
+    fn put_back(&mut self, entry: FrontierEntry) {
+        self.0.push(entry);
+    }
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+}
+
+/// Visit the highest scoring URL first; equal scores keep discovery order.
+pub struct BestFirst {
+    scorer: Arc<dyn FrontierScorer>,
+    heap: BinaryHeap<ScoredEntry>,
+    /// Insertion counter used as the tie-breaker; counts up for pushed
+    /// entries and down for put back ones, which go ahead of their equals.
+    pushed: i64,
+    put_back: i64,
+}
+
+impl BestFirst {
+    pub fn new(scorer: Arc<dyn FrontierScorer>) -> Self {
+        Self { scorer, heap: BinaryHeap::new(), pushed: 0, put_back: 0 }
+    }
+}
+
+impl Frontier for BestFirst {
+    fn push(&mut self, entry: FrontierEntry) {
+        let score = self.scorer.score(&entry);
+        self.pushed += 1;
+        self.heap.push(ScoredEntry { score, sequence: self.pushed, entry });
+    }
+    fn pop(&mut self) -> Option<FrontierEntry> {
+        self.heap.pop().map(|scored| scored.entry)
+    }
+    /// Its own score puts the entry back in front, unless something scored
+    /// higher was pushed in the meantime.
+    fn put_back(&mut self, entry: FrontierEntry) {
+        let score = self.scorer.score(&entry);
+        self.put_back -= 1;
+        self.heap.push(ScoredEntry { score, sequence: self.put_back, entry });
+    }
+    fn len(&self) -> usize {
+        self.heap.len()
+    }
+}
+
+struct ScoredEntry {
+    score: f64,
+    sequence: i64,
+    entry: FrontierEntry,
+}
+
+impl Ord for ScoredEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.score
+            .total_cmp(&other.score)
+            .then_with(|| other.sequence.cmp(&self.sequence))
+    }
+}
+
+impl PartialOrd for ScoredEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for ScoredEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for ScoredEntry {}
+
+// ————————————————————————————————————————————————————————————————————————————
+// SCORING
+// ————————————————————————————————————————————————————————————————————————————
+
+/// Priority of a URL for [`BestFirst`]; higher is visited sooner.
+pub trait FrontierScorer: Send + Sync {
+    fn score(&self, entry: &FrontierEntry) -> f64;
+}
+
+impl<F> FrontierScorer for F
+where
+    F: Fn(&FrontierEntry) -> f64 + Send + Sync,
+{
+    fn score(&self, entry: &FrontierEntry) -> f64 {
+        self(entry)
+    }
+}
+
+/// Scores URLs by keywords in their path and anchor text, and by depth.
+///
+/// Keywords match case-insensitively as substrings; every matching keyword
+/// adds its weight.
+#[derive(Debug, Clone, Default)]
+pub struct KeywordScorer {
+    pub path_keywords: Vec<(String, f64)>,
+    pub anchor_keywords: Vec<(String, f64)>,
+    /// Added once per level of depth; negative values favor shallow pages.
+    pub depth_weight: f64,
+}
+
+impl FrontierScorer for KeywordScorer {
+    fn score(&self, entry: &FrontierEntry) -> f64 {
+        let path = entry.url.path().to_lowercase();
+        let anchor_text = entry.anchor_text
+            .as_deref()
+            .unwrap_or_default()
+            .to_lowercase();
+        let matching = |keywords: &[(String, f64)], haystack: &str| -> f64 {
+            keywords
+                .iter()
+                .filter(|(keyword, _)| haystack.contains(&keyword.to_lowercase()))
+                .map(|(_, weight)| weight)
+                .sum()
+        };
+        matching(&self.path_keywords, &path)
+            + matching(&self.anchor_keywords, &anchor_text)
+            + self.depth_weight * entry.depth as f64
+    }
+}
+
+/// Which [`Frontier`] a crawl uses.
+#[derive(Clone, Default)]
+pub enum FrontierOrder {
+    #[default]
+    BreadthFirst,
+    DepthFirst,
+    BestFirst(Arc<dyn FrontierScorer>),
+}
+
+impl FrontierOrder {
+    pub fn build(&self) -> Box<dyn Frontier> {
+        match self {
+            Self::BreadthFirst => Box::new(BreadthFirst::default()),
+            Self::DepthFirst => Box::new(DepthFirst::default()),
+            Self::BestFirst(scorer) => Box::new(BestFirst::new(scorer.clone())),
+        }
+    }
+}
+
+impl std::fmt::Debug for FrontierOrder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::BreadthFirst => write!(f, "BreadthFirst"),
+            Self::DepthFirst => write!(f, "DepthFirst"),
+            Self::BestFirst(_) => write!(f, "BestFirst(..)"),
+        }
+    }
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// PERSISTENCE
+// ————————————————————————————————————————————————————————————————————————————
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "kebab-case")]
+enum FrontierRecord {
+    Push(FrontierEntry),
+    Done { url: Url },
+}
+
+/// Append-only journal of the frontier, stored as JSON lines in the project
+/// directory.
+///
+/// Every enqueued URL is written as a `push` record and every URL a worker is
+/// finished with as a `done` record. Replaying the journal yields the URLs
+/// that were still pending, including those a worker was in the middle of
+/// when the process died.
+#[derive(Debug)]
+pub struct FrontierLog {
+    file_path: PathBuf,
+    file: File,
+}
+
+impl FrontierLog {
+    pub const FILE_NAME: &'static str = ".frontier.jsonl";
+    pub fn file_path(project_directory: impl AsRef<Path>) -> PathBuf {
+        project_directory.as_ref().join(Self::FILE_NAME)
+    }
+    /// Replay the journal, returning pending entries in queue order, or
+    /// `None` if the project has no journal yet.
+    pub fn load(project_directory: impl AsRef<Path>) -> Result<Option<Vec<FrontierEntry>>, Box<dyn std::error::Error>> {
+        let file_path = Self::file_path(project_directory);
+        if !file_path.exists() {
+            return Ok(None)
+        }
+        let reader = BufReader::new(File::open(&file_path)?);
+        let mut pending = IndexMap::<Url, FrontierEntry>::new();
+        for line in reader.lines() {
+            let line = line?;
+            // A crash mid-write can leave a truncated last line behind.
+            let Ok(record) = serde_json::from_str::<FrontierRecord>(&line) else {
+                continue
+            };
+            match record {
+                FrontierRecord::Push(entry) => {
+                    pending.entry(entry.url.clone()).or_insert(entry);
+                }
+                FrontierRecord::Done { url } => {
+                    pending.shift_remove(&url);
+                }
+            }
+        }
+        Ok(Some(pending.into_values().collect()))
+    }
+    /// Start a fresh journal containing only `pending`, then keep appending.
+    pub fn open<'a>(
+        project_directory: impl AsRef<Path>,
+        pending: impl IntoIterator<Item=&'a FrontierEntry>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let project_directory = project_directory.as_ref();
+        std::fs::create_dir_all(project_directory)?;
+        let file_path = Self::file_path(project_directory);
+        let compacted_path = file_path.with_extension("jsonl.tmp");
+        {
+            let mut compacted = File::create(&compacted_path)?;
+            for entry in pending {
+                let line = serde_json::to_string(&FrontierRecord::Push(entry.clone()))?;
+                writeln!(compacted, "{line}")?;
+            }
+            compacted.sync_all()?;
+        }
+        std::fs::rename(&compacted_path, &file_path)?;
+        let file = File::options().append(true).open(&file_path)?;
+        Ok(Self { file_path, file })
+    }
+    pub fn record_push(&mut self, entry: &FrontierEntry) -> Result<(), Box<dyn std::error::Error>> {
+        self.append(&FrontierRecord::Push(entry.clone()))
+    }
+    pub fn record_done(&mut self, url: &Url) -> Result<(), Box<dyn std::error::Error>> {
+        self.append(&FrontierRecord::Done { url: url.clone() })
+    }
+    /// Make sure everything written so far reached the disk.
+    pub fn flush(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.file.sync_data()?;
+        Ok(())
+    }
+    pub fn path(&self) -> &Path {
+        &self.file_path
+    }
+    fn append(&mut self, record: &FrontierRecord) -> Result<(), Box<dyn std::error::Error>> {
+        let mut line = serde_json::to_string(record)?;
+        line.push('\n');
+        self.file.write_all(line.as_bytes())?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TempDirectory;
+
+    fn entry(path: &str) -> FrontierEntry {
+        FrontierEntry::seed(Url::parse(&format!("https://example.com{path}")).unwrap())
+    }
+
+    fn paths(entries: &[FrontierEntry]) -> Vec<&str> {
+        entries.iter().map(|entry| entry.url.path()).collect()
+    }
+
+    fn drain(frontier: &mut dyn Frontier) -> Vec<String> {
+        std::iter::from_fn(|| frontier.pop())
+            .map(|entry| entry.url.path().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn breadth_first_visits_in_discovery_order() {
+        let mut frontier = FrontierOrder::BreadthFirst.build();
+        for path in ["/a", "/b", "/c"] {
+            frontier.push(entry(path));
+        }
+        let first = frontier.pop().unwrap();
+        frontier.push(entry("/d"));
+        frontier.put_back(first);
+        assert_eq!(frontier.len(), 4);
+        assert_eq!(drain(frontier.as_mut()), ["/a", "/b", "/c", "/d"]);
+    }
+
+    #[test]
+    fn depth_first_visits_the_latest_discovery_first() {
+        let mut frontier = FrontierOrder::DepthFirst.build();
+        for path in ["/a", "/b", "/c"] {
+            frontier.push(entry(path));
+        }
+        let first = frontier.pop().unwrap();
+        frontier.put_back(first);
+        assert_eq!(drain(frontier.as_mut()), ["/c", "/b", "/a"]);
+        assert!(frontier.is_empty());
+    }
+
+    #[test]
+    fn best_first_visits_by_score_then_discovery_order() {
+        let scorer = KeywordScorer {
+            path_keywords: vec![(String::from("docs"), 10.0)],
+            anchor_keywords: vec![(String::from("guide"), 5.0)],
+            depth_weight: -1.0,
+        };
+        let mut frontier = FrontierOrder::BestFirst(Arc::new(scorer)).build();
+        let with = |path: &str, depth: usize, anchor_text: Option<&str>| FrontierEntry {
+            depth,
+            anchor_text: anchor_text.map(String::from),
+            ..entry(path)
+        };
+        frontier.push(with("/blog", 1, None));
+        frontier.push(with("/about", 1, None));
+        frontier.push(with("/DOCS/intro", 2, None));
+        frontier.push(with("/start", 1, Some("Getting started Guide")));
+        frontier.push(with("/docs/deep", 6, Some("guide")));
+        frontier.push(with("/home", 0, None));
+        // Scores: 13, 8, 4, 0, -1, -1.
+        assert_eq!(drain(frontier.as_mut()), ["/docs/deep", "/DOCS/intro", "/start", "/home", "/blog", "/about"]);
+    }
+
+    #[test]
+    fn a_put_back_entry_comes_first_among_equals() {
+        let mut frontier = FrontierOrder::BestFirst(Arc::new(|_: &FrontierEntry| 0.0)).build();
+        frontier.push(entry("/a"));
+        frontier.push(entry("/b"));
+        frontier.push(entry("/c"));
+        let first = frontier.pop().unwrap();
+        let second = frontier.pop().unwrap();
+        assert_eq!(first.url.path(), "/a");
+        frontier.put_back(second);
+        frontier.put_back(first);
+        assert_eq!(drain(frontier.as_mut()), ["/a", "/b", "/c"]);
+    }
+
+    #[test]
+    fn a_project_without_a_journal_loads_nothing() {
+        let directory = TempDirectory::new("frontier-missing");
+        assert_eq!(FrontierLog::load(&directory).unwrap(), None);
+    }
+
+    #[test]
+    fn replay_keeps_pushed_entries_that_are_not_done() {
+        let directory = TempDirectory::new("frontier-replay");
+        let mut log = FrontierLog::open(&directory, &[entry("/a")]).unwrap();
+        log.record_push(&entry("/b")).unwrap();
+        log.record_push(&entry("/c")).unwrap();
+        log.record_done(&entry("/a").url).unwrap();
+        // A second push of a pending URL keeps its first position.
+        log.record_push(&entry("/b")).unwrap();
+        log.flush().unwrap();
+        let pending = FrontierLog::load(&directory).unwrap().unwrap();
+        assert_eq!(paths(&pending), ["/b", "/c"]);
+    }
+
+    #[test]
+    fn a_truncated_last_line_is_skipped() {
+        let directory = TempDirectory::new("frontier-truncated");
+        let mut log = FrontierLog::open(&directory, &[entry("/a")]).unwrap();
+        log.record_push(&entry("/b")).unwrap();
+        drop(log);
+        let mut file = File::options().append(true).open(FrontierLog::file_path(&directory)).unwrap();
+        write!(file, r#"{{"op":"push","url":"https://example.com/c","sou"#).unwrap();
+        let pending = FrontierLog::load(&directory).unwrap().unwrap();
+        assert_eq!(paths(&pending), ["/a", "/b"]);
+    }
+
+    #[test]
+    fn opening_compacts_the_journal_to_the_pending_entries() {
+        let directory = TempDirectory::new("frontier-compact");
+        let mut log = FrontierLog::open(&directory, &[entry("/a"), entry("/b")]).unwrap();
+        log.record_done(&entry("/a").url).unwrap();
+        drop(log);
+        let pending = FrontierLog::load(&directory).unwrap().unwrap();
+        let mut log = FrontierLog::open(&directory, &pending).unwrap();
+        log.flush().unwrap();
+        let journal = std::fs::read_to_string(log.path()).unwrap();
+        assert_eq!(journal.lines().count(), 1);
+        assert!(!journal.contains("https://example.com/a"));
+        assert_eq!(paths(&FrontierLog::load(&directory).unwrap().unwrap()), ["/b"]);
+    }
+}
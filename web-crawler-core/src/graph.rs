@@ -0,0 +1,451 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::Write;
+use indexmap::IndexSet;
+use serde::Serialize;
+use url::Url;
+
+use crate::canonicalize::UrlNormalizer;
+use crate::metadata::common::{DiscoverySource, OriginalUrl, RelativeFilePath};
+use crate::metadata::project::ProjectLog;
+use crate::metadata::snapshot::SnapshotLog;
+
+// ————————————————————————————————————————————————————————————————————————————
+// GRAPH
+// ————————————————————————————————————————————————————————————————————————————
+
+/// The link structure between the snapshotted pages of a project.
+#[derive(Debug, Clone, Serialize)]
+pub struct LinkGraph {
+    pub nodes: Vec<LinkGraphNode>,
+    pub edges: Vec<LinkGraphEdge>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LinkGraphNode {
+    /// Index into `LinkGraph::nodes`.
+    pub id: usize,
+    pub url: Url,
+    pub snapshot_path: RelativeFilePath,
+    pub http_status: Option<i64>,
+    /// Number of other snapshotted pages linking here.
+    pub in_degree: usize,
+    /// Number of other snapshotted pages linked from here.
+    pub out_degree: usize,
+    /// Links to pages without a snapshot (out of scope, skipped or not
+    /// crawled yet).
+    pub unresolved_links: usize,
+    /// Fewest links from a seed, or `None` if no seed reaches this page.
+    pub depth: Option<usize>,
+    pub is_seed: bool,
+    /// Not a seed and not linked from any other page, e.g. only listed in a
+    /// sitemap.
+    pub is_orphan: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub struct LinkGraphEdge {
+    pub source: usize,
+    pub target: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkGraphFormat {
+    Json,
+    Dot,
+    GraphMl,
+}
+
+impl LinkGraphFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Dot => "dot",
+            Self::GraphMl => "graphml",
+        }
+    }
+}
+
+impl LinkGraph {
+    /// Build the graph from every snapshot log in `project`.
+    ///
+    /// Links are matched to pages by original URL, canonical URL, or the
+    /// canonical form of the link itself. Seeds are the given URLs plus
+    /// every page logged as discovered via a seed.
+    pub fn build<'a>(
+        project: &ProjectLog,
+        seed_urls: impl IntoIterator<Item=&'a Url>,
+        url_normalizer: &UrlNormalizer,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let logs = project.snapshot_logs()?;
+        let mut index = HashMap::<Url, usize>::new();
+        for (id, log) in logs.iter().enumerate() {
+            index.entry(log.original_url.0.clone()).or_insert(id);
+            index.entry(log.canonical_url.0.clone()).or_insert(id);
+            index.entry(url_normalizer.normalize(&log.original_url.0)).or_insert(id);
+        }
+        let resolve = |url: &Url| -> Option<usize> {
+            index
+                .get(url)
+                .or_else(|| index.get(&url_normalizer.normalize(url)))
+                .copied()
+        };
+        // - EDGES -
+        let mut edges = IndexSet::<LinkGraphEdge>::new();
+        let mut unresolved_links = vec![0usize; logs.len()];
+        for (source, log) in logs.iter().enumerate() {
+            for link in log.outgoing_links.iter() {
+                match resolve(&link.0) {
+                    Some(target) if target == source => (),
+                    Some(target) => {
+                        edges.insert(LinkGraphEdge { source, target });
+                    }
+                    None => unresolved_links[source] += 1,
+                }
+            }
+        }
+        let mut in_degree = vec![0usize; logs.len()];
+        let mut out_degree = vec![0usize; logs.len()];
+        let mut adjacency = vec![Vec::<usize>::new(); logs.len()];
+        for edge in edges.iter() {
+            out_degree[edge.source] += 1;
+            in_degree[edge.target] += 1;
+            adjacency[edge.source].push(edge.target);
+        }
+        // - DEPTH -
+        let mut seeds = seed_urls
+            .into_iter()
+            .filter_map(resolve)
+            .collect::<HashSet<_>>();
+        for (id, log) in logs.iter().enumerate() {
+            if log.discovered_via == Some(DiscoverySource::Seed) {
+                seeds.insert(id);
+            }
+        }
+        let mut depth = vec![None::<usize>; logs.len()];
+        let mut pending = VecDeque::<usize>::new();
+        for seed in seeds.iter() {
+            depth[*seed] = Some(0);
+            pending.push_back(*seed);
+        }
+        while let Some(id) = pending.pop_front() {
+            let next_depth = depth[id].unwrap() + 1;
+            for target in adjacency[id].iter() {
+                if depth[*target].is_none() {
+                    depth[*target] = Some(next_depth);
+                    pending.push_back(*target);
+                }
+            }
+        }
+        // - NODES -
+        let nodes = logs
+            .iter()
+            .enumerate()
+            .map(|(id, log)| {
+                let is_seed = seeds.contains(&id);
+                LinkGraphNode {
+                    id,
+                    url: log.original_url.0.clone(),
+                    snapshot_path: log.snapshot_path.clone(),
+                    http_status: log.http_status,
+                    in_degree: in_degree[id],
+                    out_degree: out_degree[id],
+                    unresolved_links: unresolved_links[id],
+                    depth: depth[id],
+                    is_seed,
+                    is_orphan: !is_seed && in_degree[id] == 0,
+                }
+            })
+            .collect::<Vec<_>>();
+        Ok(Self {
+            nodes,
+            edges: edges.into_iter().collect(),
+        })
+    }
+    pub fn orphans(&self) -> impl Iterator<Item=&LinkGraphNode> {
+        self.nodes.iter().filter(|node| node.is_orphan)
+    }
+    /// URLs of the pages linking to each node, indexed like `nodes`.
+    pub fn incoming_links(&self) -> Vec<IndexSet<OriginalUrl>> {
+        let mut incoming = vec![IndexSet::<OriginalUrl>::new(); self.nodes.len()];
+        for edge in self.edges.iter() {
+            incoming[edge.target].insert(OriginalUrl(self.nodes[edge.source].url.clone()));
+        }
+        incoming
+    }
+    /// Fill `SnapshotLog::incoming_links` for every page of the project this
+    /// graph was built from, and rewrite the logs that changed.
+    pub fn apply_incoming_links(
+        &self,
+        project: &mut ProjectLog,
+    ) -> Result<usize, Box<dyn std::error::Error>> {
+        let incoming = self.incoming_links();
+        let changed = project
+            .snapshot_logs()?
+            .into_iter()
+            .zip(incoming)
+            .filter(|(log, incoming_links)| &log.incoming_links != incoming_links)
+            .map(|(log, incoming_links)| SnapshotLog { incoming_links, ..log })
+            .collect::<Vec<_>>();
+        let updated = changed.len();
+        for log in changed {
+            project.persist_snapshot_log(log)?;
+        }
+        Ok(updated)
+    }
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// EXPORT
+// ————————————————————————————————————————————————————————————————————————————
+
+impl LinkGraph {
+    pub fn render(&self, format: LinkGraphFormat) -> String {
+        match format {
+            LinkGraphFormat::Json => self.to_json(),
+            LinkGraphFormat::Dot => self.to_dot(),
+            LinkGraphFormat::GraphMl => self.to_graphml(),
+        }
+    }
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+    /// Graphviz DOT; seeds are drawn as boxes and orphans in red.
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph \"link-graph\" {\n");
+        for node in self.nodes.iter() {
+            let shape = if node.is_seed { "box" } else { "ellipse" };
+            let color = if node.is_orphan { "red" } else { "black" };
+            let _ = writeln!(
+                out,
+                "  n{} [label=\"{}\", shape={shape}, color={color}];",
+                node.id,
+                escape_dot(node.url.as_str()),
+            );
+        }
+        for edge in self.edges.iter() {
+            let _ = writeln!(out, "  n{} -> n{};", edge.source, edge.target);
+        }
+        out.push_str("}\n");
+        out
+    }
+    pub fn to_graphml(&self) -> String {
+        let mut out = String::new();
+        out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        out.push_str("<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n");
+        let keys = [
+            ("url", "string"),
+            ("http_status", "long"),
+            ("in_degree", "int"),
+            ("out_degree", "int"),
+            ("unresolved_links", "int"),
+            ("depth", "int"),
+            ("is_seed", "boolean"),
+            ("is_orphan", "boolean"),
+        ];
+        for (name, kind) in keys {
+            let _ = writeln!(
+                out,
+                "  <key id=\"{name}\" for=\"node\" attr.name=\"{name}\" attr.type=\"{kind}\"/>",
+            );
+        }
+        out.push_str("  <graph id=\"link-graph\" edgedefault=\"directed\">\n");
+        for node in self.nodes.iter() {
+            let _ = writeln!(out, "    <node id=\"n{}\">", node.id);
+            let _ = writeln!(out, "      <data key=\"url\">{}</data>", escape_xml(node.url.as_str()));
+            if let Some(http_status) = node.http_status {
+                let _ = writeln!(out, "      <data key=\"http_status\">{http_status}</data>");
+            }
+            let _ = writeln!(out, "      <data key=\"in_degree\">{}</data>", node.in_degree);
+            let _ = writeln!(out, "      <data key=\"out_degree\">{}</data>", node.out_degree);
+            let _ = writeln!(out, "      <data key=\"unresolved_links\">{}</data>", node.unresolved_links);
+            if let Some(depth) = node.depth {
+                let _ = writeln!(out, "      <data key=\"depth\">{depth}</data>");
+            }
+            let _ = writeln!(out, "      <data key=\"is_seed\">{}</data>", node.is_seed);
+            let _ = writeln!(out, "      <data key=\"is_orphan\">{}</data>", node.is_orphan);
+            out.push_str("    </node>\n");
+        }
+        for (index, edge) in self.edges.iter().enumerate() {
+            let _ = writeln!(
+                out,
+                "    <edge id=\"e{index}\" source=\"n{}\" target=\"n{}\"/>",
+                edge.source,
+                edge.target,
+            );
+        }
+        out.push_str("  </graph>\n</graphml>\n");
+        out
+    }
+}
+
+fn escape_dot(source: &str) -> String {
+    source.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn escape_xml(source: &str) -> String {
+    source
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TempDirectory;
+
+    const SNAPSHOT_LOG: &str = r#"
+        http_status = 200
+        original_url = "https://example.com/"
+        canonical_url = "https://example.com/"
+        snapshot_path = "file://./example.com/source.snapshot.html"
+        snapshot_date = "2024-05-01T10:00:00+00:00"
+        outgoing_links = []
+        incoming_links = []
+    "#;
+
+    fn url(path: &str) -> Url {
+        Url::parse(&format!("https://example.com{path}")).unwrap()
+    }
+
+    fn snapshot_log(path: &str, links: &[&str], discovered_via: Option<DiscoverySource>) -> SnapshotLog {
+        SnapshotLog {
+            original_url: OriginalUrl(url(path)),
+            canonical_url: crate::metadata::common::CanonicalUrl(url(path)),
+            snapshot_path: RelativeFilePath(format!("example.com{path}/source.snapshot.html").into()),
+            outgoing_links: links
+                .iter()
+                .map(|link| OriginalUrl(Url::parse(link).unwrap_or_else(|_| url(link))))
+                .collect(),
+            discovered_via,
+            ..toml::from_str(SNAPSHOT_LOG).unwrap()
+        }
+    }
+
+    /// `/` is the seed and links to `/a` and `/b`; `/a` links to `/b`, back
+    /// to `/` and to itself; `/b` links off-site; `/c`, found in a sitemap,
+    /// links to `/b` but nothing links to it.
+    fn fixture_project(directory: &TempDirectory) -> ProjectLog {
+        let mut project = ProjectLog::load(directory).unwrap();
+        let sitemap = DiscoverySource::Sitemap { sitemap: OriginalUrl(url("/sitemap.xml")) };
+        let logs = [
+            snapshot_log("/", &["/a", "/b"], Some(DiscoverySource::Seed)),
+            snapshot_log("/a", &["/b", "/", "/a"], None),
+            snapshot_log("/b", &["https://other.com/"], None),
+            snapshot_log("/c", &["/b"], Some(sitemap)),
+        ];
+        for log in logs {
+            project.persist_snapshot_log(log).unwrap();
+        }
+        project
+    }
+
+    #[test]
+    fn builds_edges_depths_and_orphans() {
+        let directory = TempDirectory::new("graph-build");
+        let project = fixture_project(&directory);
+        let graph = LinkGraph::build(&project, [], &UrlNormalizer::default()).unwrap();
+        let paths = graph.nodes.iter().map(|node| node.url.path()).collect::<Vec<_>>();
+        assert_eq!(paths, ["/", "/a", "/b", "/c"]);
+        let edges = graph.edges.iter().map(|edge| (edge.source, edge.target)).collect::<Vec<_>>();
+        assert_eq!(edges, [(0, 1), (0, 2), (1, 2), (1, 0), (3, 2)]);
+        let depths = graph.nodes.iter().map(|node| node.depth).collect::<Vec<_>>();
+        assert_eq!(depths, [Some(0), Some(1), Some(1), None]);
+        let b = &graph.nodes[2];
+        assert_eq!((b.in_degree, b.out_degree, b.unresolved_links), (3, 0, 1));
+        assert!(graph.nodes[0].is_seed);
+        let orphans = graph.orphans().map(|node| node.url.path()).collect::<Vec<_>>();
+        assert_eq!(orphans, ["/c"]);
+    }
+
+    #[test]
+    fn given_seeds_count_even_when_not_logged_as_seeds() {
+        let directory = TempDirectory::new("graph-seeds");
+        let project = fixture_project(&directory);
+        let graph = LinkGraph::build(&project, [&url("/c")], &UrlNormalizer::default()).unwrap();
+        let depths = graph.nodes.iter().map(|node| node.depth).collect::<Vec<_>>();
+        assert_eq!(depths, [Some(0), Some(1), Some(1), Some(0)]);
+        assert_eq!(graph.orphans().count(), 0);
+    }
+
+    #[test]
+    fn incoming_links_land_on_the_log_of_their_node() {
+        let directory = TempDirectory::new("graph-incoming");
+        let mut project = fixture_project(&directory);
+        let graph = LinkGraph::build(&project, [], &UrlNormalizer::default()).unwrap();
+        assert_eq!(graph.apply_incoming_links(&mut project).unwrap(), 3);
+        let incoming = |path: &str| {
+            project
+                .snapshot_log_for(&url(path))
+                .unwrap()
+                .incoming_links
+                .iter()
+                .map(|link| link.0.path().to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(incoming("/"), ["/a"]);
+        assert_eq!(incoming("/a"), ["/"]);
+        assert_eq!(incoming("/b"), ["/", "/a", "/c"]);
+        assert!(incoming("/c").is_empty());
+        // Nothing changed since, so nothing is rewritten.
+        assert_eq!(graph.apply_incoming_links(&mut project).unwrap(), 0);
+    }
+
+    fn render_fixture() -> LinkGraph {
+        let node = |id: usize, source: &str, is_seed: bool| LinkGraphNode {
+            id,
+            url: Url::parse(source).unwrap(),
+            snapshot_path: RelativeFilePath(format!("page-{id}").into()),
+            http_status: Some(200),
+            in_degree: usize::from(!is_seed),
+            out_degree: usize::from(is_seed),
+            unresolved_links: 0,
+            depth: Some(usize::from(!is_seed)),
+            is_seed,
+            is_orphan: false,
+        };
+        LinkGraph {
+            nodes: vec![
+                node(0, "https://example.com/", true),
+                node(1, "https://example.com/search?q=a&page=2", false),
+            ],
+            edges: vec![LinkGraphEdge { source: 0, target: 1 }],
+        }
+    }
+
+    #[test]
+    fn renders_json() {
+        let json = serde_json::from_str::<serde_json::Value>(&render_fixture().render(LinkGraphFormat::Json)).unwrap();
+        assert_eq!(json["nodes"][1]["url"], "https://example.com/search?q=a&page=2");
+        assert_eq!(json["edges"][0]["target"], 1);
+    }
+
+    #[test]
+    fn renders_dot() {
+        let dot = render_fixture().render(LinkGraphFormat::Dot);
+        assert!(dot.starts_with("digraph \"link-graph\" {\n"));
+        assert!(dot.contains("  n0 [label=\"https://example.com/\", shape=box, color=black];\n"));
+        assert!(dot.contains("  n1 [label=\"https://example.com/search?q=a&page=2\", shape=ellipse, color=black];\n"));
+        assert!(dot.contains("  n0 -> n1;\n"));
+        assert!(dot.ends_with("}\n"));
+    }
+
+    #[test]
+    fn renders_graphml() {
+        let graphml = render_fixture().render(LinkGraphFormat::GraphMl);
+        assert!(graphml.contains("<data key=\"url\">https://example.com/search?q=a&amp;page=2</data>"));
+        assert!(graphml.contains("<edge id=\"e0\" source=\"n0\" target=\"n1\"/>"));
+        assert!(graphml.contains("<data key=\"is_seed\">true</data>"));
+        assert!(graphml.ends_with("</graphml>\n"));
+    }
+
+    #[test]
+    fn escapes_quotes_and_markup() {
+        // URLs percent-encode quotes, so they only reach the escapers through
+        // other labels.
+        assert_eq!(escape_dot(r#"say "hi" \ bye"#), r#"say \"hi\" \\ bye"#);
+        assert_eq!(escape_xml(r#"<a href="x">Tom & Jerry's</a>"#), "&lt;a href=&quot;x&quot;&gt;Tom &amp; Jerry&apos;s&lt;/a&gt;");
+    }
+}
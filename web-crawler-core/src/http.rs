@@ -0,0 +1,114 @@
+use std::time::Duration;
+use url::Url;
+
+use crate::error::CrawlError;
+
+/// Plain HTTP client for auxiliary files (robots.txt, sitemaps, …) that do
+/// not need a browser tab.
+#[derive(Debug, Clone)]
+pub struct HttpFetcher {
+    client: reqwest::Client,
+}
+
+#[derive(Debug, Clone)]
+pub struct HttpResponse {
+    pub url: Url,
+    pub http_status: u16,
+    pub content_type: Option<String>,
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.http_status)
+    }
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+}
+
+impl HttpFetcher {
+    pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(15);
+    pub const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(120);
+    pub fn new(user_agent: &str) -> Result<Self, CrawlError> {
+        let client = reqwest::Client::builder()
+            .user_agent(user_agent)
+            .timeout(Self::DEFAULT_TIMEOUT)
+            .build()
+            .map_err(|error| CrawlError::HttpClient(error.to_string()))?;
+        Ok(Self { client })
+    }
+    pub async fn get(&self, url: &Url) -> Result<HttpResponse, reqwest::Error> {
+        let response = self.client.get(url.clone()).send().await?;
+        let http_status = response.status().as_u16();
+        let final_url = response.url().clone();
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string());
+        let body = response.bytes().await?.to_vec();
+        Ok(HttpResponse {
+            url: final_url,
+            http_status,
+            content_type,
+            body,
+        })
+    }
+    /// Like [`Self::get`], but gives up as soon as the body is known to be
+    /// larger than `max_size` bytes.
+    pub async fn download(&self, url: &Url, max_size: Option<u64>) -> Result<HttpResponse, DownloadError> {
+        let mut response = self.client
+            .get(url.clone())
+            .timeout(Self::DOWNLOAD_TIMEOUT)
+            .send()
+            .await?;
+        let too_large = |size: u64| crate::resources::exceeded_max_size(size, max_size);
+        if let Some(max_size) = response.content_length().and_then(too_large) {
+            return Err(DownloadError::TooLarge { max_size })
+        }
+        let http_status = response.status().as_u16();
+        let final_url = response.url().clone();
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string());
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            body.extend_from_slice(&chunk);
+            if let Some(max_size) = too_large(body.len() as u64) {
+                return Err(DownloadError::TooLarge { max_size })
+            }
+        }
+        Ok(HttpResponse {
+            url: final_url,
+            http_status,
+            content_type,
+            body,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub enum DownloadError {
+    Request(reqwest::Error),
+    TooLarge { max_size: u64 },
+}
+
+impl std::fmt::Display for DownloadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Request(error) => write!(f, "{error}"),
+            Self::TooLarge { max_size } => write!(f, "larger than {max_size} bytes"),
+        }
+    }
+}
+
+impl std::error::Error for DownloadError {}
+
+impl From<reqwest::Error> for DownloadError {
+    fn from(error: reqwest::Error) -> Self {
+        Self::Request(error)
+    }
+}
@@ -3,3 +3,23 @@ pub mod settings;
 pub mod path_utils;
 pub mod metadata;
 pub mod engine;
+pub mod politeness;
+pub mod http;
+pub mod robots;
+pub mod sitemap;
+pub mod frontier;
+pub mod budget;
+pub mod canonicalize;
+pub mod scope;
+pub mod recrawl;
+pub mod retry;
+pub mod resources;
+pub mod dedup;
+pub mod capture;
+pub mod browser;
+pub mod warc;
+pub mod graph;
+pub mod observer;
+pub mod error;
+#[cfg(test)]
+mod test_utils;
@@ -2,6 +2,8 @@ use std::path::{Path, PathBuf};
 use serde::{Deserialize, Serialize};
 use url::Url;
 
+pub use web_client_bot::network::{DocumentResponse, RedirectHop, RedirectKind};
+
 // ————————————————————————————————————————————————————————————————————————————
 // BASICS
 // ————————————————————————————————————————————————————————————————————————————
@@ -44,15 +46,21 @@ impl SnapshotDate {
         let date = chrono::Utc::now();
         Self(date.to_rfc3339())
     }
+    /// Time elapsed since this date, or `None` if it is not valid RFC 3339.
+    pub fn age(&self) -> Option<std::time::Duration> {
+        let date = chrono::DateTime::parse_from_rfc3339(&self.0).ok()?;
+        let age = chrono::Utc::now().signed_duration_since(date);
+        Some(age.to_std().unwrap_or_default())
+    }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct RelativeFilePath(pub PathBuf);
 
 impl RelativeFilePath {
     /// Get a `file://./` prefixed string for JSON output.
     pub fn to_file_url(&self) -> String {
-        format!("file://./{}", self.0.to_str().unwrap())
+        format!("file://./{}", self.0.to_string_lossy())
     }
 }
 
@@ -78,6 +86,22 @@ impl<'de> Deserialize<'de> for RelativeFilePath {
     }
 }
 
+// ————————————————————————————————————————————————————————————————————————————
+// DISCOVERY
+// ————————————————————————————————————————————————————————————————————————————
+
+/// How a URL first entered the crawl frontier.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum DiscoverySource {
+    /// Listed in `ProjectSpec::seed_urls`.
+    Seed,
+    /// Found as an anchor on an already crawled page.
+    Link { referrer: OriginalUrl },
+    /// Listed in a sitemap.
+    Sitemap { sitemap: OriginalUrl },
+}
+
 // ————————————————————————————————————————————————————————————————————————————
 // STATUS
 // ————————————————————————————————————————————————————————————————————————————
@@ -86,8 +110,55 @@ impl<'de> Deserialize<'de> for RelativeFilePath {
 #[serde(tag = "type")]
 pub enum Status {
     Success { url: OriginalUrl, http_status: Option<i64> },
-    Failure { url: OriginalUrl, http_status: Option<i64> },
-    Redirected { from: OriginalUrl, to: OriginalUrl, http_status: Option<i64> },
+    Failure {
+        url: OriginalUrl,
+        http_status: Option<i64>,
+        /// Missing for logs written before failures were classified.
+        #[serde(default)]
+        reason: Option<FailureReason>,
+    },
+    Redirected {
+        from: OriginalUrl,
+        to: OriginalUrl,
+        http_status: Option<i64>,
+        /// Missing for logs written before redirect chains were recorded, and
+        /// when only the final location differed from the requested URL.
+        #[serde(default)]
+        kind: Option<RedirectKind>,
+    },
+    /// A non-HTML document downloaded as-is instead of snapshotted.
+    Resource {
+        url: OriginalUrl,
+        http_status: Option<i64>,
+        content_type: String,
+        /// In bytes.
+        size: u64,
+        /// Only written when the project writes the directory layout.
+        file_path: RelativeFilePath,
+    },
+}
+
+/// Why a URL ended up as a `Status::Failure`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct FailureReason {
+    pub kind: FailureKind,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FailureKind {
+    NoResponse,
+    HttpStatus,
+    NotHtml,
+    Settle,
+    Browser,
+    Timeout,
+    InvalidUrl,
+    Io,
+    Metadata,
+    ResourceRejected,
+    Download,
 }
 
 // ————————————————————————————————————————————————————————————————————————————
@@ -1,3 +1,5 @@
 pub mod common;
 pub mod snapshot;
 pub mod project;
+pub mod run;
+pub mod store;
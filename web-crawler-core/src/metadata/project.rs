@@ -1,83 +1,73 @@
-use std::{collections::LinkedList, path::Path};
-use serde::{Deserialize, Serialize};
+use std::path::Path;
+use colored::Colorize;
 use url::Url;
 
-use crate::metadata::{common::SnapshotDirectory, snapshot::{SnapshotLog, TaskLog}};
+use crate::metadata::{common::RelativeFilePath, snapshot::{SnapshotLog, TaskLog}, store::ProjectStore};
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+/// Every log of a project.
+///
+/// Backed by the [`ProjectStore`] of the project directory; lookups are
+/// indexed queries, nothing is held in memory. The first load of a project
+/// crawled before the store existed imports its TOML logs.
+#[derive(Debug)]
 pub struct ProjectLog {
-    pub snapshot_logs: LinkedList<SnapshotLog>,
-    pub task_logs: LinkedList<TaskLog>,
+    store: ProjectStore,
 }
 
 impl ProjectLog {
     pub fn load(project_directory: impl AsRef<Path>) -> Result<Self, Box<dyn std::error::Error>> {
         let project_directory = project_directory.as_ref();
-        std::fs::create_dir_all(&project_directory).unwrap();
-        let snapshot_log_glob = format!(
-            "**/{}",
-            SnapshotLog::TOML_LOG_FILE_NAME
-        );
-        let task_log_glob = format!(
-            "**/{}",
-            TaskLog::TOML_LOG_FILE_NAME
-        );
-        let snapshot_logs = wax::Glob::new(&snapshot_log_glob)
-            .unwrap()
-            .walk(project_directory)
-            .into_iter()
-            .map(|x| x.unwrap())
-            .filter(|x| x.file_type().is_file())
-            .map(|x| x.into_path())
-            .filter_map(|file_path| {
-                SnapshotLog::open(&file_path).ok()
-            })
-            .collect::<LinkedList<_>>();
-        let task_logs = wax::Glob::new(&task_log_glob)
-            .unwrap()
-            .walk(project_directory)
-            .into_iter()
-            .map(|x| x.unwrap())
-            .filter(|x| x.file_type().is_file())
-            .map(|x| x.into_path())
-            .filter_map(|file_path| {
-                TaskLog::open(&file_path).ok()
-            })
-            .collect::<LinkedList<_>>();
-        Ok(ProjectLog {
-            snapshot_logs,
-            task_logs,
-        })
+        let mut store = ProjectStore::open(project_directory)?;
+        if !store.is_imported()? {
+            let summary = store.import_toml_logs(project_directory)?;
+            if summary.snapshot_logs + summary.task_logs + summary.skipped > 0 {
+                eprintln!("{}", format!(
+                    "\t ☞ Imported {} snapshot logs and {} task logs into {} ({} unreadable)",
+                    summary.snapshot_logs,
+                    summary.task_logs,
+                    ProjectStore::FILE_NAME,
+                    summary.skipped,
+                ).blue());
+            }
+        }
+        Ok(ProjectLog { store })
     }
-    pub fn persist_snapshot_log(
-        &mut self,
-        snapshot_directory: &SnapshotDirectory,
-        log: SnapshotLog
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        log.write(snapshot_directory)?;
-        self.snapshot_logs.push_back(log);
-        Ok(())
+    /// Every snapshot log, in the order they were first written.
+    pub fn snapshot_logs(&self) -> Result<Vec<SnapshotLog>, Box<dyn std::error::Error>> {
+        self.store.snapshot_logs()
     }
-    pub fn persist_task_log(
-        &mut self,
-        snapshot_directory: &SnapshotDirectory,
-        log: TaskLog
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        log.write(snapshot_directory)?;
-        self.task_logs.push_back(log);
-        Ok(())
+    /// Every task log, in the order they were first written.
+    pub fn task_logs(&self) -> Result<Vec<TaskLog>, Box<dyn std::error::Error>> {
+        self.store.task_logs()
     }
-    pub fn should_visit(
-        &self,
-        given_url: &Url,
-        snapshot_directory: &SnapshotDirectory,
-    ) -> bool {
-        for entry in self.task_logs.iter() {
-            if entry.contains_url(given_url, snapshot_directory) {
-                return false
-            }
-        }
-        true
+    pub fn persist_snapshot_log(&mut self, log: SnapshotLog) -> Result<(), Box<dyn std::error::Error>> {
+        self.store.put_snapshot_log(&log)
+    }
+    pub fn persist_task_log(&mut self, log: TaskLog) -> Result<(), Box<dyn std::error::Error>> {
+        self.store.put_task_log(&log)
+    }
+    /// The snapshot of `url`, by its original or canonical URL.
+    pub fn snapshot_log_for(&self, url: &Url) -> Option<SnapshotLog> {
+        report_lookup_error(self.store.snapshot_log_for(url))
+    }
+    pub fn task_log_for(&self, url: &Url) -> Option<TaskLog> {
+        report_lookup_error(self.store.task_log_for(url))
+    }
+    pub fn snapshot_log_at(&self, snapshot_path: &RelativeFilePath) -> Option<SnapshotLog> {
+        report_lookup_error(self.store.snapshot_log_at(snapshot_path))
+    }
+    /// Whether no task visited `given_url` yet, including as a redirect
+    /// target.
+    pub fn should_visit(&self, given_url: &Url) -> bool {
+        report_lookup_error(self.store.is_visited(given_url).map(Some)) != Some(true)
     }
 }
 
+/// Lookups answer "not logged" when the store cannot be read, after saying
+/// so; the crawl goes on and the page is visited again.
+fn report_lookup_error<T>(result: Result<Option<T>, Box<dyn std::error::Error>>) -> Option<T> {
+    result.unwrap_or_else(|error| {
+        eprintln!("{}", format!("\t ⚠️ Failed to read {}: {error}", ProjectStore::FILE_NAME).red());
+        None
+    })
+}
@@ -0,0 +1,42 @@
+use std::path::Path;
+
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+
+use crate::budget::StopReason;
+use crate::metadata::common::SnapshotDate;
+
+// ————————————————————————————————————————————————————————————————————————————
+// RUN
+// ————————————————————————————————————————————————————————————————————————————
+
+/// Summary of a single `WebCrawler::execute` run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunLog {
+    pub started: SnapshotDate,
+    pub finished: SnapshotDate,
+    pub stop_reason: StopReason,
+    pub pages_visited: usize,
+    /// URLs still queued when the run ended.
+    pub remaining_in_queue: usize,
+    pub pages_per_host: IndexMap<String, usize>,
+}
+
+impl RunLog {
+    pub const RUNS_DIRECTORY_NAME: &'static str = ".runs";
+    pub fn open(file_path: impl AsRef<Path>) -> Result<Self, Box<dyn std::error::Error>> {
+        let file_path = file_path.as_ref();
+        let contents = std::fs::read_to_string(file_path)?;
+        let data = toml::from_str::<Self>(&contents)?;
+        Ok(data)
+    }
+    /// Write to `<project>/.runs/<started>.run.log.toml`.
+    pub fn write(&self, project_directory: impl AsRef<Path>) -> Result<(), Box<dyn std::error::Error>> {
+        let runs_directory = project_directory.as_ref().join(Self::RUNS_DIRECTORY_NAME);
+        let file_name = format!("{}.run.log.toml", self.started.0.replace(':', "-"));
+        let contents = toml::to_string_pretty(self)?;
+        std::fs::create_dir_all(&runs_directory)?;
+        std::fs::write(runs_directory.join(file_name), &contents)?;
+        Ok(())
+    }
+}
@@ -5,7 +5,7 @@ use indexmap::IndexSet;
 use serde::{Deserialize, Serialize};
 use url::Url;
 
-use crate::metadata::common::{CanonicalUrl, OriginalUrl, RelativeFilePath, SnapshotDate, SnapshotDirectory, Status};
+use crate::metadata::common::{CanonicalUrl, DiscoverySource, DocumentResponse, FailureReason, OriginalUrl, RedirectHop, RelativeFilePath, SnapshotDate, SnapshotDirectory, Status};
 
 // ————————————————————————————————————————————————————————————————————————————
 // BASICS
@@ -25,6 +25,55 @@ pub struct SnapshotLog {
     pub snapshot_date: SnapshotDate,
     pub outgoing_links: IndexSet<OriginalUrl>,
     pub incoming_links: IndexSet<OriginalUrl>,
+    /// How this URL entered the frontier; missing for older logs.
+    #[serde(default)]
+    pub discovered_via: Option<DiscoverySource>,
+    /// Link depth from the nearest seed; missing for older logs.
+    #[serde(default)]
+    pub depth: Option<usize>,
+    /// `sha256:<hex>` of the snapshot HTML; missing for older logs.
+    #[serde(default)]
+    pub content_hash: Option<String>,
+    /// Last visit, including recrawls that found the content unchanged.
+    /// `snapshot_date` only moves when the content changes.
+    #[serde(default)]
+    pub last_checked: Option<SnapshotDate>,
+    /// `simhash:<hex>` of the visible text, when near-duplicate detection is
+    /// enabled.
+    #[serde(default)]
+    pub simhash: Option<String>,
+    /// Snapshot this page is a near-duplicate of. No HTML is stored for a
+    /// duplicate.
+    #[serde(default)]
+    pub duplicate_of: Option<RelativeFilePath>,
+    /// Full-page screenshot taken with the snapshot, if enabled.
+    #[serde(default)]
+    pub screenshot_path: Option<RelativeFilePath>,
+    /// Print-to-PDF render taken with the snapshot, if enabled.
+    #[serde(default)]
+    pub pdf_path: Option<RelativeFilePath>,
+    /// `page.har` of every request the page made, if enabled.
+    #[serde(default)]
+    pub har_path: Option<RelativeFilePath>,
+    /// Headers, server address, protocol and timing of the main document
+    /// response; missing for older logs.
+    #[serde(default)]
+    pub response: Option<DocumentResponse>,
+    /// Every redirect between the requested URL and `response`, in order.
+    #[serde(default)]
+    pub redirect_chain: Vec<RedirectHop>,
+    /// Earlier snapshots of this page, oldest first.
+    #[serde(default)]
+    pub versions: Vec<SnapshotVersion>,
+}
+
+/// A superseded snapshot kept by an incremental recrawl.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotVersion {
+    pub snapshot_date: SnapshotDate,
+    pub content_hash: Option<String>,
+    /// Relative to the output directory.
+    pub snapshot_path: RelativeFilePath,
 }
 
 impl SnapshotLog {
@@ -44,7 +93,7 @@ impl SnapshotLog {
     pub fn write(&self, snapshot_directory: &SnapshotDirectory) -> Result<(), Box<dyn std::error::Error>> {
         let file_path = snapshot_directory.join(Self::TOML_LOG_FILE_NAME);
         let contents = toml::to_string_pretty(self)?;
-        std::fs::create_dir_all(&snapshot_directory.0).unwrap();
+        std::fs::create_dir_all(&snapshot_directory.0)?;
         std::fs::write(file_path, &contents)?;
         Ok(())
     }
@@ -56,6 +105,18 @@ impl SnapshotLog {
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct TaskLog {
+    /// Visits of this URL so far, counting consecutive failed attempts.
+    /// Reset to `1` once a visit succeeds. Missing in logs written before
+    /// retries; see [`TaskLog::attempts`].
+    #[serde(default)]
+    pub attempts: usize,
+    #[serde(default)]
+    pub last_attempt: Option<SnapshotDate>,
+    /// Kept so that a retry is enqueued at the depth the URL was found at.
+    #[serde(default)]
+    pub discovered_via: Option<DiscoverySource>,
+    #[serde(default)]
+    pub depth: Option<usize>,
     pub entries: Vec<Status>,
 }
 
@@ -76,7 +137,7 @@ impl TaskLog {
     pub fn write(&self, snapshot_directory: &SnapshotDirectory) -> Result<(), Box<dyn std::error::Error>> {
         let file_path = snapshot_directory.join(Self::TOML_LOG_FILE_NAME);
         let contents = toml::to_string_pretty(self)?;
-        std::fs::create_dir_all(&snapshot_directory.0).unwrap();
+        std::fs::create_dir_all(&snapshot_directory.0)?;
         std::fs::write(file_path, &contents)?;
         Ok(())
     }
@@ -126,16 +187,58 @@ impl TaskLog {
                         return true
                     }
                 }
+                Status::Resource { url, .. } => {
+                    if given_url == &url.0 {
+                        return true
+                    }
+                }
             }
         }
         false
     }
+    /// The URL this log is about.
+    pub fn url(&self) -> Option<&Url> {
+        self.entries.first().map(|status| {
+            match status {
+                Status::Success { url, .. } => &url.0,
+                Status::Failure { url, .. } => &url.0,
+                Status::Redirected { from, .. } => &from.0,
+                Status::Resource { url, .. } => &url.0,
+            }
+        })
+    }
+    /// Every URL the entries mention, i.e. every URL this task has visited.
+    pub fn urls(&self) -> impl Iterator<Item=&Url> {
+        self.entries.iter().flat_map(|status| {
+            match status {
+                Status::Success { url, .. } => [Some(&url.0), None],
+                Status::Failure { url, .. } => [Some(&url.0), None],
+                Status::Redirected { from, to, .. } => [Some(&from.0), Some(&to.0)],
+                Status::Resource { url, .. } => [Some(&url.0), None],
+            }
+        }).flatten()
+    }
+    /// Number of attempts, counting logs without one as a single attempt.
+    pub fn attempts(&self) -> usize {
+        self.attempts.max(1)
+    }
+    pub fn failures(&self) -> impl Iterator<Item=(Option<i64>, Option<&FailureReason>)> {
+        self.entries.iter().filter_map(|status| {
+            match status {
+                Status::Failure { http_status, reason, .. } => Some((*http_status, reason.as_ref())),
+                Status::Success { .. } => None,
+                Status::Redirected { .. } => None,
+                Status::Resource { .. } => None,
+            }
+        })
+    }
     pub fn contains_failures(&self) -> bool {
         self.entries.iter().any(|status| {
             match status {
                 Status::Failure { .. } => true,
                 Status::Success { .. } => false,
                 Status::Redirected { .. } => false,
+                Status::Resource { .. } => false,
             }
         })
     }
@@ -0,0 +1,481 @@
+use std::path::Path;
+
+use rusqlite::{Connection, OptionalExtension, params};
+use url::Url;
+
+use crate::metadata::common::RelativeFilePath;
+use crate::metadata::snapshot::{SnapshotLog, TaskLog};
+
+/// The indexed copy of a project's logs, in `project.sqlite3` at the root of
+/// the project directory.
+///
+/// Every log is stored whole as JSON next to the columns it is looked up
+/// by, so new log fields need no migration. This is the only record of the
+/// logs: the `.snapshot.log.toml` and `.task.log.toml` files of projects
+/// crawled before it existed are read once by
+/// [`ProjectStore::import_toml_logs`] and not written anymore.
+#[derive(Debug)]
+pub struct ProjectStore {
+    connection: Connection,
+}
+
+/// Counts of an import from TOML logs.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ImportSummary {
+    pub snapshot_logs: usize,
+    pub task_logs: usize,
+    /// Files that could not be parsed.
+    pub skipped: usize,
+}
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS snapshots (
+        snapshot_path TEXT PRIMARY KEY,
+        original_url TEXT NOT NULL,
+        canonical_url TEXT NOT NULL,
+        log TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS snapshots_by_original_url ON snapshots (original_url);
+    CREATE INDEX IF NOT EXISTS snapshots_by_canonical_url ON snapshots (canonical_url);
+    CREATE TABLE IF NOT EXISTS tasks (
+        url TEXT PRIMARY KEY,
+        log TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS visited_urls (
+        url TEXT PRIMARY KEY,
+        task_url TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS visited_urls_by_task_url ON visited_urls (task_url);
+";
+
+/// Stored as `PRAGMA user_version`. `0` is a store that was never filled:
+/// new, or opened before its project's TOML logs were imported.
+const SCHEMA_VERSION: i64 = 2;
+
+type Migration = fn(&Connection) -> Result<(), Box<dyn std::error::Error>>;
+
+/// `MIGRATIONS[n]` takes a store from version `n + 1` to `n + 2`, keeping
+/// every log. Each runs in the transaction that also sets the new version.
+const MIGRATIONS: [Migration; 1] = [
+    migrate_links_to_visited_urls,
+];
+
+impl ProjectStore {
+    pub const FILE_NAME: &'static str = "project.sqlite3";
+    pub fn open(project_directory: impl AsRef<Path>) -> Result<Self, Box<dyn std::error::Error>> {
+        let project_directory = project_directory.as_ref();
+        std::fs::create_dir_all(project_directory)?;
+        let connection = Connection::open(project_directory.join(Self::FILE_NAME))?;
+        // Every log is written on its own; WAL keeps that from syncing the
+        // whole file each time.
+        connection.pragma_update(None, "journal_mode", "WAL")?;
+        connection.pragma_update(None, "synchronous", "NORMAL")?;
+        connection.execute_batch(SCHEMA)?;
+        let mut store = Self { connection };
+        store.migrate()?;
+        Ok(store)
+    }
+    /// Whether the TOML logs of the project were imported yet. Only then
+    /// does the store hold the logs of the project.
+    pub fn is_imported(&self) -> Result<bool, Box<dyn std::error::Error>> {
+        Ok(self.version()? > 0)
+    }
+    fn version(&self) -> Result<i64, Box<dyn std::error::Error>> {
+        Ok(self.connection.pragma_query_value(None, "user_version", |row| row.get::<_, i64>(0))?)
+    }
+    /// Run the migrations from the version of the store up to
+    /// [`SCHEMA_VERSION`].
+    fn migrate(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let mut version = self.version()?;
+        // The first layout marked an import in a `meta` table instead.
+        let has_meta_table = self.connection
+            .query_row("SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'meta'", [], |_| Ok(()))
+            .optional()?
+            .is_some();
+        if version == 0 && has_meta_table {
+            let imported = self.connection
+                .query_row("SELECT 1 FROM meta WHERE key = 'imported'", [], |_| Ok(()))
+                .optional()?;
+            if imported.is_some() {
+                version = 1;
+            }
+        }
+        if version > SCHEMA_VERSION {
+            return Err(format!(
+                "{} has schema version {version}, newer than the {SCHEMA_VERSION} this build knows",
+                Self::FILE_NAME,
+            ).into())
+        }
+        while version > 0 && version < SCHEMA_VERSION {
+            let transaction = self.connection.transaction()?;
+            MIGRATIONS[(version - 1) as usize](&transaction)?;
+            version += 1;
+            transaction.pragma_update(None, "user_version", version)?;
+            transaction.commit()?;
+        }
+        Ok(())
+    }
+    // ————————————————————————————————————————————————————————————————————————
+    // WRITE
+    // ————————————————————————————————————————————————————————————————————————
+    pub fn put_snapshot_log(&self, log: &SnapshotLog) -> Result<(), Box<dyn std::error::Error>> {
+        Self::insert_snapshot_log(&self.connection, log)
+    }
+    /// Replaces the task log of the same URL, and the URLs it visited. Logs
+    /// without entries are not about any URL and are ignored.
+    pub fn put_task_log(&self, log: &TaskLog) -> Result<(), Box<dyn std::error::Error>> {
+        Self::insert_task_log(&self.connection, log)
+    }
+    /// Rewrites keep the row, and so the position of the log in
+    /// [`Self::snapshot_logs`].
+    fn insert_snapshot_log(connection: &Connection, log: &SnapshotLog) -> Result<(), Box<dyn std::error::Error>> {
+        connection.execute(
+            "INSERT INTO snapshots (snapshot_path, original_url, canonical_url, log) VALUES (?1, ?2, ?3, ?4)
+                ON CONFLICT (snapshot_path) DO UPDATE SET
+                    original_url = excluded.original_url,
+                    canonical_url = excluded.canonical_url,
+                    log = excluded.log",
+            params![
+                log.snapshot_path.0.to_string_lossy(),
+                log.original_url.0.as_str(),
+                log.canonical_url.0.as_str(),
+                serde_json::to_string(log)?,
+            ],
+        )?;
+        Ok(())
+    }
+    fn insert_task_log(connection: &Connection, log: &TaskLog) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(url) = log.url() else {
+            return Ok(())
+        };
+        connection.execute(
+            "INSERT INTO tasks (url, log) VALUES (?1, ?2)
+                ON CONFLICT (url) DO UPDATE SET log = excluded.log",
+            params![url.as_str(), serde_json::to_string(log)?],
+        )?;
+        Self::insert_visited_urls(connection, url, log)
+    }
+    /// Replace the URLs visited by the task of `task_url`.
+    fn insert_visited_urls(connection: &Connection, task_url: &Url, log: &TaskLog) -> Result<(), Box<dyn std::error::Error>> {
+        // A URL's task log is rewritten on every attempt.
+        connection.execute("DELETE FROM visited_urls WHERE task_url = ?1", params![task_url.as_str()])?;
+        let mut insert_visited = connection.prepare_cached(
+            "INSERT INTO visited_urls (url, task_url) VALUES (?1, ?2)
+                ON CONFLICT (url) DO UPDATE SET task_url = excluded.task_url",
+        )?;
+        for visited in log.urls() {
+            insert_visited.execute(params![visited.as_str(), task_url.as_str()])?;
+        }
+        Ok(())
+    }
+    // ————————————————————————————————————————————————————————————————————————
+    // READ
+    // ————————————————————————————————————————————————————————————————————————
+    pub fn snapshot_logs(&self) -> Result<Vec<SnapshotLog>, Box<dyn std::error::Error>> {
+        let mut statement = self.connection.prepare("SELECT log FROM snapshots ORDER BY rowid")?;
+        let logs = statement
+            .query_map([], |row| row.get::<_, String>(0))?
+            .map(|log| Ok(serde_json::from_str::<SnapshotLog>(&log?)?))
+            .collect::<Result<Vec<_>, Box<dyn std::error::Error>>>()?;
+        Ok(logs)
+    }
+    pub fn task_logs(&self) -> Result<Vec<TaskLog>, Box<dyn std::error::Error>> {
+        let mut statement = self.connection.prepare("SELECT log FROM tasks ORDER BY rowid")?;
+        let logs = statement
+            .query_map([], |row| row.get::<_, String>(0))?
+            .map(|log| Ok(serde_json::from_str::<TaskLog>(&log?)?))
+            .collect::<Result<Vec<_>, Box<dyn std::error::Error>>>()?;
+        Ok(logs)
+    }
+    /// The snapshot of `url`, by its original or canonical URL.
+    pub fn snapshot_log_for(&self, url: &Url) -> Result<Option<SnapshotLog>, Box<dyn std::error::Error>> {
+        let log = self.connection
+            .query_row(
+                "SELECT log FROM snapshots WHERE original_url = ?1 OR canonical_url = ?1 LIMIT 1",
+                params![url.as_str()],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()?;
+        Ok(log.map(|log| serde_json::from_str(&log)).transpose()?)
+    }
+    pub fn snapshot_log_at(&self, snapshot_path: &RelativeFilePath) -> Result<Option<SnapshotLog>, Box<dyn std::error::Error>> {
+        let log = self.connection
+            .query_row(
+                "SELECT log FROM snapshots WHERE snapshot_path = ?1",
+                params![snapshot_path.0.to_string_lossy()],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()?;
+        Ok(log.map(|log| serde_json::from_str(&log)).transpose()?)
+    }
+    /// The task log of `url`, keyed by [`TaskLog::url`].
+    pub fn task_log_for(&self, url: &Url) -> Result<Option<TaskLog>, Box<dyn std::error::Error>> {
+        let log = self.connection
+            .query_row("SELECT log FROM tasks WHERE url = ?1", params![url.as_str()], |row| row.get::<_, String>(0))
+            .optional()?;
+        Ok(log.map(|log| serde_json::from_str(&log)).transpose()?)
+    }
+    /// Whether any task visited `url`, including as a redirect target.
+    pub fn is_visited(&self, url: &Url) -> Result<bool, Box<dyn std::error::Error>> {
+        let visited = self.connection
+            .query_row("SELECT 1 FROM visited_urls WHERE url = ?1", params![url.as_str()], |_| Ok(()))
+            .optional()?;
+        Ok(visited.is_some())
+    }
+    // ————————————————————————————————————————————————————————————————————————
+    // IMPORT
+    // ————————————————————————————————————————————————————————————————————————
+    /// Fill a store that was never imported with every `.snapshot.log.toml`
+    /// and `.task.log.toml` below `project_directory`, in one transaction.
+    ///
+    /// Happens once per project; an imported store is the only record of
+    /// its logs and is refused.
+    pub fn import_toml_logs(
+        &mut self,
+        project_directory: impl AsRef<Path>,
+    ) -> Result<ImportSummary, Box<dyn std::error::Error>> {
+        if self.is_imported()? {
+            return Err(format!("{} is already imported", Self::FILE_NAME).into())
+        }
+        let project_directory = project_directory.as_ref();
+        let mut summary = ImportSummary::default();
+        let transaction = self.connection.transaction()?;
+        // Left by the first layout when it was opened but never imported.
+        transaction.execute_batch("
+            DROP TABLE IF EXISTS links;
+            DROP TABLE IF EXISTS meta;
+        ")?;
+        for file_path in find_files(project_directory, SnapshotLog::TOML_LOG_FILE_NAME)? {
+            match SnapshotLog::open(&file_path) {
+                Ok(log) => {
+                    Self::insert_snapshot_log(&transaction, &log)?;
+                    summary.snapshot_logs += 1;
+                }
+                Err(_) => summary.skipped += 1,
+            }
+        }
+        for file_path in find_files(project_directory, TaskLog::TOML_LOG_FILE_NAME)? {
+            match TaskLog::open(&file_path) {
+                Ok(log) => {
+                    Self::insert_task_log(&transaction, &log)?;
+                    summary.task_logs += 1;
+                }
+                Err(_) => summary.skipped += 1,
+            }
+        }
+        transaction.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+        transaction.commit()?;
+        Ok(summary)
+    }
+}
+
+/// Version 1 kept the outgoing links of every snapshot in `links`, and
+/// marked the import in `meta`. Version 2 looks up visited URLs instead.
+fn migrate_links_to_visited_urls(connection: &Connection) -> Result<(), Box<dyn std::error::Error>> {
+    connection.execute_batch("
+        DROP TABLE IF EXISTS links;
+        DROP TABLE IF EXISTS meta;
+    ")?;
+    let task_logs = {
+        let mut statement = connection.prepare("SELECT log FROM tasks ORDER BY rowid")?;
+        statement
+            .query_map([], |row| row.get::<_, String>(0))?
+            .map(|log| Ok(serde_json::from_str::<TaskLog>(&log?)?))
+            .collect::<Result<Vec<_>, Box<dyn std::error::Error>>>()?
+    };
+    for log in task_logs.iter() {
+        if let Some(url) = log.url() {
+            ProjectStore::insert_visited_urls(connection, url, log)?;
+        }
+    }
+    Ok(())
+}
+
+/// Every file named `file_name` below `directory`.
+fn find_files(directory: &Path, file_name: &str) -> Result<Vec<std::path::PathBuf>, Box<dyn std::error::Error>> {
+    let glob = format!("**/{file_name}");
+    let files = wax::Glob::new(&glob).map_err(|error| error.to_string())?
+        .walk(directory)
+        .filter_map(|x| x.ok())
+        .filter(|x| x.file_type().is_file())
+        .map(|x| x.into_path())
+        .collect();
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::common::{OriginalUrl, Status};
+    use crate::metadata::project::ProjectLog;
+    use crate::test_utils::TempDirectory;
+
+    fn url(source: &str) -> Url {
+        Url::parse(source).unwrap()
+    }
+
+    /// As written by a crawl before the store existed; without any of the
+    /// fields added since.
+    const SNAPSHOT_LOG: &str = r#"
+        http_status = 200
+        original_url = "https://example.com/docs?ref=nav"
+        canonical_url = "https://example.com/docs"
+        snapshot_path = "file://./example.com/docs/source.snapshot.html"
+        snapshot_date = "2024-05-01T10:00:00+00:00"
+        outgoing_links = ["https://example.com/", "https://example.com/about"]
+        incoming_links = []
+    "#;
+
+    const TASK_LOG: &str = r#"
+        [[entries]]
+        type = "Redirected"
+        from = "https://example.com/old"
+        to = "https://example.com/new"
+        http_status = 301
+
+        [[entries]]
+        type = "Success"
+        url = "https://example.com/new"
+        http_status = 200
+    "#;
+
+    #[test]
+    fn imports_an_existing_toml_project() {
+        let directory = TempDirectory::new("store-import");
+        let snapshot_directory = directory.join("example.com/docs");
+        std::fs::create_dir_all(&snapshot_directory).unwrap();
+        std::fs::write(snapshot_directory.join(SnapshotLog::TOML_LOG_FILE_NAME), SNAPSHOT_LOG).unwrap();
+        let task_directory = directory.join("example.com/old");
+        std::fs::create_dir_all(&task_directory).unwrap();
+        std::fs::write(task_directory.join(TaskLog::TOML_LOG_FILE_NAME), TASK_LOG).unwrap();
+        std::fs::write(directory.join(TaskLog::TOML_LOG_FILE_NAME), "not = [toml").unwrap();
+
+        let mut store = ProjectStore::open(&directory).unwrap();
+        assert!(!store.is_imported().unwrap());
+        let summary = store.import_toml_logs(&directory).unwrap();
+        assert_eq!((summary.snapshot_logs, summary.task_logs, summary.skipped), (1, 1, 1));
+        assert!(store.is_imported().unwrap());
+
+        let expected = toml::from_str::<SnapshotLog>(SNAPSHOT_LOG).unwrap();
+        let by_original = store.snapshot_log_for(&url("https://example.com/docs?ref=nav")).unwrap().unwrap();
+        let by_canonical = store.snapshot_log_for(&url("https://example.com/docs")).unwrap().unwrap();
+        let by_path = store.snapshot_log_at(&expected.snapshot_path).unwrap().unwrap();
+        for log in [by_original, by_canonical, by_path] {
+            assert_eq!(toml::to_string(&log).unwrap(), toml::to_string(&expected).unwrap());
+        }
+        let task_log = store.task_log_for(&url("https://example.com/old")).unwrap().unwrap();
+        assert_eq!(task_log.entries, toml::from_str::<TaskLog>(TASK_LOG).unwrap().entries);
+        assert!(store.is_visited(&url("https://example.com/old")).unwrap());
+        assert!(store.is_visited(&url("https://example.com/new")).unwrap());
+        assert!(!store.is_visited(&url("https://example.com/docs")).unwrap());
+        drop(store);
+
+        // Loaded again, the project reads the store and leaves TOML alone.
+        std::fs::remove_file(snapshot_directory.join(SnapshotLog::TOML_LOG_FILE_NAME)).unwrap();
+        let mut project = ProjectLog::load(&directory).unwrap();
+        assert_eq!(project.snapshot_logs().unwrap().len(), 1);
+        assert!(!project.should_visit(&url("https://example.com/new")));
+        project.persist_task_log(TaskLog {
+            entries: vec![Status::Success {
+                url: OriginalUrl(url("https://example.com/old")),
+                http_status: Some(200),
+            }],
+            ..TaskLog::default()
+        }).unwrap();
+        assert!(project.should_visit(&url("https://example.com/new")));
+        assert!(!snapshot_directory.join(SnapshotLog::TOML_LOG_FILE_NAME).exists());
+        // Importing again would drop every log written since.
+        drop(project);
+        let mut store = ProjectStore::open(&directory).unwrap();
+        assert!(store.import_toml_logs(&directory).is_err());
+        assert!(store.task_log_for(&url("https://example.com/old")).unwrap().is_some());
+    }
+
+    #[test]
+    fn rewrites_keep_the_order_logs_were_first_written_in() {
+        let directory = TempDirectory::new("store-order");
+        let store = ProjectStore::open(&directory).unwrap();
+        let snapshot_log = |path: &str, snapshot_date: &str| SnapshotLog {
+            snapshot_path: RelativeFilePath(path.into()),
+            snapshot_date: crate::metadata::common::SnapshotDate(snapshot_date.to_string()),
+            ..toml::from_str::<SnapshotLog>(SNAPSHOT_LOG).unwrap()
+        };
+        store.put_snapshot_log(&snapshot_log("a/source.snapshot.html", "1")).unwrap();
+        store.put_snapshot_log(&snapshot_log("b/source.snapshot.html", "1")).unwrap();
+        store.put_snapshot_log(&snapshot_log("a/source.snapshot.html", "2")).unwrap();
+        let logs = store.snapshot_logs().unwrap();
+        let logs = logs
+            .iter()
+            .map(|log| (log.snapshot_path.0.to_str().unwrap(), log.snapshot_date.0.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(logs, [("a/source.snapshot.html", "2"), ("b/source.snapshot.html", "1")]);
+
+        let task_log = |target: &str| TaskLog {
+            entries: vec![Status::Redirected {
+                from: OriginalUrl(url("https://example.com/a")),
+                to: OriginalUrl(url(target)),
+                http_status: Some(301),
+                kind: None,
+            }],
+            ..TaskLog::default()
+        };
+        store.put_task_log(&task_log("https://example.com/b")).unwrap();
+        store.put_task_log(&toml::from_str::<TaskLog>(TASK_LOG).unwrap()).unwrap();
+        store.put_task_log(&task_log("https://example.com/c")).unwrap();
+        let urls = store.task_logs().unwrap();
+        let urls = urls.iter().map(|log| log.url().unwrap().as_str()).collect::<Vec<_>>();
+        assert_eq!(urls, ["https://example.com/a", "https://example.com/old"]);
+        assert!(store.is_visited(&url("https://example.com/c")).unwrap());
+        assert!(!store.is_visited(&url("https://example.com/b")).unwrap());
+        drop(store);
+    }
+
+    #[test]
+    fn migrates_the_first_layout_without_losing_logs() {
+        let directory = TempDirectory::new("store-migrate");
+        {
+            let connection = Connection::open(directory.join(ProjectStore::FILE_NAME)).unwrap();
+            connection.execute_batch("
+                CREATE TABLE snapshots (
+                    snapshot_path TEXT PRIMARY KEY,
+                    original_url TEXT NOT NULL,
+                    canonical_url TEXT NOT NULL,
+                    log TEXT NOT NULL
+                );
+                CREATE TABLE tasks (url TEXT PRIMARY KEY, log TEXT NOT NULL);
+                CREATE TABLE links (source TEXT NOT NULL, target TEXT NOT NULL, PRIMARY KEY (source, target));
+                CREATE TABLE meta (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+                INSERT INTO meta (key, value) VALUES ('imported', '2024-05-01T10:00:00+00:00');
+            ").unwrap();
+            let task_log = toml::from_str::<TaskLog>(TASK_LOG).unwrap();
+            connection.execute(
+                "INSERT INTO tasks (url, log) VALUES (?1, ?2)",
+                params!["https://example.com/old", serde_json::to_string(&task_log).unwrap()],
+            ).unwrap();
+        }
+        // TOML logs that must not be imported over the store.
+        std::fs::write(directory.join(SnapshotLog::TOML_LOG_FILE_NAME), SNAPSHOT_LOG).unwrap();
+
+        let project = ProjectLog::load(&directory).unwrap();
+        assert_eq!(project.task_logs().unwrap().len(), 1);
+        assert!(project.snapshot_logs().unwrap().is_empty());
+        assert!(!project.should_visit(&url("https://example.com/new")));
+        drop(project);
+        let store = ProjectStore::open(&directory).unwrap();
+        assert_eq!(store.version().unwrap(), SCHEMA_VERSION);
+        let legacy_tables = store.connection
+            .query_row("SELECT count(*) FROM sqlite_master WHERE name IN ('links', 'meta')", [], |row| row.get::<_, i64>(0))
+            .unwrap();
+        assert_eq!(legacy_tables, 0);
+        drop(store);
+    }
+
+    #[test]
+    fn refuses_stores_of_a_newer_version() {
+        let directory = TempDirectory::new("store-newer");
+        let store = ProjectStore::open(&directory).unwrap();
+        store.connection.pragma_update(None, "user_version", SCHEMA_VERSION + 1).unwrap();
+        drop(store);
+        assert!(ProjectStore::open(&directory).is_err());
+    }
+}
@@ -0,0 +1,234 @@
+use std::path::Path;
+use std::time::Duration;
+use colored::Colorize;
+use url::Url;
+
+use crate::budget::StopReason;
+use crate::error::CrawlError;
+use crate::metadata::common::{CanonicalUrl, SnapshotDate};
+use crate::metadata::run::RunLog;
+use crate::metadata::snapshot::SnapshotLog;
+use crate::settings::{FailedFilterReason, SkipUrlReason};
+
+// ————————————————————————————————————————————————————————————————————————————
+// EVENTS
+// ————————————————————————————————————————————————————————————————————————————
+
+/// A page a worker has loaded in a tab.
+#[derive(Debug, Clone)]
+pub struct PageVisited<'a> {
+    pub url: &'a Url,
+    pub canonical_url: &'a CanonicalUrl,
+    pub depth: usize,
+    /// Status of the main document; `None` if nothing was received.
+    pub http_status: Option<i64>,
+}
+
+/// Something that went wrong without failing a page or the crawl.
+#[derive(Debug, Clone)]
+pub enum CrawlWarning<'a> {
+    /// The frontier journal could not be read, updated or flushed.
+    FrontierJournal { operation: &'static str, error: String },
+    CloseBrowser(String),
+    CloseTab { url: &'a Url, error: String },
+    /// An `href` that does not parse as a URL.
+    InvalidLink { href: &'a str, error: String },
+    /// The previous snapshot of a changed page, or of one that turned into a
+    /// duplicate, could not be archived, or its captures removed.
+    ArchiveSnapshot { url: &'a Url, error: String },
+    /// A page, or with no URL the final flush, could not be written to the
+    /// WARC output.
+    WarcOutput { url: Option<&'a Url>, error: String },
+    TaskLog { url: &'a Url, error: String },
+    RunLog(String),
+    /// A file listed in the project logs could not be read, so an export
+    /// leaves it out.
+    UnreadableFile { path: &'a Path, error: String },
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// OBSERVER
+// ————————————————————————————————————————————————————————————————————————————
+
+/// Receives crawl events from [`crate::engine::WebCrawler`].
+///
+/// Every method has an empty default, so implementations only override what
+/// they need. Calls happen inline on the crawl workers, so keep them cheap
+/// and hand heavy work off to a channel or task.
+pub trait CrawlObserver: Send + Sync {
+    fn page_visited(&self, _event: &PageVisited) {}
+    fn page_skipped(&self, _url: &Url, _reason: &SkipUrlReason) {}
+    fn redirected(&self, _from: &Url, _to: &Url, _http_status: Option<i64>) {}
+    /// A page failed. [`CrawlError::HttpStatus`] and [`CrawlError::Settle`]
+    /// are reported too, even though the page is still snapshotted.
+    fn page_failed(&self, _url: &Url, _error: &CrawlError) {}
+    /// A failed page goes back on the queue for `attempt` after `delay`.
+    fn retry_scheduled(&self, _url: &Url, _attempt: usize, _delay: Duration) {}
+    fn snapshot_written(&self, _log: &SnapshotLog) {}
+    /// A page's text matches an existing snapshot, so only its log is kept.
+    fn duplicate_found(&self, _url: &Url, _original: &SnapshotLog, _distance: u32) {}
+    /// A non-HTML document was downloaded instead of snapshotted.
+    fn resource_saved(&self, _url: &Url, _content_type: &str, _size: u64) {}
+    /// A recrawled page is the same as its snapshot from `since`.
+    fn snapshot_unchanged(&self, _url: &Url, _since: &SnapshotDate) {}
+    /// A recrawled page differs from its snapshot from `since`, which was
+    /// archived.
+    fn snapshot_changed(&self, _url: &Url, _since: &SnapshotDate) {}
+    /// A host answered 429 or 503, so its requests are spaced by `backoff`.
+    fn throttled(&self, _host: &str, _http_status: Option<i64>, _backoff: Duration) {}
+    /// No more pages are claimed; open ones still finish.
+    fn budget_exhausted(&self, _stop_reason: &StopReason) {}
+    /// The first Ctrl+C: open pages finish, the frontier is saved in
+    /// `frontier_journal`.
+    fn interrupted(&self, _frontier_journal: &Path) {}
+    /// The second Ctrl+C, just before the process exits.
+    fn interrupted_again(&self) {}
+    fn warning(&self, _warning: &CrawlWarning) {}
+    fn crawl_finished(&self, _run_log: &RunLog) {}
+}
+
+/// The crawler's standard colored output on stderr.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ConsoleObserver;
+
+impl CrawlObserver for ConsoleObserver {
+    fn page_visited(&self, event: &PageVisited) {
+        eprintln!("{}", format!("🔎 Visiting: {}", event.canonical_url.0).bright_magenta());
+    }
+    fn page_skipped(&self, url: &Url, reason: &SkipUrlReason) {
+        let msg = match reason {
+            SkipUrlReason::AlreadyVisited => {
+                format!("\t ⓘ Skipping [already-visited] {:?}", url.to_string())
+            }
+            SkipUrlReason::FailedFilter(FailedFilterReason::FailedWhitelistedSchemeCheck) => {
+                format!("\t ⓘ Skipping [failed-whitelisted-scheme-check] {:?}", url.to_string())
+            }
+            SkipUrlReason::FailedFilter(FailedFilterReason::FailedWhitelistedDomainCheck) => {
+                format!("\t ⓘ Skipping [failed-whitelisted-domain-check] {:?}", url.to_string())
+            }
+            SkipUrlReason::FailedFilter(FailedFilterReason::IsBlacklistedScheme) => {
+                format!("\t ⓘ Skipping [is-blacklisted-scheme] {:?}", url.to_string())
+            }
+            SkipUrlReason::FailedFilter(FailedFilterReason::DisallowedByRobotsTxt) => {
+                format!("\t ⓘ Skipping [disallowed-by-robots-txt] {:?}", url.to_string())
+            }
+            SkipUrlReason::FailedFilter(FailedFilterReason::ExcludedByRule(rule)) => {
+                format!("\t ⓘ Skipping [excluded-by-rule] {:?} » {rule}", url.to_string())
+            }
+            SkipUrlReason::FailedFilter(FailedFilterReason::NotMatchedByIncludeRule) => {
+                format!("\t ⓘ Skipping [not-matched-by-include-rule] {:?}", url.to_string())
+            }
+            SkipUrlReason::ExceedsMaxDepth => {
+                format!("\t ⓘ Skipping [exceeds-max-depth] {:?}", url.to_string())
+            }
+            SkipUrlReason::HostBudgetExhausted => {
+                format!("\t ⓘ Skipping [host-budget-exhausted] {:?}", url.to_string())
+            }
+        };
+        eprintln!("{}", msg.cyan());
+    }
+    fn page_failed(&self, url: &Url, error: &CrawlError) {
+        let msg = match error {
+            CrawlError::NoResponse => {
+                format!("\t Skipping {:?} » status: None", url.to_string()).bright_red()
+            }
+            CrawlError::HttpStatus(http_status) => {
+                format!("\t Error {:?} » status: Some({http_status})", url.to_string()).red()
+            }
+            CrawlError::NotHtml => {
+                format!("\t ⓘ Skipping {:?} : NOT HTML DOCUMENT", url.to_string()).red()
+            }
+            CrawlError::Settle(error) => {
+                format!("\t ❌ Failed to settle: {:?} » {error}", url.as_str()).red()
+            }
+            error => {
+                format!("\t ❌ Failed {:?} » {error}", url.as_str()).red()
+            }
+        };
+        eprintln!("{msg}");
+    }
+    fn duplicate_found(&self, url: &Url, original: &SnapshotLog, distance: u32) {
+        eprintln!("{}", format!(
+            "\t ⓘ Near-duplicate of {:?} » distance: {distance} » {:?}",
+            original.original_url.0.as_str(),
+            url.as_str(),
+        ).cyan());
+    }
+    fn resource_saved(&self, url: &Url, content_type: &str, size: u64) {
+        eprintln!("{}", format!(
+            "\t 📦 Saved {content_type} ({size} bytes) » {:?}",
+            url.as_str(),
+        ).bright_blue());
+    }
+    fn retry_scheduled(&self, url: &Url, attempt: usize, delay: Duration) {
+        eprintln!("{}", format!(
+            "\t ↻ Retrying {:?} in {delay:?} » attempt {attempt}",
+            url.as_str(),
+        ).yellow());
+    }
+    fn snapshot_unchanged(&self, url: &Url, since: &SnapshotDate) {
+        eprintln!("{}", format!("\t ⓘ Unchanged since {} » {:?}", since.0, url.as_str()).cyan());
+    }
+    fn snapshot_changed(&self, url: &Url, since: &SnapshotDate) {
+        eprintln!("{}", format!("\t ⓘ Changed since {} » {:?}", since.0, url.as_str()).bright_blue());
+    }
+    fn throttled(&self, host: &str, http_status: Option<i64>, backoff: Duration) {
+        eprintln!("{}", format!(
+            "\t ⚠️ Throttled by {host:?} » status: {http_status:?} » backing off {backoff:?}",
+        ).yellow());
+    }
+    fn budget_exhausted(&self, stop_reason: &StopReason) {
+        eprintln!("{}", format!("⏹  Crawl budget exhausted » {stop_reason:?}").yellow());
+    }
+    fn interrupted(&self, frontier_journal: &Path) {
+        eprintln!("{}", format!(
+            "⏹  Interrupted — finishing open pages; the frontier is saved in {frontier_journal:?}. Press Ctrl+C again to exit now.",
+        ).yellow());
+    }
+    fn interrupted_again(&self) {
+        eprintln!("{}", "⏹  Interrupted again — exiting now.".red());
+    }
+    fn warning(&self, warning: &CrawlWarning) {
+        let msg = match warning {
+            CrawlWarning::FrontierJournal { operation, error } => {
+                format!("\t ⚠️ Failed to {operation} the frontier journal: {error}").red()
+            }
+            CrawlWarning::CloseBrowser(error) => {
+                format!("\t ⚠️ Failed to close the browser: {error}").red()
+            }
+            CrawlWarning::CloseTab { url, error } => {
+                format!("\t ⚠️ Failed to close the tab of {:?}: {error}", url.as_str()).yellow()
+            }
+            CrawlWarning::InvalidLink { href, error } => {
+                format!("\t ⓘ Skipping {href:?} : {error}").normal()
+            }
+            CrawlWarning::ArchiveSnapshot { url, error } => {
+                format!("\t ⚠️ Failed to archive the previous snapshot of {:?}: {error}", url.as_str()).yellow()
+            }
+            CrawlWarning::WarcOutput { url: Some(url), error } => {
+                format!("\t ⚠️ Failed to write {:?} to the WARC output: {error}", url.as_str()).red()
+            }
+            CrawlWarning::WarcOutput { url: None, error } => {
+                format!("\t ⚠️ Failed to flush the WARC output: {error}").red()
+            }
+            CrawlWarning::TaskLog { url, error } => {
+                format!("\t ⚠️ Failed to write the task log of {:?}: {error}", url.as_str()).red()
+            }
+            CrawlWarning::RunLog(error) => {
+                format!("\t ⚠️ Failed to write run log: {error}").red()
+            }
+            CrawlWarning::UnreadableFile { path, error } => {
+                format!("\t ⚠️ Skipping {:?}: {error}", path.display()).yellow()
+            }
+        };
+        eprintln!("{msg}");
+    }
+    fn crawl_finished(&self, run_log: &RunLog) {
+        eprintln!("{}", format!(
+            "🏁 Crawl finished » reason: {:?} » pages visited: {} » still queued: {}",
+            run_log.stop_reason,
+            run_log.pages_visited,
+            run_log.remaining_in_queue,
+        ).bright_green());
+    }
+}
@@ -1,4 +1,5 @@
-use std::path::PathBuf;
+use std::fmt::Write;
+use std::path::{Component, Path, PathBuf};
 
 use url::Url;
 
@@ -10,75 +11,334 @@ const MAX_PATH_LEN: usize = 240;
 // const MAX_SEGMENT_LEN: usize = 64;
 // const MAX_PATH_LEN: usize = 255;
 
+pub const SNAPSHOT_FILE_NAME: &str = "source.snapshot.html";
+
+/// Directory of URLs too long for the directory tree, below the host.
+const LONG_DIRECTORY_NAME: &str = "~long";
+/// Stands for an empty path segment, e.g. the one after a trailing slash.
+const EMPTY_SEGMENT: &str = "~index";
+const QUERY_PREFIX: &str = "~q~";
+
+// ————————————————————————————————————————————————————————————————————————————
+// URL → PATH
+// ————————————————————————————————————————————————————————————————————————————
+//
+// Every URL gets its own directory:
+// `<scheme>~<host>[~<port>]/<segment>/…[/~q~<query>]`.
+//
+// Segments keep lowercase letters, digits and `-_.=,+@`; every other byte,
+// including `%` and uppercase letters, is written as `%XX`. That makes the
+// mapping injective, also on case-insensitive file systems, and lets
+// `url_from_rel_snapshot_dir` undo it. Names starting with `~` are never
+// produced by escaping, so they are free for markers:
+//
+// - `~index` is an empty segment, e.g. `/docs/` is `docs/~index`. The root
+//   path `/` is the host directory itself.
+// - `~q~<query>` is the query string, as the last segment.
+// - `~long/<hash>` replaces the path of URLs that would not fit.
+// - `~<n>` is appended by `resolve_snapshot_file_path` when the directory is
+//   already taken by another URL.
+//
+// The scheme is always written, so the host directory splits into its
+// parts at `~` without guessing; `~` itself is escaped in schemes and
+// hosts. The port is left out when it is the scheme's default. Fragments
+// are ignored; they address the same document.
+
 /// Build relative file path for a given URL.
-/// Regarding the parent directory — falls back to hashed folder if path becomes too long or unsafe.
+/// Falls back to a hashed folder if the path becomes too long.
 pub fn build_rel_html_snapshot_file_path(url: &str) -> Option<RelativeFilePath> {
     Some(RelativeFilePath(build_rel_html_snapshot_dir(url).map(|base| {
-        base.join("source.snapshot.html")
+        base.join(SNAPSHOT_FILE_NAME)
     })?))
 }
 
 /// Build directory path for a given URL, including query parameters.
-/// Falls back to hashed folder if path becomes too long or unsafe.
+/// Falls back to a hashed folder if the path becomes too long.
 pub fn build_rel_html_snapshot_dir(url: &str) -> Option<PathBuf> {
     use sha2::{Digest, Sha256};
 
-    fn sanitize(s: &str) -> String {
-        s.chars()
-            .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
-            .collect()
+    let parsed = Url::parse(url).ok()?;
+    let host = parsed.host_str().filter(|host| !host.is_empty())?;
+    let mut host = format!("{}~{}", escape_segment(parsed.scheme()), escape_segment(host));
+    if let Some(port) = parsed.port() {
+        host = format!("{host}~{port}");
+    }
+
+    let path = parsed.path().strip_prefix('/')?;
+    let mut segments: Vec<String> = match path {
+        "" => Vec::new(),
+        path => path
+            .split('/')
+            .map(|segment| match segment {
+                "" => EMPTY_SEGMENT.to_string(),
+                segment => escape_segment(segment),
+            })
+            .collect(),
+    };
+    if let Some(query) = parsed.query() {
+        segments.push(format!("{QUERY_PREFIX}{}", escape_segment(query)));
     }
 
-    fn short_hash(s: &str) -> String {
-        let mut hasher = Sha256::new();
-        hasher.update(s.as_bytes());
-        let hash = hasher.finalize();
-        let short = hash[..4]
+    let total_len = segments
+        .iter()
+        .fold(host.len(), |total_len, segment| total_len + segment.len() + 1);
+    let too_long = total_len > MAX_PATH_LEN
+        || segments.iter().any(|segment| segment.len() > MAX_SEGMENT_LEN);
+    if too_long {
+        // 128 bits, so that distinct URLs practically never share a hash.
+        // Collisions are still caught by `resolve_snapshot_file_path`.
+        let mut url = parsed.clone();
+        url.set_fragment(None);
+        let hash = Sha256::digest(url.as_str().as_bytes());
+        let hash = hash[..16]
             .iter()
             .map(|b| format!("{:02x}", b))
             .collect::<String>();
-        format!("h{short}")
+        return Some(PathBuf::from(host).join(LONG_DIRECTORY_NAME).join(hash));
     }
 
-    let parsed = Url::parse(url).ok()?;
-    let host = parsed.host_str().unwrap_or("unknown");
+    Some(segments.iter().fold(PathBuf::from(host), |path, segment| path.join(segment)))
+}
 
-    let mut path = parsed.path().trim_matches('/').to_string();
-    if path.is_empty() {
-        path = "index".to_string();
+/// The snapshot file path of `url`, moved to `<dir>/~2`, `<dir>/~3`, … for
+/// as long as `is_taken_by_other` reports the path as holding the snapshot
+/// of a different URL.
+pub fn resolve_snapshot_file_path(
+    url: &str,
+    is_taken_by_other: impl Fn(&RelativeFilePath) -> bool,
+) -> Option<RelativeFilePath> {
+    let directory = build_rel_html_snapshot_dir(url)?;
+    let mut file_path = RelativeFilePath(directory.join(SNAPSHOT_FILE_NAME));
+    let mut counter = 1usize;
+    while is_taken_by_other(&file_path) {
+        counter += 1;
+        file_path = RelativeFilePath(directory.join(format!("~{counter}")).join(SNAPSHOT_FILE_NAME));
     }
+    Some(file_path)
+}
+
+/// Files and directories stored next to a snapshot. Path segments spelled
+/// like one are escaped so a page's directory never clashes with them.
+fn is_reserved_name(segment: &str) -> bool {
+    segment.starts_with("source.") || segment == "page.har"
+}
 
-    let query = parsed.query().unwrap_or("");
-    let query_suffix = if !query.is_empty() {
-        let sanitized_query = sanitize(query);
-        format!("~q~{sanitized_query}")
-    } else {
-        String::new()
+fn escape_segment(segment: &str) -> String {
+    let reserved = is_reserved_name(segment);
+    let mut escaped = String::with_capacity(segment.len());
+    for (index, byte) in segment.bytes().enumerate() {
+        let is_safe = matches!(byte, b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'=' | b',' | b'+' | b'@');
+        // A leading `.` would hide the directory and could spell `..`.
+        let is_first_escaped = index == 0 && (byte == b'.' || reserved);
+        if is_safe && !is_first_escaped {
+            escaped.push(byte as char);
+        } else {
+            let _ = write!(escaped, "%{byte:02X}");
+        }
+    }
+    escaped
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// PATH → URL
+// ————————————————————————————————————————————————————————————————————————————
+
+/// The URL whose snapshot directory is `directory`, relative to the project
+/// directory. A trailing snapshot file name is ignored.
+///
+/// Returns `None` for hashed `~long/` directories and for anything this
+/// module did not produce; those can only be resolved through the snapshot
+/// log stored in the directory.
+pub fn url_from_rel_snapshot_dir(directory: impl AsRef<Path>) -> Option<Url> {
+    let mut segments = directory
+        .as_ref()
+        .components()
+        .map(|component| match component {
+            Component::Normal(segment) => segment.to_str(),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()?;
+    if segments.last() == Some(&SNAPSHOT_FILE_NAME) {
+        segments.pop();
+    }
+    // Disambiguated by `resolve_snapshot_file_path`.
+    if let Some(last) = segments.last() {
+        let counter = last.strip_prefix('~').unwrap_or("");
+        if !counter.is_empty() && counter.bytes().all(|byte| byte.is_ascii_digit()) {
+            segments.pop();
+        }
+    }
+    let (host, segments) = segments.split_first()?;
+    let (scheme, host, port) = match host.split('~').collect::<Vec<_>>().as_slice() {
+        [scheme, host] => (*scheme, *host, None),
+        [scheme, host, port] if !port.is_empty() && port.bytes().all(|byte| byte.is_ascii_digit()) => {
+            (*scheme, *host, Some(*port))
+        }
+        _ => return None,
+    };
+    let mut url = format!("{}://{}", unescape_segment(scheme)?, unescape_segment(host)?);
+    if let Some(port) = port {
+        url.push(':');
+        url.push_str(port);
+    }
+    let (segments, query) = match segments.split_last() {
+        Some((last, rest)) if last.starts_with(QUERY_PREFIX) => (rest, Some(&last[QUERY_PREFIX.len()..])),
+        _ => (segments, None),
     };
+    url.push('/');
+    let path = segments
+        .iter()
+        .map(|segment| match *segment {
+            EMPTY_SEGMENT => Some(String::new()),
+            segment if segment.starts_with('~') => None,
+            segment => unescape_segment(segment),
+        })
+        .collect::<Option<Vec<_>>>()?;
+    url.push_str(&path.join("/"));
+    if let Some(query) = query {
+        url.push('?');
+        url.push_str(&unescape_segment(query)?);
+    }
+    Url::parse(&url).ok()
+}
+
+fn unescape_segment(segment: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(segment.len());
+    let mut rest = segment.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = std::str::from_utf8(tail.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
 
-    let mut full_path = PathBuf::from(host);
-    let mut total_len = host.len();
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    let mut segments: Vec<String> = path
-        .split('/')
-        .map(sanitize)
-        .collect();
+    fn snapshot_dir(url: &str) -> String {
+        build_rel_html_snapshot_dir(url).unwrap().to_str().unwrap().to_string()
+    }
+
+    /// Map `url` to its snapshot file path and back.
+    fn round_trip(url: &str) -> Option<String> {
+        let file_path = build_rel_html_snapshot_file_path(url).unwrap();
+        url_from_rel_snapshot_dir(&file_path.0).map(String::from)
+    }
 
-    // Append sanitized query to the last segment
-    if !query_suffix.is_empty() {
-        if let Some(last) = segments.last_mut() {
-            last.push_str(&query_suffix);
+    #[test]
+    fn always_writes_the_scheme() {
+        assert_eq!(snapshot_dir("https://example.com/"), "https~example.com");
+        assert_eq!(snapshot_dir("http://example.com/docs/"), "http~example.com/docs/~index");
+        assert_eq!(snapshot_dir("https://example.com/a/b.html"), "https~example.com/a/b.html");
+    }
+
+    #[test]
+    fn schemes_and_ports_never_share_a_directory() {
+        assert_eq!(snapshot_dir("foo://8080/"), "foo~8080");
+        assert_eq!(snapshot_dir("https://foo:8080/"), "https~foo~8080");
+        assert_eq!(round_trip("foo://8080/").as_deref(), Some("foo://8080/"));
+        assert_eq!(round_trip("https://foo:8080/").as_deref(), Some("https://foo:8080/"));
+        // Default ports are not part of the URL.
+        assert_eq!(snapshot_dir("http://example.com:80/"), "http~example.com");
+        assert_eq!(round_trip("http://example.com:8080/x").as_deref(), Some("http://example.com:8080/x"));
+    }
+
+    #[test]
+    fn round_trips_ipv6_hosts() {
+        assert_eq!(snapshot_dir("http://[::1]:8080/"), "http~%5B%3A%3A1%5D~8080");
+        assert_eq!(round_trip("http://[::1]:8080/a").as_deref(), Some("http://[::1]:8080/a"));
+        assert_eq!(round_trip("https://[2001:db8::1]/").as_deref(), Some("https://[2001:db8::1]/"));
+    }
+
+    #[test]
+    fn round_trips_paths_and_queries() {
+        let urls = [
+            "https://example.com/",
+            "https://example.com/docs/",
+            "https://example.com/docs//guide",
+            "https://example.com/Docs/Guide.HTML",
+            "https://example.com/caf%C3%A9/100%25",
+            "https://example.com/a~b/~index",
+            "https://example.com/search?q=a+b&page=2",
+            "https://example.com/search?q=%2F%7E&Sort=DESC",
+            "https://example.com/?",
+        ];
+        for url in urls {
+            assert_eq!(round_trip(url).as_deref(), Some(url));
         }
+        // Fragments address the same document.
+        assert_eq!(snapshot_dir("https://example.com/a#top"), snapshot_dir("https://example.com/a"));
     }
 
-    for seg in &segments {
-        total_len += seg.len() + 1;
-        if seg.len() > MAX_SEGMENT_LEN || total_len > MAX_PATH_LEN {
-            let hashed = short_hash(url);
-            return Some(PathBuf::from(host).join("long").join(hashed));
+    #[test]
+    fn keeps_case_and_markers_apart() {
+        assert_ne!(snapshot_dir("https://example.com/Docs"), snapshot_dir("https://example.com/docs"));
+        assert_eq!(snapshot_dir("https://example.com/~index"), "https~example.com/%7Eindex");
+        assert_eq!(snapshot_dir("https://example.com/?a"), "https~example.com/~q~a");
+        assert_eq!(snapshot_dir("https://example.com/~q~a"), "https~example.com/%7Eq%7Ea");
+    }
+
+    #[test]
+    fn escapes_reserved_names() {
+        assert_eq!(
+            snapshot_dir("https://example.com/source.snapshot.html"),
+            "https~example.com/%73ource.snapshot.html",
+        );
+        assert_eq!(snapshot_dir("https://example.com/page.har"), "https~example.com/%70age.har");
+        assert_eq!(snapshot_dir("https://example.com/.well-known"), "https~example.com/%2Ewell-known");
+        for url in ["https://example.com/source.snapshot.html", "https://example.com/page.har"] {
+            assert_eq!(round_trip(url).as_deref(), Some(url));
         }
-        full_path = full_path.join(seg);
     }
 
-    Some(full_path)
+    #[test]
+    fn hashes_long_paths() {
+        let url = format!("https://example.com/{}", "segment/".repeat(40));
+        let directory = snapshot_dir(&url);
+        let (prefix, hash) = directory.rsplit_once('/').unwrap();
+        assert_eq!(prefix, "https~example.com/~long");
+        assert_eq!(hash.len(), 32);
+        assert_eq!(snapshot_dir(&format!("{url}#top")), directory);
+        assert_ne!(snapshot_dir(&format!("{url}x")), directory);
+        // Only the snapshot log knows which URL a hash stands for.
+        assert_eq!(round_trip(&url), None);
+        let url = format!("https://example.com/{}", "a".repeat(MAX_SEGMENT_LEN + 1));
+        assert!(snapshot_dir(&url).starts_with("https~example.com/~long/"));
+    }
+
+    #[test]
+    fn resolves_taken_directories_to_numbered_ones() {
+        let taken = [
+            "https~example.com/docs/source.snapshot.html",
+            "https~example.com/docs/~2/source.snapshot.html",
+        ];
+        let file_path = resolve_snapshot_file_path("https://example.com/docs", |path| {
+            taken.iter().any(|taken| path.0 == Path::new(taken))
+        }).unwrap();
+        assert_eq!(file_path.0, Path::new("https~example.com/docs/~3/source.snapshot.html"));
+        assert_eq!(url_from_rel_snapshot_dir(&file_path.0).unwrap().as_str(), "https://example.com/docs");
+    }
+
+    #[test]
+    fn rejects_directories_it_did_not_produce() {
+        let directories = [
+            // Written before the scheme was always part of the host directory.
+            "example.com",
+            "https~example.com~x",
+            "https~example.com~",
+            "../https~example.com",
+            "https~example.com/~tmp",
+            "https~example.com/%zz",
+        ];
+        for directory in directories {
+            assert_eq!(url_from_rel_snapshot_dir(directory), None, "{directory}");
+        }
+    }
 }
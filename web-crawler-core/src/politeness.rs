@@ -0,0 +1,220 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::Instant;
+use url::Url;
+
+// ————————————————————————————————————————————————————————————————————————————
+// SETTINGS
+// ————————————————————————————————————————————————————————————————————————————
+
+#[derive(Debug, Clone)]
+pub struct PolitenessSettings {
+    /// Minimum delay between two requests to the same host.
+    pub min_delay: Duration,
+    /// Maximum number of pages open against the same host at once.
+    pub max_in_flight_per_host: usize,
+    /// Upper bound for the adaptive backoff after a 429/503 response.
+    pub max_backoff: Duration,
+}
+
+impl Default for PolitenessSettings {
+    fn default() -> Self {
+        Self {
+            min_delay: Duration::from_millis(1000),
+            max_in_flight_per_host: 2,
+            max_backoff: Duration::from_secs(120),
+        }
+    }
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// SCHEDULER
+// ————————————————————————————————————————————————————————————————————————————
+
+/// Spaces out requests per host.
+///
+/// Workers call [`HostScheduler::acquire`] before opening a page and
+/// [`HostScheduler::release`] once they are done with it, reporting the HTTP
+/// status so that throttling responses slow the host down.
+#[derive(Debug)]
+pub struct HostScheduler {
+    settings: PolitenessSettings,
+    hosts: Mutex<HashMap<HostKey, HostState>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct HostKey(pub String);
+
+impl HostKey {
+    pub fn from_url(url: &Url) -> Self {
+        let host = url.host_str().unwrap_or_default().to_ascii_lowercase();
+        match url.port() {
+            Some(port) => Self(format!("{host}:{port}")),
+            None => Self(host),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct HostState {
+    in_flight: usize,
+    next_allowed: Instant,
+    /// `Crawl-delay` requested by the host's robots.txt, if any.
+    crawl_delay: Duration,
+    /// Extra delay added on top of `min_delay` after throttling responses.
+    backoff: Duration,
+}
+
+impl HostState {
+    fn new() -> Self {
+        Self {
+            in_flight: 0,
+            next_allowed: Instant::now(),
+            crawl_delay: Duration::ZERO,
+            backoff: Duration::ZERO,
+        }
+    }
+}
+
+impl HostScheduler {
+    pub fn new(settings: PolitenessSettings) -> Self {
+        Self {
+            settings,
+            hosts: Default::default(),
+        }
+    }
+    /// Wait until the host of `url` may receive another request.
+    pub async fn acquire(&self, url: &Url) -> HostKey {
+        let key = HostKey::from_url(url);
+        let max_in_flight = self.settings.max_in_flight_per_host.max(1);
+        loop {
+            let wait = {
+                let mut hosts = self.hosts.lock().unwrap();
+                let now = Instant::now();
+                let state = hosts.entry(key.clone()).or_insert_with(HostState::new);
+                if state.in_flight < max_in_flight && now >= state.next_allowed {
+                    state.in_flight += 1;
+                    let delay = self.settings.min_delay.max(state.crawl_delay);
+                    state.next_allowed = now + delay + state.backoff;
+                    return key
+                }
+                if now < state.next_allowed {
+                    state.next_allowed - now
+                } else {
+                    Duration::from_millis(100)
+                }
+            };
+            tokio::time::sleep(wait).await;
+        }
+    }
+    /// Return the slot taken by [`HostScheduler::acquire`].
+    ///
+    /// A 429 or 503 doubles the host's backoff (up to `max_backoff`), any other
+    /// response halves it again.
+    pub fn release(&self, key: &HostKey, http_status: Option<i64>) {
+        let mut hosts = self.hosts.lock().unwrap();
+        let Some(state) = hosts.get_mut(key) else {
+            return
+        };
+        state.in_flight = state.in_flight.saturating_sub(1);
+        match http_status {
+            Some(429) | Some(503) => {
+                let doubled = (state.backoff * 2).max(self.settings.min_delay.max(Duration::from_secs(1)));
+                state.backoff = doubled.min(self.settings.max_backoff);
+                state.next_allowed = state.next_allowed.max(Instant::now() + state.backoff);
+            }
+            Some(_) => {
+                state.backoff /= 2;
+                if state.backoff < Duration::from_millis(100) {
+                    state.backoff = Duration::ZERO;
+                }
+            }
+            None => (),
+        }
+    }
+    /// Honour a robots.txt `Crawl-delay` for the host of `url`.
+    pub fn set_crawl_delay(&self, url: &Url, crawl_delay: Duration) {
+        let mut hosts = self.hosts.lock().unwrap();
+        let state = hosts
+            .entry(HostKey::from_url(url))
+            .or_insert_with(HostState::new);
+        state.crawl_delay = crawl_delay;
+    }
+    /// Current backoff for the host of `url`, if any.
+    pub fn backoff_for(&self, url: &Url) -> Duration {
+        let hosts = self.hosts.lock().unwrap();
+        hosts
+            .get(&HostKey::from_url(url))
+            .map(|state| state.backoff)
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(source: &str) -> Url {
+        Url::parse(source).unwrap()
+    }
+
+    fn scheduler(max_in_flight_per_host: usize) -> HostScheduler {
+        HostScheduler::new(PolitenessSettings {
+            min_delay: Duration::ZERO,
+            max_in_flight_per_host,
+            max_backoff: Duration::from_secs(5),
+        })
+    }
+
+    #[test]
+    fn throttling_doubles_the_backoff_up_to_the_cap() {
+        let scheduler = scheduler(1);
+        let page = url("https://example.com/");
+        let key = HostKey::from_url(&page);
+        scheduler.set_crawl_delay(&page, Duration::ZERO);
+        let mut backoffs = Vec::new();
+        for http_status in [429, 503, 429, 429, 429] {
+            scheduler.release(&key, Some(http_status));
+            backoffs.push(scheduler.backoff_for(&page).as_secs());
+        }
+        assert_eq!(backoffs, [1, 2, 4, 5, 5]);
+    }
+
+    #[test]
+    fn other_responses_halve_the_backoff_until_it_is_gone() {
+        let scheduler = scheduler(1);
+        let page = url("https://example.com/");
+        let key = HostKey::from_url(&page);
+        scheduler.set_crawl_delay(&page, Duration::ZERO);
+        scheduler.release(&key, Some(429));
+        scheduler.release(&key, Some(429));
+        scheduler.release(&key, Some(200));
+        assert_eq!(scheduler.backoff_for(&page), Duration::from_secs(1));
+        // No response says nothing about the host.
+        scheduler.release(&key, None);
+        assert_eq!(scheduler.backoff_for(&page), Duration::from_secs(1));
+        for _ in 0..4 {
+            scheduler.release(&key, Some(404));
+        }
+        assert_eq!(scheduler.backoff_for(&page), Duration::ZERO);
+        // Other hosts are not affected.
+        assert_eq!(scheduler.backoff_for(&url("https://other.com/")), Duration::ZERO);
+    }
+
+    #[tokio::test]
+    async fn hosts_get_at_most_their_in_flight_limit() {
+        let scheduler = scheduler(2);
+        let page = url("https://example.com/");
+        let first = scheduler.acquire(&page).await;
+        let _second = scheduler.acquire(&page).await;
+        let third = tokio::time::timeout(Duration::from_millis(300), scheduler.acquire(&page)).await;
+        assert!(third.is_err());
+        // Other hosts and ports have their own slots.
+        scheduler.acquire(&url("https://other.com/")).await;
+        assert_eq!(scheduler.acquire(&url("https://example.com:8443/")).await.0, "example.com:8443");
+        scheduler.release(&first, Some(200));
+        let third = tokio::time::timeout(Duration::from_millis(300), scheduler.acquire(&page)).await;
+        assert!(third.is_ok());
+    }
+}
@@ -0,0 +1,143 @@
+use std::path::Path;
+use std::time::Duration;
+
+use crate::metadata::common::{RelativeFilePath, SnapshotDate};
+use crate::metadata::snapshot::SnapshotLog;
+
+// ————————————————————————————————————————————————————————————————————————————
+// SETTINGS
+// ————————————————————————————————————————————————————————————————————————————
+
+/// Revisiting pages that already have a snapshot.
+#[derive(Debug, Clone, Default)]
+pub struct RecrawlSettings {
+    /// Pages last checked longer ago than this are visited again. `None`
+    /// visits every page only once.
+    pub max_age: Option<Duration>,
+}
+
+impl RecrawlSettings {
+    pub fn is_due(&self, log: &SnapshotLog) -> bool {
+        let Some(max_age) = self.max_age else {
+            return false
+        };
+        let last_checked = log.last_checked.as_ref().unwrap_or(&log.snapshot_date);
+        match last_checked.age() {
+            Some(age) => age >= max_age,
+            // Unreadable dates are treated as stale rather than never retried.
+            None => true,
+        }
+    }
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// VERSIONS
+// ————————————————————————————————————————————————————————————————————————————
+
+/// Directory, next to `source.snapshot.html`, holding superseded snapshots.
+/// Starts with a dot so it can never clash with a sanitized URL segment.
+pub const VERSIONS_DIRECTORY_NAME: &str = ".versions";
+
+/// `sha256:<hex>` of a snapshot's contents.
+pub fn content_hash(contents: &[u8]) -> String {
+    use sha2::{Digest, Sha256};
+    let hash = Sha256::digest(contents);
+    let hex = hash
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<String>();
+    format!("sha256:{hex}")
+}
+
+/// Move the snapshot at `snapshot_path` into the versions directory, named
+/// after the date it was taken, and return its new relative path.
+pub fn archive_snapshot(
+    project_directory: impl AsRef<Path>,
+    snapshot_path: &RelativeFilePath,
+    snapshot_date: &SnapshotDate,
+) -> Result<RelativeFilePath, std::io::Error> {
+    let project_directory = project_directory.as_ref();
+    let file_name = format!("{}.snapshot.html", snapshot_date.0.replace(':', "-"));
+    let archived_path = snapshot_path.0
+        .parent()
+        .unwrap_or(Path::new(""))
+        .join(VERSIONS_DIRECTORY_NAME)
+        .join(file_name);
+    let target = project_directory.join(&archived_path);
+    std::fs::create_dir_all(target.parent().unwrap())?;
+    std::fs::rename(project_directory.join(&snapshot_path.0), &target)?;
+    Ok(RelativeFilePath(archived_path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TempDirectory;
+
+    const SNAPSHOT_LOG: &str = r#"
+        http_status = 200
+        original_url = "https://example.com/docs"
+        canonical_url = "https://example.com/docs"
+        snapshot_path = "file://./example.com/docs/source.snapshot.html"
+        snapshot_date = "2024-05-01T10:00:00+00:00"
+        outgoing_links = []
+        incoming_links = []
+    "#;
+
+    fn snapshot_log(snapshot_date: SnapshotDate, last_checked: Option<SnapshotDate>) -> SnapshotLog {
+        SnapshotLog {
+            snapshot_date,
+            last_checked,
+            ..toml::from_str(SNAPSHOT_LOG).unwrap()
+        }
+    }
+
+    fn ago(age: Duration) -> SnapshotDate {
+        SnapshotDate((chrono::Utc::now() - age).to_rfc3339())
+    }
+
+    #[test]
+    fn pages_are_due_once_older_than_max_age() {
+        let settings = RecrawlSettings { max_age: Some(Duration::from_secs(3600)) };
+        assert!(!settings.is_due(&snapshot_log(ago(Duration::from_secs(60)), None)));
+        assert!(settings.is_due(&snapshot_log(ago(Duration::from_secs(7200)), None)));
+        // The last check counts, not when the content last changed.
+        let checked_recently = snapshot_log(ago(Duration::from_secs(7200)), Some(ago(Duration::from_secs(60))));
+        assert!(!settings.is_due(&checked_recently));
+        // Unreadable dates are retried rather than kept forever.
+        assert!(settings.is_due(&snapshot_log(SnapshotDate(String::from("yesterday")), None)));
+    }
+
+    #[test]
+    fn without_max_age_nothing_is_due() {
+        let settings = RecrawlSettings::default();
+        assert!(!settings.is_due(&snapshot_log(ago(Duration::from_secs(365 * 24 * 3600)), None)));
+        assert!(!settings.is_due(&snapshot_log(SnapshotDate(String::from("yesterday")), None)));
+    }
+
+    #[test]
+    fn archived_snapshots_are_named_after_their_date() {
+        let directory = TempDirectory::new("recrawl-archive");
+        let snapshot_path = RelativeFilePath("example.com/docs/source.snapshot.html".into());
+        std::fs::create_dir_all(directory.join("example.com/docs")).unwrap();
+        std::fs::write(directory.join(&snapshot_path.0), "old").unwrap();
+        let date = SnapshotDate(String::from("2024-05-01T10:00:00+00:00"));
+        let archived = archive_snapshot(&directory, &snapshot_path, &date).unwrap();
+        assert_eq!(
+            archived.0,
+            Path::new("example.com/docs/.versions/2024-05-01T10-00-00+00-00.snapshot.html"),
+        );
+        assert_eq!(std::fs::read_to_string(directory.join(&archived.0)).unwrap(), "old");
+        assert!(!directory.join(&snapshot_path.0).exists());
+        // Nothing left to archive.
+        assert!(archive_snapshot(&directory, &snapshot_path, &date).is_err());
+    }
+
+    #[test]
+    fn content_hashes_are_prefixed_sha256() {
+        assert_eq!(
+            content_hash(b"abc"),
+            "sha256:ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+        );
+    }
+}
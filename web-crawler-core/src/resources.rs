@@ -0,0 +1,181 @@
+use url::Url;
+
+// ————————————————————————————————————————————————————————————————————————————
+// SETTINGS
+// ————————————————————————————————————————————————————————————————————————————
+
+/// Downloading linked documents that are not HTML (PDFs, images, CSV, …).
+#[derive(Debug, Clone)]
+pub struct ResourceSettings {
+    /// Off by default: non-HTML documents are recorded as failures.
+    pub enabled: bool,
+    /// MIME types to keep, e.g. `application/pdf` or `image/*`.
+    pub allowed_mime_types: Vec<String>,
+    /// Larger documents are skipped. `None` keeps everything.
+    pub max_size: Option<u64>,
+}
+
+impl Default for ResourceSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            allowed_mime_types: Self::default_allowed_mime_types(),
+            max_size: Some(Self::DEFAULT_MAX_SIZE),
+        }
+    }
+}
+
+impl ResourceSettings {
+    pub const DEFAULT_MAX_SIZE: u64 = 50 * 1024 * 1024;
+    pub fn default_allowed_mime_types() -> Vec<String> {
+        [
+            "application/pdf",
+            "image/*",
+            "text/csv",
+            "text/plain",
+            "application/json",
+        ]
+        .into_iter()
+        .map(String::from)
+        .collect()
+    }
+    pub fn is_allowed(&self, mime_type: &str) -> bool {
+        let mime_type = mime_essence(mime_type);
+        self.allowed_mime_types
+            .iter()
+            .any(|pattern| mime_type_matches(pattern, &mime_type))
+    }
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// MIME TYPES
+// ————————————————————————————————————————————————————————————————————————————
+
+/// `text/html; charset=utf-8` → `text/html`.
+pub fn mime_essence(mime_type: &str) -> String {
+    mime_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase()
+}
+
+pub fn is_html_mime_type(mime_type: &str) -> bool {
+    matches!(mime_essence(mime_type).as_str(), "text/html" | "application/xhtml+xml")
+}
+
+/// `*/*`, `type/*` or an exact match.
+fn mime_type_matches(pattern: &str, mime_type: &str) -> bool {
+    let pattern = mime_essence(pattern);
+    match pattern.split_once('/') {
+        Some(("*", "*")) => true,
+        Some((kind, "*")) => mime_type.split('/').next() == Some(kind),
+        _ => pattern == mime_type,
+    }
+}
+
+/// The cap a document of `size` bytes exceeds, if any; `None` as the cap
+/// keeps everything.
+pub fn exceeded_max_size(size: u64, max_size: Option<u64>) -> Option<u64> {
+    max_size.filter(|max_size| size > *max_size)
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// FILES
+// ————————————————————————————————————————————————————————————————————————————
+
+/// File name for a downloaded document, next to where its HTML snapshot
+/// would be. The extension comes from the MIME type, then the URL.
+pub fn resource_file_name(mime_type: &str, url: &Url) -> String {
+    let known = match mime_essence(mime_type).as_str() {
+        "application/pdf" => Some("pdf"),
+        "image/png" => Some("png"),
+        "image/jpeg" => Some("jpg"),
+        "image/gif" => Some("gif"),
+        "image/webp" => Some("webp"),
+        "image/svg+xml" => Some("svg"),
+        "image/avif" => Some("avif"),
+        "text/csv" => Some("csv"),
+        "text/plain" => Some("txt"),
+        "application/json" => Some("json"),
+        "application/xml" | "text/xml" => Some("xml"),
+        "application/zip" => Some("zip"),
+        _ => None,
+    };
+    let from_url = || {
+        let last_segment = url.path_segments()?.next_back()?;
+        let (_, extension) = last_segment.rsplit_once('.')?;
+        let valid = !extension.is_empty()
+            && extension.len() <= 8
+            && extension.chars().all(|c| c.is_ascii_alphanumeric());
+        valid.then(|| extension.to_ascii_lowercase())
+    };
+    let extension = known
+        .map(String::from)
+        .or_else(from_url)
+        .unwrap_or_else(|| String::from("bin"));
+    format!("source.resource.{extension}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(allowed_mime_types: &[&str]) -> ResourceSettings {
+        ResourceSettings {
+            enabled: true,
+            allowed_mime_types: allowed_mime_types.iter().map(|x| x.to_string()).collect(),
+            max_size: None,
+        }
+    }
+
+    #[test]
+    fn the_default_allow_list() {
+        let settings = ResourceSettings::default();
+        assert!(settings.is_allowed("application/pdf"));
+        assert!(settings.is_allowed("image/svg+xml"));
+        assert!(settings.is_allowed("text/csv; charset=utf-8"));
+        assert!(!settings.is_allowed("application/zip"));
+        assert!(!settings.is_allowed("video/mp4"));
+    }
+
+    #[test]
+    fn mime_types_match_exactly_by_kind_or_wildcard() {
+        let exact = settings(&["Application/PDF"]);
+        assert!(exact.is_allowed("application/pdf"));
+        assert!(exact.is_allowed("APPLICATION/PDF; name=report.pdf"));
+        assert!(!exact.is_allowed("application/pdfx"));
+        let kind = settings(&["image/*"]);
+        assert!(kind.is_allowed("image/png"));
+        assert!(!kind.is_allowed("imagex/png"));
+        assert!(!kind.is_allowed("text/plain"));
+        assert!(settings(&["*/*"]).is_allowed("anything/at-all"));
+        assert!(!settings(&[]).is_allowed("application/pdf"));
+    }
+
+    #[test]
+    fn html_is_recognized_with_parameters() {
+        assert!(is_html_mime_type("text/html"));
+        assert!(is_html_mime_type("Text/HTML; charset=utf-8"));
+        assert!(is_html_mime_type("application/xhtml+xml"));
+        assert!(!is_html_mime_type("text/plain"));
+    }
+
+    #[test]
+    fn sizes_above_the_cap_are_rejected() {
+        assert_eq!(exceeded_max_size(100, Some(100)), None);
+        assert_eq!(exceeded_max_size(101, Some(100)), Some(100));
+        assert_eq!(exceeded_max_size(u64::MAX, None), None);
+    }
+
+    #[test]
+    fn file_names_take_the_extension_from_the_mime_type_then_the_url() {
+        let url = |source: &str| Url::parse(source).unwrap();
+        assert_eq!(resource_file_name("application/pdf", &url("https://example.com/download")), "source.resource.pdf");
+        assert_eq!(resource_file_name("image/jpeg", &url("https://example.com/photo.jpeg")), "source.resource.jpg");
+        assert_eq!(resource_file_name("application/octet-stream", &url("https://example.com/data.TAR")), "source.resource.tar");
+        assert_eq!(resource_file_name("application/octet-stream", &url("https://example.com/file.a-b")), "source.resource.bin");
+        assert_eq!(resource_file_name("application/octet-stream", &url("https://example.com/")), "source.resource.bin");
+    }
+}
@@ -0,0 +1,176 @@
+use std::time::Duration;
+
+use crate::metadata::common::{FailureKind, FailureReason};
+use crate::metadata::snapshot::TaskLog;
+
+// ————————————————————————————————————————————————————————————————————————————
+// SETTINGS
+// ————————————————————————————————————————————————————————————————————————————
+
+/// Which URLs a run visits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CrawlMode {
+    /// Seeds, sitemaps, the frontier journal and every link found on the way.
+    #[default]
+    Full,
+    /// Only URLs whose task log records a retryable failure. Links found on
+    /// retried pages are added to the frontier journal for the next full run
+    /// instead of being visited.
+    RetryFailed,
+}
+
+/// How often and how soon failed URLs are visited again.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Attempts per URL, including the first. `1` disables retries.
+    pub max_attempts: usize,
+    /// Delay before the first retry; doubled after every further attempt.
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// Failures worth another attempt; everything else is terminal.
+    pub retryable: Vec<RetryableFailure>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_secs(5),
+            max_backoff: Duration::from_secs(300),
+            retryable: RetryableFailure::ALL.to_vec(),
+        }
+    }
+}
+
+/// A class of failures that is likely to be transient.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RetryableFailure {
+    /// HTTP 5xx.
+    ServerError,
+    /// HTTP 429.
+    TooManyRequests,
+    /// HTTP 408.
+    RequestTimeout,
+    /// Nothing was received for the main document.
+    NoResponse,
+    /// An operation on the page timed out.
+    Timeout,
+    /// The browser failed, e.g. because the tab crashed.
+    Browser,
+}
+
+impl RetryableFailure {
+    pub const ALL: [Self; 6] = [
+        Self::ServerError,
+        Self::TooManyRequests,
+        Self::RequestTimeout,
+        Self::NoResponse,
+        Self::Timeout,
+        Self::Browser,
+    ];
+    /// Failures logged before they were classified have no `reason`; those
+    /// are matched on their HTTP status alone.
+    pub fn matches(&self, http_status: Option<i64>, reason: Option<&FailureReason>) -> bool {
+        let kind = reason.map(|reason| reason.kind);
+        match self {
+            Self::ServerError => matches!(http_status, Some(500..=599)),
+            Self::TooManyRequests => http_status == Some(429),
+            Self::RequestTimeout => http_status == Some(408),
+            Self::NoResponse => match kind {
+                Some(kind) => kind == FailureKind::NoResponse,
+                None => http_status.is_none(),
+            },
+            Self::Timeout => kind == Some(FailureKind::Timeout),
+            Self::Browser => kind == Some(FailureKind::Browser),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Whether the failure is in one of the retryable classes.
+    pub fn is_retryable(&self, http_status: Option<i64>, reason: Option<&FailureReason>) -> bool {
+        self.retryable
+            .iter()
+            .any(|class| class.matches(http_status, reason))
+    }
+    /// Whether the URL of `task_log` failed in a retryable way and has
+    /// attempts left.
+    pub fn should_retry(&self, task_log: &TaskLog) -> bool {
+        if task_log.attempts() >= self.max_attempts {
+            return false
+        }
+        task_log
+            .failures()
+            .any(|(http_status, reason)| self.is_retryable(http_status, reason))
+    }
+    /// Delay before the next attempt, after `attempts` attempts so far.
+    pub fn backoff(&self, attempts: usize) -> Duration {
+        let exponent = attempts.saturating_sub(1).min(16) as u32;
+        self.initial_backoff
+            .saturating_mul(2u32.pow(exponent))
+            .min(self.max_backoff)
+    }
+    /// What is left of the backoff since the last attempt of `task_log`.
+    pub fn remaining_backoff(&self, task_log: &TaskLog) -> Duration {
+        let backoff = self.backoff(task_log.attempts());
+        let elapsed = task_log.last_attempt
+            .as_ref()
+            .and_then(|date| date.age())
+            .unwrap_or(backoff);
+        backoff.saturating_sub(elapsed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::common::{OriginalUrl, SnapshotDate, Status};
+
+    fn failed_task_log(attempts: usize, last_attempt: SnapshotDate) -> TaskLog {
+        TaskLog {
+            attempts,
+            last_attempt: Some(last_attempt),
+            entries: vec![Status::Failure {
+                url: OriginalUrl(url::Url::parse("https://example.com/").unwrap()),
+                http_status: Some(503),
+                reason: None,
+            }],
+            ..TaskLog::default()
+        }
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_maximum() {
+        let retry = RetryPolicy::default();
+        assert_eq!(retry.backoff(1), Duration::from_secs(5));
+        assert_eq!(retry.backoff(2), Duration::from_secs(10));
+        assert_eq!(retry.backoff(3), Duration::from_secs(20));
+        assert_eq!(retry.backoff(100), Duration::from_secs(300));
+    }
+
+    #[test]
+    fn a_fresh_failure_is_not_due_yet() {
+        let retry = RetryPolicy::default();
+        let log = failed_task_log(1, SnapshotDate::now());
+        assert!(retry.should_retry(&log));
+        let remaining = retry.remaining_backoff(&log);
+        assert!(remaining > Duration::from_secs(4) && remaining <= Duration::from_secs(5));
+        let log = failed_task_log(1, SnapshotDate(String::from("2024-05-01T10:00:00+00:00")));
+        assert_eq!(retry.remaining_backoff(&log), Duration::ZERO);
+    }
+
+    #[test]
+    fn retries_stop_after_the_last_attempt() {
+        let retry = RetryPolicy::default();
+        assert!(!retry.should_retry(&failed_task_log(3, SnapshotDate::now())));
+        let not_found = TaskLog {
+            entries: vec![Status::Failure {
+                url: OriginalUrl(url::Url::parse("https://example.com/").unwrap()),
+                http_status: Some(404),
+                reason: None,
+            }],
+            ..TaskLog::default()
+        };
+        assert!(!retry.should_retry(&not_found));
+    }
+}
@@ -0,0 +1,432 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use colored::Colorize;
+use url::Url;
+
+use crate::http::HttpFetcher;
+
+// ————————————————————————————————————————————————————————————————————————————
+// SETTINGS
+// ————————————————————————————————————————————————————————————————————————————
+
+#[derive(Debug, Clone)]
+pub struct RobotsSettings {
+    /// Whether robots.txt rules are fetched and enforced at all.
+    pub enabled: bool,
+    /// Sent as the `User-Agent` header. Its product token, the part before
+    /// the first `/` or space, is matched against `User-agent` lines.
+    pub user_agent: String,
+    /// How long an origin whose robots.txt could not be fetched stays fully
+    /// disallowed before it is fetched again.
+    pub unreachable_retry: Duration,
+}
+
+impl Default for RobotsSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            user_agent: String::from("web-crawler"),
+            unreachable_retry: Duration::from_secs(10 * 60),
+        }
+    }
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// ROBOTS.TXT
+// ————————————————————————————————————————————————————————————————————————————
+
+/// A parsed robots.txt file (RFC 9309).
+#[derive(Debug, Clone, Default)]
+pub struct RobotsTxt {
+    groups: Vec<RobotsGroup>,
+    /// `Sitemap:` lines, which apply regardless of user agent.
+    sitemaps: Vec<Url>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct RobotsGroup {
+    user_agents: Vec<String>,
+    rules: Vec<RobotsRule>,
+    crawl_delay: Option<Duration>,
+}
+
+#[derive(Debug, Clone)]
+struct RobotsRule {
+    allow: bool,
+    pattern: String,
+}
+
+impl RobotsTxt {
+    /// Everything is allowed, e.g. when robots.txt does not exist.
+    pub fn allow_all() -> Self {
+        Self::default()
+    }
+    /// Nothing is allowed, e.g. when robots.txt is unreachable.
+    pub fn disallow_all() -> Self {
+        Self {
+            groups: vec![RobotsGroup {
+                user_agents: vec![String::from("*")],
+                rules: vec![RobotsRule { allow: false, pattern: String::from("/") }],
+                crawl_delay: None,
+            }],
+            sitemaps: Vec::new(),
+        }
+    }
+    pub fn parse(source: &str) -> Self {
+        let mut groups = Vec::<RobotsGroup>::new();
+        let mut sitemaps = Vec::<Url>::new();
+        let mut expecting_user_agents = false;
+        for line in source.lines() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            let Some((key, value)) = line.split_once(':') else {
+                continue
+            };
+            let key = key.trim().to_ascii_lowercase();
+            let value = value.trim();
+            match key.as_str() {
+                "user-agent" => {
+                    if !expecting_user_agents || groups.is_empty() {
+                        groups.push(RobotsGroup::default());
+                    }
+                    expecting_user_agents = true;
+                    groups.last_mut().unwrap().user_agents.push(product_token(value));
+                }
+                "allow" | "disallow" => {
+                    expecting_user_agents = false;
+                    let Some(group) = groups.last_mut() else {
+                        continue
+                    };
+                    if value.is_empty() {
+                        continue
+                    }
+                    group.rules.push(RobotsRule {
+                        allow: key == "allow",
+                        pattern: value.to_string(),
+                    });
+                }
+                "crawl-delay" => {
+                    expecting_user_agents = false;
+                    let Some(group) = groups.last_mut() else {
+                        continue
+                    };
+                    if let Ok(seconds) = value.parse::<f64>()
+                        && seconds.is_finite()
+                        && seconds >= 0.0
+                    {
+                        group.crawl_delay = Some(Duration::from_secs_f64(seconds));
+                    }
+                }
+                "sitemap" => {
+                    if let Ok(url) = Url::parse(value) {
+                        sitemaps.push(url);
+                    }
+                }
+                _ => {
+                    expecting_user_agents = false;
+                }
+            }
+        }
+        Self { groups, sitemaps }
+    }
+    /// The groups that apply to `user_agent`: every group naming its product
+    /// token, case-insensitively, or else the `*` groups.
+    fn matching_groups(&self, user_agent: &str) -> Vec<&RobotsGroup> {
+        let product_token = product_token(user_agent);
+        let specific = self.groups
+            .iter()
+            .filter(|group| group.user_agents.iter().any(|agent| agent != "*" && *agent == product_token))
+            .collect::<Vec<_>>();
+        if !specific.is_empty() {
+            return specific
+        }
+        self.groups
+            .iter()
+            .filter(|group| group.user_agents.iter().any(|agent| agent == "*"))
+            .collect()
+    }
+    /// The longest matching rule wins; on a tie `Allow` wins.
+    pub fn is_allowed(&self, user_agent: &str, url: &Url) -> bool {
+        let target = match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_string(),
+        };
+        let mut best: Option<&RobotsRule> = None;
+        for group in self.matching_groups(user_agent) {
+            for rule in group.rules.iter() {
+                if !pattern_matches(&rule.pattern, &target) {
+                    continue
+                }
+                best = match best {
+                    None => Some(rule),
+                    Some(current) if rule.pattern.len() > current.pattern.len() => Some(rule),
+                    Some(current) if rule.pattern.len() == current.pattern.len() && rule.allow => Some(rule),
+                    Some(current) => Some(current),
+                };
+            }
+        }
+        best.map(|rule| rule.allow).unwrap_or(true)
+    }
+    pub fn crawl_delay(&self, user_agent: &str) -> Option<Duration> {
+        self.matching_groups(user_agent)
+            .into_iter()
+            .filter_map(|group| group.crawl_delay)
+            .max()
+    }
+    pub fn sitemaps(&self) -> &[Url] {
+        &self.sitemaps
+    }
+}
+
+/// `Foo-Bot/2.1 (+https://foo.example)` → `foo-bot`
+fn product_token(user_agent: &str) -> String {
+    user_agent
+        .split(|c: char| c == '/' || c.is_whitespace())
+        .next()
+        .unwrap_or("")
+        .to_ascii_lowercase()
+}
+
+/// Match a robots.txt path pattern, supporting `*` wildcards and a trailing
+/// `$` end anchor.
+fn pattern_matches(pattern: &str, target: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(pattern) => (pattern, true),
+        None => (pattern, false),
+    };
+    let parts = pattern.split('*').collect::<Vec<_>>();
+    let Some(rest) = target.strip_prefix(parts[0]) else {
+        return false
+    };
+    if parts.len() == 1 {
+        return !anchored || rest.is_empty()
+    }
+    let mut rest = rest;
+    let last_index = parts.len() - 1;
+    for (index, part) in parts.iter().enumerate().skip(1) {
+        if index == last_index && anchored {
+            return rest.ends_with(part)
+        }
+        match rest.find(part) {
+            Some(position) => rest = &rest[position + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// CACHE
+// ————————————————————————————————————————————————————————————————————————————
+
+/// Fetches robots.txt once per origin and answers visit checks from memory.
+#[derive(Debug)]
+pub struct RobotsCache {
+    settings: RobotsSettings,
+    fetcher: HttpFetcher,
+    entries: Mutex<HashMap<String, CachedRobotsTxt>>,
+}
+
+#[derive(Debug)]
+struct CachedRobotsTxt {
+    robots: Arc<RobotsTxt>,
+    /// Set for the full disallow of an unreachable robots.txt, which is
+    /// fetched again afterwards.
+    expires: Option<Instant>,
+}
+
+impl RobotsCache {
+    pub fn new(settings: RobotsSettings, fetcher: HttpFetcher) -> Self {
+        Self {
+            settings,
+            fetcher,
+            entries: Default::default(),
+        }
+    }
+    fn origin_key(url: &Url) -> String {
+        url.origin().ascii_serialization()
+    }
+    /// Cached rules for the origin of `url`, if they were loaded already and
+    /// did not expire.
+    pub fn get(&self, url: &Url) -> Option<Arc<RobotsTxt>> {
+        let entries = self.entries.lock().unwrap();
+        let entry = entries.get(&Self::origin_key(url))?;
+        if entry.expires.is_some_and(|expires| expires <= Instant::now()) {
+            return None
+        }
+        Some(entry.robots.clone())
+    }
+    /// Fetch (or reuse) the robots.txt for the origin of `url`.
+    ///
+    /// A 4xx response means no restrictions; a 5xx or an unreachable server
+    /// means full disallow, as required by RFC 9309, until
+    /// `RobotsSettings::unreachable_retry` has passed.
+    pub async fn load(&self, url: &Url) -> Option<Arc<RobotsTxt>> {
+        if !self.settings.enabled {
+            return None
+        }
+        if url.scheme() != "http" && url.scheme() != "https" {
+            return None
+        }
+        if let Some(robots) = self.get(url) {
+            return Some(robots)
+        }
+        let robots_url = url.join("/robots.txt").ok()?;
+        let retry_at = Instant::now() + self.settings.unreachable_retry;
+        let (robots, expires) = match self.fetcher.get(&robots_url).await {
+            Ok(response) if response.is_success() => (RobotsTxt::parse(&response.text()), None),
+            Ok(response) if (400..500).contains(&response.http_status) => (RobotsTxt::allow_all(), None),
+            Ok(response) => {
+                eprintln!("{}", format!(
+                    "\t ⚠️ robots.txt at {:?} returned {} — treating origin as disallowed",
+                    robots_url.as_str(),
+                    response.http_status,
+                ).yellow());
+                (RobotsTxt::disallow_all(), Some(retry_at))
+            }
+            Err(error) => {
+                eprintln!("{}", format!(
+                    "\t ⚠️ robots.txt at {:?} is unreachable: {error} — treating origin as disallowed",
+                    robots_url.as_str(),
+                ).yellow());
+                (RobotsTxt::disallow_all(), Some(retry_at))
+            }
+        };
+        let robots = Arc::new(robots);
+        self.entries
+            .lock()
+            .unwrap()
+            .insert(Self::origin_key(url), CachedRobotsTxt { robots: robots.clone(), expires });
+        Some(robots)
+    }
+    /// URLs of origins that were not loaded yet are allowed; they get checked
+    /// again once their robots.txt is known.
+    pub fn is_allowed(&self, url: &Url) -> bool {
+        if !self.settings.enabled {
+            return true
+        }
+        self.get(url)
+            .map(|robots| robots.is_allowed(&self.settings.user_agent, url))
+            .unwrap_or(true)
+    }
+    pub fn crawl_delay(&self, url: &Url) -> Option<Duration> {
+        self.get(url)?.crawl_delay(&self.settings.user_agent)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROBOTS_TXT: &str = "\
+# Example robots.txt
+User-agent: *
+Disallow: /private/
+Allow: /private/press/
+Disallow: /*.pdf$
+Disallow: /search?*sort=
+Crawl-delay: 2
+
+User-agent: Web-Crawler
+User-agent: other-bot
+Disallow: /drafts
+Allow: /drafts/public
+Crawl-delay: 0.5
+
+User-agent: web
+Disallow: /
+
+Sitemap: https://example.com/sitemap.xml
+Sitemap: not a url
+";
+
+    fn allowed(robots: &RobotsTxt, user_agent: &str, url: &str) -> bool {
+        robots.is_allowed(user_agent, &Url::parse(url).unwrap())
+    }
+
+    #[test]
+    fn user_agents_match_the_whole_product_token() {
+        let robots = RobotsTxt::parse(ROBOTS_TXT);
+        // Named group, case-insensitively and from a full User-Agent header.
+        assert!(!allowed(&robots, "web-crawler", "https://example.com/drafts/x"));
+        assert!(!allowed(&robots, "WEB-CRAWLER/1.2 (+https://example.com/bot)", "https://example.com/drafts/x"));
+        assert!(allowed(&robots, "web-crawler", "https://example.com/private/x"));
+        // `web` is a prefix of `web-crawler`, not its token.
+        assert!(allowed(&robots, "web-crawler", "https://example.com/"));
+        assert!(!allowed(&robots, "web", "https://example.com/"));
+        // Neither is `web-crawler` of `web-crawler-two`; it gets `*`.
+        assert!(!allowed(&robots, "web-crawler-two", "https://example.com/private/x"));
+        assert!(allowed(&robots, "web-crawler-two", "https://example.com/drafts/x"));
+    }
+
+    #[test]
+    fn longest_match_wins_and_allow_wins_ties() {
+        let robots = RobotsTxt::parse(ROBOTS_TXT);
+        assert!(!allowed(&robots, "any-bot", "https://example.com/private/report"));
+        assert!(allowed(&robots, "any-bot", "https://example.com/private/press/release"));
+        assert!(!allowed(&robots, "web-crawler", "https://example.com/drafts"));
+        assert!(allowed(&robots, "web-crawler", "https://example.com/drafts/public/1"));
+        let tie = RobotsTxt::parse("User-agent: *\nDisallow: /page\nAllow: /page\n");
+        assert!(allowed(&tie, "any-bot", "https://example.com/page"));
+        let tie = RobotsTxt::parse("User-agent: *\nAllow: /page\nDisallow: /page\n");
+        assert!(allowed(&tie, "any-bot", "https://example.com/page"));
+    }
+
+    #[test]
+    fn wildcards_and_end_anchors() {
+        let robots = RobotsTxt::parse(ROBOTS_TXT);
+        assert!(!allowed(&robots, "any-bot", "https://example.com/files/report.pdf"));
+        assert!(allowed(&robots, "any-bot", "https://example.com/files/report.pdf?download=1"));
+        assert!(allowed(&robots, "any-bot", "https://example.com/files/report.pdfx"));
+        assert!(!allowed(&robots, "any-bot", "https://example.com/search?q=a&sort=date"));
+        assert!(allowed(&robots, "any-bot", "https://example.com/search?q=a"));
+
+        assert!(pattern_matches("/fish", "/fish.html"));
+        assert!(pattern_matches("/fish*", "/fish"));
+        assert!(!pattern_matches("/fish", "/Fish"));
+        assert!(pattern_matches("/*.php", "/a/b.php?x"));
+        assert!(pattern_matches("/*.php$", "/a/b.php"));
+        assert!(!pattern_matches("/*.php$", "/a/b.php?x"));
+        assert!(pattern_matches("/fish$", "/fish"));
+        assert!(!pattern_matches("/fish$", "/fish/"));
+        assert!(pattern_matches("/a*b*c", "/aXbYc"));
+        assert!(!pattern_matches("/a*b*c", "/aXcYb"));
+        assert!(pattern_matches("*", "/anything"));
+    }
+
+    #[test]
+    fn crawl_delay_and_sitemaps() {
+        let robots = RobotsTxt::parse(ROBOTS_TXT);
+        assert_eq!(robots.crawl_delay("other-bot"), Some(Duration::from_millis(500)));
+        assert_eq!(robots.crawl_delay("any-bot"), Some(Duration::from_secs(2)));
+        assert_eq!(robots.sitemaps(), [Url::parse("https://example.com/sitemap.xml").unwrap()]);
+    }
+
+    #[test]
+    fn empty_and_missing_rules() {
+        let robots = RobotsTxt::parse("User-agent: *\nDisallow:\n");
+        assert!(allowed(&robots, "any-bot", "https://example.com/"));
+        // Rules before any `User-agent` line belong to no group.
+        let robots = RobotsTxt::parse("Disallow: /\n");
+        assert!(allowed(&robots, "any-bot", "https://example.com/"));
+        assert!(allowed(&RobotsTxt::allow_all(), "any-bot", "https://example.com/"));
+        assert!(!allowed(&RobotsTxt::disallow_all(), "any-bot", "https://example.com/"));
+    }
+
+    #[tokio::test]
+    async fn unreachable_robots_txt_is_fetched_again_later() {
+        // A port nothing listens on.
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let url = Url::parse(&format!("http://127.0.0.1:{port}/page")).unwrap();
+        let settings = |unreachable_retry| RobotsSettings { unreachable_retry, ..RobotsSettings::default() };
+
+        let cache = RobotsCache::new(settings(Duration::from_secs(3600)), HttpFetcher::new("web-crawler").unwrap());
+        let robots = cache.load(&url).await.unwrap();
+        assert!(!robots.is_allowed("web-crawler", &url));
+        assert!(!cache.is_allowed(&url));
+
+        let cache = RobotsCache::new(settings(Duration::ZERO), HttpFetcher::new("web-crawler").unwrap());
+        assert!(cache.load(&url).await.is_some());
+        assert!(cache.get(&url).is_none());
+    }
+}
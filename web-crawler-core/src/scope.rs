@@ -0,0 +1,189 @@
+use once_cell::sync::Lazy;
+use publicsuffix::{List, Psl};
+
+/// Snapshot of https://publicsuffix.org/list/public_suffix_list.dat; refresh
+/// it by replacing the file.
+static PUBLIC_SUFFIX_LIST: Lazy<List> = Lazy::new(|| {
+    include_str!("../data/public_suffix_list.dat")
+        .parse()
+        .expect("bundled public suffix list should parse")
+});
+
+/// How far beyond the whitelisted hosts a crawl may wander.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ScopeMode {
+    /// Only the whitelisted hosts themselves.
+    #[default]
+    ExactHost,
+    /// Whitelisted hosts and any of their subdomains:
+    /// `example.com` admits `blog.example.com`, but not the other way round.
+    IncludeSubdomains,
+    /// Every host sharing a registrable domain (public suffix plus one label)
+    /// with a whitelisted host: `www.example.co.uk` admits
+    /// `shop.example.co.uk` and `example.co.uk`.
+    RegistrableDomain,
+}
+
+/// The registrable domain of `host`, e.g. `example.co.uk` for
+/// `www.example.co.uk`. `None` for IP addresses and bare public suffixes.
+pub fn registrable_domain(host: &str) -> Option<String> {
+    let host = host.trim_end_matches('.').to_ascii_lowercase();
+    let unbracketed = host.trim_start_matches('[').trim_end_matches(']');
+    if unbracketed.parse::<std::net::IpAddr>().is_ok() {
+        return None
+    }
+    let domain = PUBLIC_SUFFIX_LIST.domain(host.as_bytes())?;
+    std::str::from_utf8(domain.as_bytes()).ok().map(String::from)
+}
+
+/// Whether `host` falls under `allowed` for the given mode. Both must already
+/// be lowercase.
+pub fn host_in_scope(mode: ScopeMode, host: &str, allowed: &str) -> bool {
+    match mode {
+        ScopeMode::ExactHost => host == allowed,
+        ScopeMode::IncludeSubdomains => {
+            host == allowed || host.ends_with(&format!(".{allowed}"))
+        }
+        ScopeMode::RegistrableDomain => {
+            if host == allowed {
+                return true
+            }
+            match (registrable_domain(host), registrable_domain(allowed)) {
+                (Some(host), Some(allowed)) => host == allowed,
+                _ => false,
+            }
+        }
+    }
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// ORIGINS
+// ————————————————————————————————————————————————————————————————————————————
+
+/// Whitelist entry for the origin of `url`: the lowercase host followed by
+/// its effective port, so a seed on `https://example.com/` admits
+/// `example.com:443` only. IPv6 hosts keep their brackets. `None` for URLs
+/// without a host, such as `data:` URLs.
+pub fn whitelist_entry_for_url(url: &url::Url) -> Option<String> {
+    let host = url.host_str()?.to_ascii_lowercase();
+    match url.port_or_known_default() {
+        Some(port) => Some(format!("{host}:{port}")),
+        None => Some(host),
+    }
+}
+
+/// Lowercase a whitelist entry and bring IP literals into the form
+/// `Url::host_str` produces, so `::1` and `[0:0::1]` both become `[::1]`.
+pub fn normalize_whitelist_entry(entry: &str) -> String {
+    let entry = entry.trim().to_ascii_lowercase();
+    if let Ok(address) = entry.parse::<std::net::Ipv6Addr>() {
+        return format!("[{address}]")
+    }
+    let (host, port) = split_host_port(&entry);
+    let host = host
+        .strip_prefix('[')
+        .and_then(|x| x.strip_suffix(']'))
+        .and_then(|x| x.parse::<std::net::Ipv6Addr>().ok())
+        .map(|address| format!("[{address}]"))
+        .unwrap_or_else(|| host.to_string());
+    match port {
+        Some(port) => format!("{host}:{port}"),
+        None => host,
+    }
+}
+
+/// Split a whitelist entry such as `example.com`, `127.0.0.1:8080` or
+/// `[::1]:3000` into its host and optional port.
+pub fn split_host_port(entry: &str) -> (&str, Option<u16>) {
+    if entry.starts_with('[') {
+        return match entry.find(']') {
+            Some(end) => {
+                let port = entry[end + 1..]
+                    .strip_prefix(':')
+                    .and_then(|port| port.parse().ok());
+                (&entry[..=end], port)
+            }
+            None => (entry, None),
+        }
+    }
+    match entry.rsplit_once(':') {
+        // A second colon means an unbracketed IPv6 address, not a port.
+        Some((host, port)) if !host.contains(':') => match port.parse() {
+            Ok(port) => (host, Some(port)),
+            Err(_) => (entry, None),
+        },
+        _ => (entry, None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seed_entries_carry_the_effective_port() {
+        let entry = |url: &str| whitelist_entry_for_url(&url::Url::parse(url).unwrap());
+        assert_eq!(entry("https://Example.com/").as_deref(), Some("example.com:443"));
+        assert_eq!(entry("http://example.com/").as_deref(), Some("example.com:80"));
+        assert_eq!(entry("http://127.0.0.1:8080/").as_deref(), Some("127.0.0.1:8080"));
+        assert_eq!(entry("http://[::1]/").as_deref(), Some("[::1]:80"));
+        assert_eq!(entry("data:text/plain,hello"), None);
+    }
+
+    #[test]
+    fn exact_host_admits_only_the_host_itself() {
+        assert!(host_in_scope(ScopeMode::ExactHost, "example.com", "example.com"));
+        assert!(!host_in_scope(ScopeMode::ExactHost, "blog.example.com", "example.com"));
+        assert!(!host_in_scope(ScopeMode::ExactHost, "example.com", "www.example.com"));
+    }
+
+    #[test]
+    fn subdomains_widen_downwards_only() {
+        let mode = ScopeMode::IncludeSubdomains;
+        assert!(host_in_scope(mode, "example.com", "example.com"));
+        assert!(host_in_scope(mode, "a.b.example.com", "example.com"));
+        assert!(!host_in_scope(mode, "example.com", "blog.example.com"));
+        assert!(!host_in_scope(mode, "notexample.com", "example.com"));
+    }
+
+    #[test]
+    fn registrable_domains_follow_the_public_suffix_list() {
+        assert_eq!(registrable_domain("www.example.co.uk").as_deref(), Some("example.co.uk"));
+        assert_eq!(registrable_domain("co.uk"), None);
+        assert_eq!(registrable_domain("127.0.0.1"), None);
+        assert_eq!(registrable_domain("[::1]"), None);
+        let mode = ScopeMode::RegistrableDomain;
+        assert!(host_in_scope(mode, "shop.example.co.uk", "www.example.co.uk"));
+        assert!(host_in_scope(mode, "example.co.uk", "www.example.co.uk"));
+        // Sharing only the public suffix is not enough.
+        assert!(!host_in_scope(mode, "other.co.uk", "www.example.co.uk"));
+    }
+
+    #[test]
+    fn private_suffixes_separate_their_tenants() {
+        assert_eq!(registrable_domain("docs.alice.github.io").as_deref(), Some("alice.github.io"));
+        let mode = ScopeMode::RegistrableDomain;
+        assert!(host_in_scope(mode, "docs.alice.github.io", "alice.github.io"));
+        assert!(!host_in_scope(mode, "bob.github.io", "alice.github.io"));
+    }
+
+    #[test]
+    fn whitelist_entries_are_normalized() {
+        assert_eq!(normalize_whitelist_entry(" Example.COM "), "example.com");
+        assert_eq!(normalize_whitelist_entry("example.com:8080"), "example.com:8080");
+        assert_eq!(normalize_whitelist_entry("::1"), "[::1]");
+        assert_eq!(normalize_whitelist_entry("[0:0::1]"), "[::1]");
+        assert_eq!(normalize_whitelist_entry("[0:0::1]:3000"), "[::1]:3000");
+        assert_eq!(normalize_whitelist_entry("127.0.0.1:8080"), "127.0.0.1:8080");
+    }
+
+    #[test]
+    fn entries_split_into_host_and_port() {
+        assert_eq!(split_host_port("example.com"), ("example.com", None));
+        assert_eq!(split_host_port("example.com:443"), ("example.com", Some(443)));
+        assert_eq!(split_host_port("[::1]:3000"), ("[::1]", Some(3000)));
+        assert_eq!(split_host_port("[::1]"), ("[::1]", None));
+        assert_eq!(split_host_port("::1"), ("::1", None));
+        assert_eq!(split_host_port("example.com:http"), ("example.com:http", None));
+    }
+}
@@ -3,11 +3,30 @@ use std::{collections::BTreeSet, path::PathBuf, str::FromStr};
 use indexmap::IndexSet;
 use url::Url;
 
+use crate::browser::BrowserSettings;
+use crate::budget::CrawlBudget;
+use crate::canonicalize::UrlNormalizer;
+use crate::dedup::DedupSettings;
+use crate::capture::CaptureSettings;
+use crate::warc::WarcSettings;
+use crate::frontier::FrontierOrder;
+use crate::politeness::PolitenessSettings;
+use crate::recrawl::RecrawlSettings;
+use crate::resources::ResourceSettings;
+use crate::retry::{CrawlMode, RetryPolicy};
+use crate::robots::RobotsSettings;
+use crate::scope::ScopeMode;
+use crate::sitemap::SitemapSettings;
+
 #[derive(Debug, Clone)]
 pub struct UrlVisitorSettings {
     pub domain_whitelist: BTreeSet<String>,
+    /// How `domain_whitelist` entries are matched against URL hosts.
+    pub scope: ScopeMode,
     pub protocol_whitelist: BTreeSet<String>,
     pub protocol_blacklist: BTreeSet<String>,
+    /// Ordered include/exclude rules; see [`UrlRule`].
+    pub url_rules: Vec<UrlRule>,
 }
 
 impl UrlVisitorSettings {
@@ -16,24 +35,30 @@ impl UrlVisitorSettings {
             domain_whitelist: domain_whitelist
                 .into_iter()
                 .map(|x| x.into())
-                .map(|x| x.to_ascii_lowercase())
+                .map(|x| crate::scope::normalize_whitelist_entry(&x))
                 .collect(),
+            scope: Default::default(),
             protocol_whitelist: Default::default(),
             protocol_blacklist: Default::default(),
+            url_rules: Default::default(),
         };
         this.normalize().union(Self::standard_defaults())
     }
+    /// Whitelist the origin of every seed; see
+    /// [`crate::scope::whitelist_entry_for_url`]. Seeds without a host are
+    /// ignored.
     pub fn from_seed_urls_with_defaults(urls: impl AsRef<[Url]>) -> Self {
-        let domains = urls
+        let origins = urls
             .as_ref()
-            .into_iter()
-            .map(|x| x.domain().unwrap())
+            .iter()
+            .filter_map(crate::scope::whitelist_entry_for_url)
             .collect::<Vec<_>>();
-        Self::from_domain_whitelist_with_defaults(domains)
+        Self::from_domain_whitelist_with_defaults(origins)
     }
     pub fn standard_defaults() -> Self {
         Self {
             domain_whitelist: Default::default(),
+            scope: Default::default(),
             // protocol_whitelist: Default::default(),
             protocol_whitelist: BTreeSet::from_iter(vec![
                 String::from("http"),
@@ -42,12 +67,14 @@ impl UrlVisitorSettings {
             protocol_blacklist: BTreeSet::from_iter(vec![
                 String::from("tel"),
             ]),
+            url_rules: Default::default(),
         }
     }
     pub fn merge_mut(&mut self, other: Self) {
         self.domain_whitelist.extend(other.domain_whitelist);
         self.protocol_whitelist.extend(other.protocol_whitelist);
         self.protocol_blacklist.extend(other.protocol_blacklist);
+        self.url_rules.extend(other.url_rules);
     }
     pub fn union(mut self, other: Self) -> Self {
         self.merge_mut(other);
@@ -57,8 +84,9 @@ impl UrlVisitorSettings {
         Self {
             domain_whitelist: self.domain_whitelist
                 .into_iter()
-                .map(|x| x.to_ascii_lowercase())
+                .map(|x| crate::scope::normalize_whitelist_entry(&x))
                 .collect(),
+            scope: self.scope,
             protocol_whitelist: self.protocol_whitelist
                 .into_iter()
                 .map(|x| x.to_ascii_lowercase())
@@ -67,15 +95,25 @@ impl UrlVisitorSettings {
                 .into_iter()
                 .map(|x| x.to_ascii_lowercase())
                 .collect(),
+            url_rules: self.url_rules,
         }
     }
     pub fn with_whitelisted_domain(mut self, entry: impl AsRef<str>) -> Self {
-        match Url::from_str(entry.as_ref()) {
-            Ok(url) => {
-                self.domain_whitelist.insert(url.domain().unwrap().to_ascii_lowercase().to_string());
+        // `localhost:8080` parses as a URL with scheme `localhost` and no
+        // host, so only trust the parse when it found one. A configured URL
+        // without an explicit port stays a bare host, matching any port.
+        let from_url = Url::from_str(entry.as_ref())
+            .ok()
+            .and_then(|url| match url.port() {
+                Some(_) => crate::scope::whitelist_entry_for_url(&url),
+                None => url.host_str().map(crate::scope::normalize_whitelist_entry),
+            });
+        match from_url {
+            Some(origin) => {
+                self.domain_whitelist.insert(origin);
             }
-            Err(_) => {
-                self.domain_whitelist.insert(entry.as_ref().to_ascii_lowercase().to_string());
+            None => {
+                self.domain_whitelist.insert(crate::scope::normalize_whitelist_entry(entry.as_ref()));
             }
         }
         self
@@ -88,14 +126,19 @@ impl UrlVisitorSettings {
         self.protocol_blacklist.insert(entry.into());
         self
     }
+    pub fn with_scope(mut self, scope: ScopeMode) -> Self {
+        self.scope = scope;
+        self
+    }
+    pub fn with_url_rule(mut self, rule: UrlRule) -> Self {
+        self.url_rules.push(rule);
+        self
+    }
     pub fn should_visit(&self, url: &Url) -> Result<(), FailedFilterReason> {
         let use_scheme_whitelist = self.protocol_whitelist.is_empty();
         let is_whitelisted_scheme = self.protocol_whitelist.contains(&url.scheme().to_ascii_lowercase());
         let is_blacklisted_scheme = self.protocol_blacklist.contains(&url.scheme().to_ascii_lowercase());
-        let is_whitelisted_domain = url.domain().map(|domain| {
-            self.domain_whitelist.contains(&domain.to_ascii_lowercase())
-        });
-        if !is_whitelisted_domain.unwrap_or(false) {
+        if !self.is_whitelisted_origin(url) {
             return Err(FailedFilterReason::FailedWhitelistedDomainCheck)
         }
         if use_scheme_whitelist && !is_whitelisted_scheme {
@@ -104,8 +147,51 @@ impl UrlVisitorSettings {
         if is_blacklisted_scheme {
             return Err(FailedFilterReason::IsBlacklistedScheme)
         }
+        self.check_url_rules(url)
+    }
+    /// The first rule matching `url` decides. When no rule matches, the URL
+    /// is accepted unless there are include rules, in which case it must have
+    /// matched one of them.
+    fn check_url_rules(&self, url: &Url) -> Result<(), FailedFilterReason> {
+        for rule in self.url_rules.iter() {
+            if !rule.is_match(url) {
+                continue
+            }
+            return match rule.action {
+                UrlRuleAction::Include => Ok(()),
+                UrlRuleAction::Exclude => Err(FailedFilterReason::ExcludedByRule(rule.to_string())),
+            }
+        }
+        let has_include_rules = self.url_rules
+            .iter()
+            .any(|rule| rule.action == UrlRuleAction::Include);
+        if has_include_rules {
+            return Err(FailedFilterReason::NotMatchedByIncludeRule)
+        }
         Ok(())
     }
+    /// Whitelist entries are either a bare host, matching any port, or
+    /// `host:port`, matching only that port (default ports included, so
+    /// `example.com:443` matches `https://example.com/`). Scope modes only
+    /// widen domain names; IP addresses must match exactly.
+    pub fn is_whitelisted_origin(&self, url: &Url) -> bool {
+        let Some(host) = url.host_str() else {
+            return false
+        };
+        let host = host.to_ascii_lowercase();
+        let port = url.port_or_known_default();
+        let scope = match url.host() {
+            Some(url::Host::Domain(_)) => self.scope,
+            _ => ScopeMode::ExactHost,
+        };
+        self.domain_whitelist.iter().any(|entry| {
+            let (allowed_host, allowed_port) = crate::scope::split_host_port(entry);
+            if allowed_port.is_some() && allowed_port != port {
+                return false
+            }
+            crate::scope::host_in_scope(scope, &host, allowed_host)
+        })
+    }
     /// Check if a domain is a subdomain of any whitelisted domain
     pub fn is_subdomain_of_whitelisted(&self, url: &Url) -> bool {
         let url_domain = match url.domain() {
@@ -113,17 +199,146 @@ impl UrlVisitorSettings {
             None => return false,
         };
         self.domain_whitelist.iter().any(|allowed| {
-            let allowed = allowed.to_ascii_lowercase();
-            url_domain == allowed || url_domain.ends_with(&format!(".{}", allowed))
+            let (allowed_host, _) = crate::scope::split_host_port(allowed);
+            crate::scope::host_in_scope(ScopeMode::IncludeSubdomains, &url_domain, allowed_host)
         })
     }
 }
 
+// ————————————————————————————————————————————————————————————————————————————
+// URL RULES
+// ————————————————————————————————————————————————————————————————————————————
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UrlRuleAction {
+    Include,
+    Exclude,
+}
+
+/// The part of the URL a rule is matched against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UrlRuleTarget {
+    /// `/blog/post`
+    Path,
+    /// `page=2&sort=asc`, without the leading `?`; empty when there is none.
+    Query,
+    /// `/blog/post?page=2`
+    PathAndQuery,
+}
+
+#[derive(Debug, Clone)]
+pub enum UrlPattern {
+    /// Glob syntax as understood by `wax`, e.g. `/blog/**`.
+    Glob(wax::Glob<'static>),
+    /// Searched anywhere in the target unless anchored with `^`/`$`.
+    Regex(regex::Regex),
+}
+
+impl UrlPattern {
+    pub fn glob(source: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let glob = wax::Glob::new(source)
+            .map_err(|error| format!("invalid glob {source:?}: {error}"))?;
+        Ok(Self::Glob(glob.into_owned()))
+    }
+    pub fn regex(source: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self::Regex(regex::Regex::new(source)?))
+    }
+    pub fn is_match(&self, target: &str) -> bool {
+        use wax::Pattern;
+        match self {
+            Self::Glob(glob) => glob.is_match(target),
+            Self::Regex(regex) => regex.is_match(target),
+        }
+    }
+}
+
+impl std::fmt::Display for UrlPattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Glob(glob) => write!(f, "glob {:?}", glob.to_string()),
+            Self::Regex(regex) => write!(f, "regex {:?}", regex.as_str()),
+        }
+    }
+}
+
+/// One entry of the ordered include/exclude list in [`UrlVisitorSettings`].
+#[derive(Debug, Clone)]
+pub struct UrlRule {
+    pub action: UrlRuleAction,
+    pub target: UrlRuleTarget,
+    pub pattern: UrlPattern,
+}
+
+impl UrlRule {
+    pub fn include(target: UrlRuleTarget, pattern: UrlPattern) -> Self {
+        Self { action: UrlRuleAction::Include, target, pattern }
+    }
+    pub fn exclude(target: UrlRuleTarget, pattern: UrlPattern) -> Self {
+        Self { action: UrlRuleAction::Exclude, target, pattern }
+    }
+    pub fn is_match(&self, url: &Url) -> bool {
+        let target = match self.target {
+            UrlRuleTarget::Path => url.path().to_string(),
+            UrlRuleTarget::Query => url.query().unwrap_or("").to_string(),
+            UrlRuleTarget::PathAndQuery => match url.query() {
+                Some(query) => format!("{}?{}", url.path(), query),
+                None => url.path().to_string(),
+            },
+        };
+        self.pattern.is_match(&target)
+    }
+}
+
+impl std::fmt::Display for UrlRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let action = match self.action {
+            UrlRuleAction::Include => "include",
+            UrlRuleAction::Exclude => "exclude",
+        };
+        let target = match self.target {
+            UrlRuleTarget::Path => "path",
+            UrlRuleTarget::Query => "query",
+            UrlRuleTarget::PathAndQuery => "path-and-query",
+        };
+        write!(f, "{action} {target} {}", self.pattern)
+    }
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// CRAWLER
+// ————————————————————————————————————————————————————————————————————————————
+
 #[derive(Debug, Clone)]
 pub struct CrawlerSettings {
     pub seed_urls: IndexSet<Url>,
     pub url_visitor_settings: UrlVisitorSettings,
     pub project_directory: PathBuf,
+    /// Number of tabs (workers) crawling the shared frontier at once.
+    pub max_concurrent_tabs: usize,
+    pub politeness: PolitenessSettings,
+    pub robots: RobotsSettings,
+    pub sitemaps: SitemapSettings,
+    pub budget: CrawlBudget,
+    /// Rules mapping every URL to the canonical form used for deduplication,
+    /// filtering and snapshot paths.
+    pub url_normalizer: UrlNormalizer,
+    pub recrawl: RecrawlSettings,
+    pub resources: ResourceSettings,
+    pub dedup: DedupSettings,
+    /// Screenshots and PDF renders stored with each snapshot.
+    pub capture: CaptureSettings,
+    /// Whether snapshots go to the directory layout, WARC files or both.
+    pub warc: WarcSettings,
+    /// Which queued URL is visited next.
+    pub frontier_order: FrontierOrder,
+    pub retry: RetryPolicy,
+    /// Browser launch options, navigation timeouts and the wait strategy.
+    pub browser: BrowserSettings,
+    pub mode: CrawlMode,
+}
+
+impl CrawlerSettings {
+    pub const DEFAULT_MAX_CONCURRENT_TABS: usize = 4;
 }
 
 // #[derive(Debug, Clone)]
@@ -136,6 +351,8 @@ pub struct CrawlerSettings {
 pub enum SkipUrlReason {
     AlreadyVisited,
     FailedFilter(FailedFilterReason),
+    ExceedsMaxDepth,
+    HostBudgetExhausted,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -143,4 +360,93 @@ pub enum FailedFilterReason {
     FailedWhitelistedSchemeCheck,
     FailedWhitelistedDomainCheck,
     IsBlacklistedScheme,
+    DisallowedByRobotsTxt,
+    /// An exclude rule matched; holds the rule's description.
+    ExcludedByRule(String),
+    /// Include rules exist but none of them matched.
+    NotMatchedByIncludeRule,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(rules: Vec<UrlRule>) -> UrlVisitorSettings {
+        rules.into_iter().fold(
+            UrlVisitorSettings::from_domain_whitelist_with_defaults(["example.com"]),
+            UrlVisitorSettings::with_url_rule,
+        )
+    }
+
+    fn check(settings: &UrlVisitorSettings, url: &str) -> Result<(), FailedFilterReason> {
+        settings.should_visit(&Url::parse(url).unwrap())
+    }
+
+    #[test]
+    fn without_rules_every_whitelisted_url_passes() {
+        let no_rules = settings(Vec::new());
+        assert_eq!(check(&no_rules, "https://example.com/anything?at=all"), Ok(()));
+        assert_eq!(
+            check(&no_rules, "https://other.com/"),
+            Err(FailedFilterReason::FailedWhitelistedDomainCheck),
+        );
+    }
+
+    #[test]
+    fn the_first_matching_rule_wins() {
+        let include_first = settings(vec![
+            UrlRule::include(UrlRuleTarget::Path, UrlPattern::glob("/blog/drafts/**").unwrap()),
+            UrlRule::exclude(UrlRuleTarget::Path, UrlPattern::glob("/blog/**").unwrap()),
+        ]);
+        assert_eq!(check(&include_first, "https://example.com/blog/drafts/post"), Ok(()));
+        assert_eq!(
+            check(&include_first, "https://example.com/blog/post"),
+            Err(FailedFilterReason::ExcludedByRule(String::from("exclude path glob \"/blog/**\""))),
+        );
+        let exclude_first = settings(vec![
+            UrlRule::exclude(UrlRuleTarget::Path, UrlPattern::glob("/blog/**").unwrap()),
+            UrlRule::include(UrlRuleTarget::Path, UrlPattern::glob("/blog/drafts/**").unwrap()),
+        ]);
+        assert!(matches!(
+            check(&exclude_first, "https://example.com/blog/drafts/post"),
+            Err(FailedFilterReason::ExcludedByRule(_)),
+        ));
+    }
+
+    #[test]
+    fn include_rules_reject_what_they_do_not_match() {
+        let with_include = settings(vec![
+            UrlRule::exclude(UrlRuleTarget::Query, UrlPattern::regex("(^|&)sort=").unwrap()),
+            UrlRule::include(UrlRuleTarget::Path, UrlPattern::glob("/docs/**").unwrap()),
+        ]);
+        assert_eq!(check(&with_include, "https://example.com/docs/intro"), Ok(()));
+        assert_eq!(
+            check(&with_include, "https://example.com/about"),
+            Err(FailedFilterReason::NotMatchedByIncludeRule),
+        );
+        // Only exclude rules: whatever they don't match passes.
+        let exclude_only = settings(vec![
+            UrlRule::exclude(UrlRuleTarget::Path, UrlPattern::glob("/private/**").unwrap()),
+        ]);
+        assert_eq!(check(&exclude_only, "https://example.com/about"), Ok(()));
+    }
+
+    #[test]
+    fn globs_match_the_whole_target_and_regexes_anywhere() {
+        let glob = UrlRule::include(UrlRuleTarget::Path, UrlPattern::glob("/blog/*").unwrap());
+        let regex = UrlRule::include(UrlRuleTarget::Path, UrlPattern::regex("blog").unwrap());
+        let anchored = UrlRule::include(UrlRuleTarget::Path, UrlPattern::regex("^/blog$").unwrap());
+        let url = |path: &str| Url::parse(&format!("https://example.com{path}")).unwrap();
+        assert!(glob.is_match(&url("/blog/post")));
+        assert!(!glob.is_match(&url("/blog/2024/post")));
+        assert!(!glob.is_match(&url("/en/blog/post")));
+        assert!(regex.is_match(&url("/en/blog/post")));
+        assert!(anchored.is_match(&url("/blog")));
+        assert!(!anchored.is_match(&url("/blog/post")));
+        let path_and_query = UrlRule::include(UrlRuleTarget::PathAndQuery, UrlPattern::glob("/search?q=*").unwrap());
+        assert!(path_and_query.is_match(&url("/search?q=rust")));
+        assert!(!path_and_query.is_match(&url("/search")));
+        assert!(UrlPattern::glob("/blog/[").is_err());
+        assert!(UrlPattern::regex("(").is_err());
+    }
 }
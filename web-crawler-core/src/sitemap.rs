@@ -0,0 +1,421 @@
+use std::collections::{HashSet, VecDeque};
+use std::io::Read;
+use colored::Colorize;
+use indexmap::IndexMap;
+use url::Url;
+
+use crate::http::HttpFetcher;
+
+// ————————————————————————————————————————————————————————————————————————————
+// SETTINGS
+// ————————————————————————————————————————————————————————————————————————————
+
+#[derive(Debug, Clone)]
+pub struct SitemapSettings {
+    /// Whether seeds are extended with URLs listed in sitemaps.
+    pub enabled: bool,
+    /// Upper bound on the number of sitemap files fetched per crawl,
+    /// including nested sitemap indexes.
+    pub max_sitemaps: usize,
+}
+
+impl Default for SitemapSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_sitemaps: 500,
+        }
+    }
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// DOCUMENT
+// ————————————————————————————————————————————————————————————————————————————
+
+/// A parsed sitemap file (https://www.sitemaps.org/protocol.html).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SitemapDocument {
+    /// `<sitemapindex>` — points to further sitemaps.
+    Index(Vec<Url>),
+    /// `<urlset>` or a plain-text sitemap — lists pages.
+    UrlSet(Vec<Url>),
+}
+
+impl SitemapDocument {
+    /// Largest sitemap accepted, compressed or not, as set by the protocol.
+    pub const MAX_SIZE: usize = 50 * 1024 * 1024;
+    /// Parse a sitemap body, transparently decompressing gzip.
+    ///
+    /// Bodies, and gzip bodies once decompressed, larger than
+    /// [`Self::MAX_SIZE`] are rejected.
+    pub fn parse(body: &[u8]) -> Result<Self, std::io::Error> {
+        let too_large = || std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("larger than {} bytes", Self::MAX_SIZE),
+        );
+        let mut bytes = Vec::new();
+        body.take(Self::MAX_SIZE as u64 + 1).read_to_end(&mut bytes)?;
+        if bytes.len() > Self::MAX_SIZE {
+            return Err(too_large())
+        }
+        if bytes.starts_with(&[0x1f, 0x8b]) {
+            let decoder = flate2::read::GzDecoder::new(body);
+            bytes.clear();
+            decoder.take(Self::MAX_SIZE as u64 + 1).read_to_end(&mut bytes)?;
+            if bytes.len() > Self::MAX_SIZE {
+                return Err(too_large())
+            }
+        }
+        Ok(Self::parse_str(&String::from_utf8_lossy(&bytes)))
+    }
+    pub fn parse_str(source: &str) -> Self {
+        let trimmed = source.trim_start_matches('\u{feff}').trim_start();
+        if !trimmed.starts_with('<') {
+            let urls = trimmed
+                .lines()
+                .filter_map(|line| Url::parse(line.trim()).ok())
+                .collect();
+            return Self::UrlSet(urls)
+        }
+        let urls = extract_elements(source, "loc")
+            .into_iter()
+            .filter_map(|loc| Url::parse(&loc).ok())
+            .collect();
+        let root = tags(source).find(|tag| !tag.is_end).map(|tag| tag.local_name);
+        match root {
+            Some("sitemapindex") => Self::Index(urls),
+            _ => Self::UrlSet(urls),
+        }
+    }
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// XML
+// ————————————————————————————————————————————————————————————————————————————
+//
+// Sitemaps are flat enough that a tag scanner does: elements are matched by
+// local name, so `<loc>`, `<sm:loc >` and `<loc xml:lang="en">` are all the
+// same element.
+
+/// A start or end tag, spanning `start..end` of the source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Tag<'a> {
+    /// The name without namespace prefix.
+    local_name: &'a str,
+    is_end: bool,
+    is_empty: bool,
+    start: usize,
+    end: usize,
+}
+
+/// Every start and end tag of `source`, in order. Comments, CDATA sections,
+/// processing instructions and declarations are skipped.
+fn tags(source: &str) -> impl Iterator<Item=Tag<'_>> {
+    let mut position = 0;
+    std::iter::from_fn(move || {
+        loop {
+            let start = position + source[position..].find('<')?;
+            let rest = &source[start..];
+            let skip_to = |terminator: &str| rest.find(terminator).map(|end| start + end + terminator.len());
+            if rest.starts_with("<!--") {
+                position = skip_to("-->")?;
+                continue
+            }
+            if rest.starts_with("<![CDATA[") {
+                position = skip_to("]]>")?;
+                continue
+            }
+            if rest.starts_with("<?") {
+                position = skip_to("?>")?;
+                continue
+            }
+            if rest.starts_with("<!") {
+                position = skip_to(">")?;
+                continue
+            }
+            // The closing `>`, outside of quoted attribute values.
+            let mut quote = None;
+            let length = rest.char_indices().skip(1).find_map(|(index, c)| {
+                match (quote, c) {
+                    (None, '"' | '\'') => quote = Some(c),
+                    (Some(open), c) if c == open => quote = None,
+                    (None, '>') => return Some(index + 1),
+                    _ => (),
+                }
+                None
+            })?;
+            position = start + length;
+            let inner = &rest[1..length - 1];
+            let (is_end, inner) = match inner.strip_prefix('/') {
+                Some(inner) => (true, inner),
+                None => (false, inner),
+            };
+            let is_empty = inner.ends_with('/');
+            let name = inner
+                .trim_start()
+                .split(|c: char| c.is_whitespace() || c == '/')
+                .next()
+                .unwrap_or("");
+            if name.is_empty() {
+                continue
+            }
+            return Some(Tag {
+                local_name: name.rsplit(':').next().unwrap_or(name),
+                is_end,
+                is_empty,
+                start,
+                end: position,
+            })
+        }
+    })
+}
+
+/// Text content of every element named `local_name`, with CDATA sections
+/// and entities resolved.
+fn extract_elements(source: &str, local_name: &str) -> Vec<String> {
+    let mut results = Vec::new();
+    let mut content_start = None;
+    for tag in tags(source).filter(|tag| tag.local_name == local_name) {
+        match (tag.is_end, content_start) {
+            (false, _) if tag.is_empty => results.push(String::new()),
+            (false, _) => content_start = Some(tag.end),
+            (true, Some(start)) => {
+                results.push(element_text(&source[start..tag.start]));
+                content_start = None;
+            }
+            (true, None) => (),
+        }
+    }
+    results
+}
+
+/// Character data of an element: CDATA sections taken as they are, the
+/// rest unescaped, markup of nested elements dropped.
+fn element_text(content: &str) -> String {
+    let mut text = String::new();
+    let mut rest = content;
+    while !rest.is_empty() {
+        if let Some(cdata) = rest.strip_prefix("<![CDATA[") {
+            let end = cdata.find("]]>").unwrap_or(cdata.len());
+            text.push_str(&cdata[..end]);
+            rest = cdata.get(end + 3..).unwrap_or("");
+            continue
+        }
+        let end = rest.find('<').unwrap_or(rest.len());
+        text.push_str(&unescape_xml(&rest[..end]));
+        rest = &rest[end..];
+        if !rest.is_empty() && !rest.starts_with("<![CDATA[") {
+            rest = rest.find('>').map_or("", |end| &rest[end + 1..]);
+        }
+    }
+    text.trim().to_string()
+}
+
+/// Resolve the predefined entities and character references. Anything else
+/// that looks like an entity is kept as written.
+fn unescape_xml(source: &str) -> String {
+    let mut text = String::with_capacity(source.len());
+    let mut rest = source;
+    while let Some(start) = rest.find('&') {
+        text.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let resolved = rest.find(';').and_then(|end| {
+            let character = match &rest[1..end] {
+                "lt" => '<',
+                "gt" => '>',
+                "quot" => '"',
+                "apos" => '\'',
+                "amp" => '&',
+                reference => {
+                    let code = match reference.strip_prefix("#x").or_else(|| reference.strip_prefix("#X")) {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                        None => reference.strip_prefix('#')?.parse::<u32>().ok()?,
+                    };
+                    char::from_u32(code)?
+                }
+            };
+            Some((character, end + 1))
+        });
+        match resolved {
+            Some((character, length)) => {
+                text.push(character);
+                rest = &rest[length..];
+            }
+            None => {
+                text.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    text.push_str(rest);
+    text
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// DISCOVERY
+// ————————————————————————————————————————————————————————————————————————————
+
+/// Walk the given sitemaps (following sitemap indexes) and collect every page
+/// URL, keyed to the sitemap that listed it.
+pub async fn collect_sitemap_urls(
+    fetcher: &HttpFetcher,
+    sitemap_urls: impl IntoIterator<Item=Url>,
+    settings: &SitemapSettings,
+) -> IndexMap<Url, Url> {
+    let mut pending = sitemap_urls.into_iter().collect::<VecDeque<_>>();
+    let mut fetched = HashSet::<Url>::new();
+    let mut pages = IndexMap::<Url, Url>::new();
+    while let Some(sitemap_url) = pending.pop_front() {
+        if fetched.len() >= settings.max_sitemaps {
+            eprintln!("{}", format!(
+                "\t ⚠️ Sitemap limit of {} reached — ignoring the remaining sitemaps",
+                settings.max_sitemaps,
+            ).yellow());
+            break
+        }
+        if !fetched.insert(sitemap_url.clone()) {
+            continue
+        }
+        let response = match fetcher.download(&sitemap_url, Some(SitemapDocument::MAX_SIZE as u64)).await {
+            Ok(response) if response.is_success() => response,
+            Ok(_) => continue,
+            Err(error) => {
+                eprintln!("{}", format!(
+                    "\t ⚠️ Failed to fetch sitemap {:?}: {error}",
+                    sitemap_url.as_str(),
+                ).yellow());
+                continue
+            }
+        };
+        let document = match SitemapDocument::parse(&response.body) {
+            Ok(document) => document,
+            Err(error) => {
+                eprintln!("{}", format!(
+                    "\t ⚠️ Failed to read sitemap {:?}: {error}",
+                    sitemap_url.as_str(),
+                ).yellow());
+                continue
+            }
+        };
+        match document {
+            SitemapDocument::Index(children) => {
+                pending.extend(children);
+            }
+            SitemapDocument::UrlSet(urls) if urls.is_empty() => (),
+            SitemapDocument::UrlSet(urls) => {
+                eprintln!("{}", format!(
+                    "🗺  Sitemap {:?} lists {} URLs",
+                    sitemap_url.as_str(),
+                    urls.len(),
+                ).bright_blue());
+                for url in urls {
+                    pages.entry(url).or_insert_with(|| sitemap_url.clone());
+                }
+            }
+        }
+    }
+    pages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn urls(sources: &[&str]) -> Vec<Url> {
+        sources.iter().map(|source| Url::parse(source).unwrap()).collect()
+    }
+
+    fn gzip(bytes: &[u8]) -> Vec<u8> {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(bytes).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    const URLSET: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+  <!-- <loc>https://example.com/commented-out</loc> -->
+  <url>
+    <loc>https://example.com/</loc>
+    <lastmod>2024-05-01</lastmod>
+  </url>
+  <url><loc>
+    https://example.com/search?q=a&amp;page=2
+  </loc></url>
+  <url><loc><![CDATA[https://example.com/a&b]]></loc></url>
+  <url><loc>https://example.com/caf&#233;?x=&#x31;</loc></url>
+  <url><loc>not a url</loc></url>
+</urlset>"#;
+
+    #[test]
+    fn url_sets() {
+        assert_eq!(
+            SitemapDocument::parse_str(URLSET),
+            SitemapDocument::UrlSet(urls(&[
+                "https://example.com/",
+                "https://example.com/search?q=a&page=2",
+                "https://example.com/a&b",
+                "https://example.com/café?x=1",
+            ])),
+        );
+    }
+
+    #[test]
+    fn sitemap_indexes_with_prefixes_and_padded_tags() {
+        let source = r#"<?xml version="1.0"?>
+<sm:sitemapindex xmlns:sm="http://www.sitemaps.org/schemas/sitemap/0.9">
+  <sm:sitemap><sm:loc >https://example.com/a.xml</sm:loc ></sm:sitemap>
+  <sm:sitemap>
+    < sm:loc xml:lang="en" note="a > b">https://example.com/b.xml.gz</sm:loc>
+  </sm:sitemap>
+</sm:sitemapindex>"#;
+        assert_eq!(
+            SitemapDocument::parse_str(source),
+            SitemapDocument::Index(urls(&["https://example.com/a.xml", "https://example.com/b.xml.gz"])),
+        );
+        // The root element decides, not a mention of it elsewhere.
+        let source = "<urlset><!-- no <sitemapindex> here --><url><loc>https://example.com/</loc></url></urlset>";
+        assert_eq!(SitemapDocument::parse_str(source), SitemapDocument::UrlSet(urls(&["https://example.com/"])));
+    }
+
+    #[test]
+    fn plain_text_sitemaps() {
+        let source = "\u{feff}https://example.com/\n  https://example.com/about  \n\nnot a url\n";
+        assert_eq!(
+            SitemapDocument::parse_str(source),
+            SitemapDocument::UrlSet(urls(&["https://example.com/", "https://example.com/about"])),
+        );
+    }
+
+    #[test]
+    fn gzip_bodies() {
+        let document = SitemapDocument::parse(&gzip(URLSET.as_bytes())).unwrap();
+        assert_eq!(document, SitemapDocument::parse_str(URLSET));
+        assert!(SitemapDocument::parse(&[0x1f, 0x8b, 0x00]).is_err());
+    }
+
+    #[test]
+    fn oversized_bodies_are_rejected() {
+        // Compresses to a few dozen kilobytes.
+        let bomb = gzip(&vec![b' '; SitemapDocument::MAX_SIZE + 1]);
+        assert!(bomb.len() < 1024 * 1024);
+        assert_eq!(SitemapDocument::parse(&bomb).unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+        let plain = vec![b' '; SitemapDocument::MAX_SIZE + 1];
+        assert_eq!(SitemapDocument::parse(&plain).unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+        let at_limit = gzip(&vec![b' '; SitemapDocument::MAX_SIZE]);
+        assert_eq!(SitemapDocument::parse(&at_limit).unwrap(), SitemapDocument::UrlSet(Vec::new()));
+    }
+
+    #[test]
+    fn entities() {
+        assert_eq!(unescape_xml("a &lt;b&gt; &quot;c&quot; &apos;d&apos; &amp;amp;"), "a <b> \"c\" 'd' &amp;");
+        assert_eq!(unescape_xml("&#65;&#x42;&#X43;"), "ABC");
+        assert_eq!(unescape_xml("&nbsp; & &#xZZ; &#1114112;"), "&nbsp; & &#xZZ; &#1114112;");
+    }
+
+    #[test]
+    fn element_text_and_empty_elements() {
+        assert_eq!(element_text(" a <b>bold</b> <![CDATA[<raw> &amp;]]> &amp; "), "a bold <raw> &amp; &");
+        assert_eq!(extract_elements("<loc/><loc>x</loc><location>y</location>", "loc"), ["", "x"]);
+    }
+}
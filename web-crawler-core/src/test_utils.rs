@@ -0,0 +1,38 @@
+//! Helpers shared by the unit tests.
+
+use std::path::{Path, PathBuf};
+
+/// A fresh, empty directory below the system temp directory, removed again
+/// when dropped, including when the test panics.
+#[derive(Debug)]
+pub struct TempDirectory(PathBuf);
+
+impl TempDirectory {
+    /// `name` keeps tests running in parallel apart; the process id keeps
+    /// concurrent test runs apart.
+    pub fn new(name: &str) -> Self {
+        let directory = std::env::temp_dir().join(format!("web-crawler-core-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(&directory).unwrap();
+        Self(directory)
+    }
+}
+
+impl std::ops::Deref for TempDirectory {
+    type Target = Path;
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TempDirectory {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDirectory {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
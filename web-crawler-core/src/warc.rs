@@ -0,0 +1,821 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use colored::Colorize;
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use url::Url;
+
+use crate::metadata::common::{DiscoverySource, DocumentResponse, SnapshotDate, Status};
+use crate::metadata::project::ProjectLog;
+use crate::metadata::snapshot::SnapshotLog;
+use crate::observer::{CrawlObserver, CrawlWarning};
+
+// ————————————————————————————————————————————————————————————————————————————
+// SETTINGS
+// ————————————————————————————————————————————————————————————————————————————
+
+/// Where page snapshots are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SnapshotOutput {
+    /// `source.snapshot.html` files next to their logs.
+    #[default]
+    Directory,
+    /// WARC files only. Logs are still written, since they are the crawl
+    /// state.
+    Warc,
+    Both,
+}
+
+impl SnapshotOutput {
+    pub fn writes_directory(&self) -> bool {
+        matches!(self, Self::Directory | Self::Both)
+    }
+    pub fn writes_warc(&self) -> bool {
+        matches!(self, Self::Warc | Self::Both)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WarcSettings {
+    pub output: SnapshotOutput,
+    /// A new file is started once the current one reaches this size.
+    pub max_file_size: u64,
+    /// Gzip every record on its own (`.warc.gz`).
+    pub compress: bool,
+}
+
+impl Default for WarcSettings {
+    fn default() -> Self {
+        Self {
+            output: SnapshotOutput::Directory,
+            max_file_size: Self::DEFAULT_MAX_FILE_SIZE,
+            compress: true,
+        }
+    }
+}
+
+impl WarcSettings {
+    pub const DEFAULT_MAX_FILE_SIZE: u64 = 1024 * 1024 * 1024;
+    /// Directory below the project directory holding the WARC files.
+    pub const DIRECTORY_NAME: &'static str = "warc";
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// RECORDS
+// ————————————————————————————————————————————————————————————————————————————
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WarcRecordType {
+    Warcinfo,
+    Request,
+    Response,
+    Resource,
+    Metadata,
+    Revisit,
+}
+
+impl WarcRecordType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Warcinfo => "warcinfo",
+            Self::Request => "request",
+            Self::Response => "response",
+            Self::Resource => "resource",
+            Self::Metadata => "metadata",
+            Self::Revisit => "revisit",
+        }
+    }
+}
+
+/// A single WARC record. `WARC-Record-ID`, `WARC-Date`, `Content-Length` and
+/// the block digest are filled in by [`WarcRecord::new`].
+#[derive(Debug, Clone)]
+pub struct WarcRecord {
+    pub record_type: WarcRecordType,
+    pub record_id: String,
+    pub date: String,
+    pub target_uri: Option<String>,
+    pub content_type: Option<String>,
+    /// Further named fields, in order.
+    pub fields: Vec<(String, String)>,
+    pub block: Vec<u8>,
+}
+
+impl WarcRecord {
+    pub fn new(record_type: WarcRecordType, date: &SnapshotDate, block: Vec<u8>) -> Self {
+        Self {
+            record_type,
+            record_id: new_record_id(),
+            date: warc_date(date),
+            target_uri: None,
+            content_type: None,
+            fields: Vec::new(),
+            block,
+        }
+    }
+    pub fn with_target_uri(mut self, target_uri: impl Into<String>) -> Self {
+        self.target_uri = Some(target_uri.into());
+        self
+    }
+    pub fn with_content_type(mut self, content_type: impl Into<String>) -> Self {
+        self.content_type = Some(content_type.into());
+        self
+    }
+    pub fn with_field(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.fields.push((name.into(), value.into()));
+        self
+    }
+    /// The record as written to an uncompressed WARC file, including the
+    /// two trailing CRLFs.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut header = String::from("WARC/1.1\r\n");
+        header.push_str(&format!("WARC-Type: {}\r\n", self.record_type.as_str()));
+        header.push_str(&format!("WARC-Record-ID: {}\r\n", self.record_id));
+        header.push_str(&format!("WARC-Date: {}\r\n", self.date));
+        if let Some(target_uri) = self.target_uri.as_ref() {
+            header.push_str(&format!("WARC-Target-URI: {target_uri}\r\n"));
+        }
+        for (name, value) in self.fields.iter() {
+            header.push_str(&format!("{name}: {value}\r\n"));
+        }
+        header.push_str(&format!("WARC-Block-Digest: {}\r\n", crate::recrawl::content_hash(&self.block)));
+        if let Some(content_type) = self.content_type.as_ref() {
+            header.push_str(&format!("Content-Type: {content_type}\r\n"));
+        }
+        header.push_str(&format!("Content-Length: {}\r\n\r\n", self.block.len()));
+        let mut bytes = header.into_bytes();
+        bytes.extend_from_slice(&self.block);
+        bytes.extend_from_slice(b"\r\n\r\n");
+        bytes
+    }
+}
+
+/// `<urn:uuid:…>` in the UUID v4 layout, from a hash of the time, the
+/// process and a counter.
+fn new_record_id() -> String {
+    use sha2::{Digest, Sha256};
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    let seed = format!("{nanos}:{}:{}", std::process::id(), COUNTER.fetch_add(1, Ordering::Relaxed));
+    let hash = Sha256::digest(seed.as_bytes());
+    let mut bytes = [0u8; 16];
+    bytes.copy_from_slice(&hash[..16]);
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex = bytes.iter().map(|b| format!("{b:02x}")).collect::<String>();
+    format!(
+        "<urn:uuid:{}-{}-{}-{}-{}>",
+        &hex[0..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..32],
+    )
+}
+
+/// `WARC-Date` form (UTC, second precision) of a snapshot date. Dates that
+/// do not parse are replaced by the current time.
+fn warc_date(date: &SnapshotDate) -> String {
+    chrono::DateTime::parse_from_rfc3339(&date.0)
+        .map(|date| date.with_timezone(&chrono::Utc))
+        .unwrap_or_else(|_| chrono::Utc::now())
+        .to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+}
+
+/// `application/warc-fields` block.
+fn warc_fields<'a>(fields: impl IntoIterator<Item=(&'a str, String)>) -> Vec<u8> {
+    fields
+        .into_iter()
+        .map(|(name, value)| format!("{name}: {value}\r\n"))
+        .collect::<String>()
+        .into_bytes()
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// PAGES
+// ————————————————————————————————————————————————————————————————————————————
+
+/// Everything written to the WARC for one visited page or document.
+#[derive(Debug, Clone)]
+pub struct WarcPage<'a> {
+    pub url: &'a Url,
+    pub date: &'a SnapshotDate,
+    pub http_status: Option<i64>,
+    pub content_type: &'a str,
+    pub body: &'a [u8],
+    /// The response as the browser saw it. Without it, only a status line
+    /// and `Content-Type` are written.
+    pub response: Option<&'a DocumentResponse>,
+    /// `application/warc-fields` of the metadata record, e.g. `outlink`.
+    pub metadata: Vec<(&'static str, String)>,
+}
+
+/// `metadata` fields describing a snapshot log.
+pub fn snapshot_metadata(log: &SnapshotLog) -> Vec<(&'static str, String)> {
+    let mut metadata = Vec::new();
+    match log.discovered_via.as_ref() {
+        Some(DiscoverySource::Seed) => metadata.push(("seed", String::from("true"))),
+        Some(DiscoverySource::Link { referrer }) => metadata.push(("via", referrer.0.to_string())),
+        Some(DiscoverySource::Sitemap { sitemap }) => metadata.push(("sitemap", sitemap.0.to_string())),
+        None => (),
+    }
+    if let Some(depth) = log.depth {
+        metadata.push(("hopsFromSeed", depth.to_string()));
+    }
+    metadata.push(("snapshot-path", log.snapshot_path.0.to_string_lossy().into_owned()));
+    if let Some(simhash) = log.simhash.as_ref() {
+        metadata.push(("simhash", simhash.clone()));
+    }
+    if let Some(original) = log.duplicate_of.as_ref() {
+        metadata.push(("duplicate-of", original.0.to_string_lossy().into_owned()));
+    }
+    for link in log.outgoing_links.iter() {
+        metadata.push(("outlink", link.0.to_string()));
+    }
+    metadata
+}
+
+fn http_request_block(url: &Url, user_agent: &str) -> Vec<u8> {
+    let mut target = url.path().to_string();
+    if let Some(query) = url.query() {
+        target.push('?');
+        target.push_str(query);
+    }
+    let host = match url.port() {
+        Some(port) => format!("{}:{port}", url.host_str().unwrap_or_default()),
+        None => url.host_str().unwrap_or_default().to_string(),
+    };
+    format!("GET {target} HTTP/1.1\r\nHost: {host}\r\nUser-Agent: {user_agent}\r\n\r\n").into_bytes()
+}
+
+fn http_response_block(page: &WarcPage) -> Vec<u8> {
+    let http_status = page.http_status.unwrap_or(200);
+    let reason = match page.response {
+        Some(response) if !response.status_text.is_empty() => response.status_text.as_str(),
+        _ => u16::try_from(http_status)
+            .ok()
+            .and_then(|code| reqwest::StatusCode::from_u16(code).ok())
+            .and_then(|code| code.canonical_reason())
+            .unwrap_or_default(),
+    };
+    let mut head = format!("HTTP/1.1 {http_status} {reason}\r\n");
+    match page.response {
+        // The body is stored decoded and re-serialized, so the framing
+        // headers of the original response no longer apply.
+        Some(response) => {
+            let headers = response.headers
+                .iter()
+                .filter(|(name, _)| !matches!(name.as_str(), "content-length" | "content-encoding" | "transfer-encoding"));
+            for (name, value) in headers {
+                for value in value.split('\n') {
+                    head.push_str(&format!("{name}: {value}\r\n"));
+                }
+            }
+        }
+        None => head.push_str(&format!("content-type: {}\r\n", page.content_type)),
+    }
+    head.push_str(&format!("content-length: {}\r\n\r\n", page.body.len()));
+    let mut block = head.into_bytes();
+    block.extend_from_slice(page.body);
+    block
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// WRITER
+// ————————————————————————————————————————————————————————————————————————————
+
+struct WarcFile {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    size: u64,
+}
+
+/// Appends records to `<directory>/<prefix>-<timestamp>-<serial>.warc[.gz]`,
+/// starting a new file, with its own `warcinfo` record, whenever the current
+/// one exceeds [`WarcSettings::max_file_size`].
+pub struct WarcWriter {
+    directory: PathBuf,
+    prefix: String,
+    settings: WarcSettings,
+    user_agent: String,
+    started: String,
+    serial: usize,
+    file: Option<WarcFile>,
+}
+
+impl WarcWriter {
+    pub fn new(
+        directory: impl Into<PathBuf>,
+        prefix: impl Into<String>,
+        settings: WarcSettings,
+        user_agent: impl Into<String>,
+    ) -> Self {
+        Self {
+            directory: directory.into(),
+            prefix: prefix.into(),
+            settings,
+            user_agent: user_agent.into(),
+            started: chrono::Utc::now().format("%Y%m%d%H%M%S").to_string(),
+            serial: 0,
+            file: None,
+        }
+    }
+    /// Files written so far, including the current one.
+    pub fn file_count(&self) -> usize {
+        self.serial
+    }
+    pub fn write_record(&mut self, record: &WarcRecord) -> std::io::Result<()> {
+        let rotate = self.file
+            .as_ref()
+            .is_none_or(|file| file.size >= self.settings.max_file_size);
+        if rotate {
+            self.open_next_file()?;
+        }
+        self.append(record)
+    }
+    /// `request`, `response` and `metadata` records for a page. Returns the
+    /// ID of the response record.
+    ///
+    /// For HTML pages the response body is the DOM as serialized after the
+    /// page settled, i.e. the same HTML as `source.snapshot.html`, not the
+    /// bytes the server sent.
+    pub fn write_page(&mut self, page: &WarcPage) -> std::io::Result<String> {
+        let target_uri = page.url.as_str();
+        let mut response = WarcRecord::new(
+            WarcRecordType::Response,
+            page.date,
+            http_response_block(page),
+        )
+            .with_target_uri(target_uri)
+            .with_content_type("application/http;msgtype=response")
+            .with_field("WARC-Payload-Digest", crate::recrawl::content_hash(page.body));
+        if let Some(ip_address) = page.response.and_then(|response| response.remote_ip_address.as_ref()) {
+            response = response.with_field("WARC-IP-Address", ip_address.trim_matches(['[', ']']));
+        }
+        let request = WarcRecord::new(
+            WarcRecordType::Request,
+            page.date,
+            http_request_block(page.url, &self.user_agent),
+        )
+            .with_target_uri(target_uri)
+            .with_content_type("application/http;msgtype=request")
+            .with_field("WARC-Concurrent-To", response.record_id.clone());
+        self.write_record(&request)?;
+        self.write_record(&response)?;
+        if !page.metadata.is_empty() {
+            self.write_metadata(page.url, page.date, Some(&response.record_id), &page.metadata)?;
+        }
+        Ok(response.record_id)
+    }
+    /// A `revisit` record for a page whose content did not change since
+    /// `original_date`, plus its `metadata` record.
+    pub fn write_revisit(
+        &mut self,
+        page: &WarcPage,
+        original_date: &SnapshotDate,
+    ) -> std::io::Result<()> {
+        let target_uri = page.url.as_str();
+        let revisit = WarcRecord::new(WarcRecordType::Revisit, page.date, Vec::new())
+            .with_target_uri(target_uri)
+            .with_content_type("application/http;msgtype=response")
+            .with_field("WARC-Profile", "http://netpreserve.org/warc/1.1/revisit/identical-payload-digest")
+            .with_field("WARC-Refers-To-Target-URI", target_uri)
+            .with_field("WARC-Refers-To-Date", warc_date(original_date))
+            .with_field("WARC-Payload-Digest", crate::recrawl::content_hash(page.body));
+        self.write_record(&revisit)?;
+        self.write_metadata(page.url, page.date, Some(&revisit.record_id), &page.metadata)
+    }
+    /// A `metadata` record about `url`, referring to the record `refers_to`
+    /// if there is one.
+    pub fn write_metadata(
+        &mut self,
+        url: &Url,
+        date: &SnapshotDate,
+        refers_to: Option<&str>,
+        metadata: &[(&'static str, String)],
+    ) -> std::io::Result<()> {
+        let block = warc_fields(metadata.iter().map(|(name, value)| (*name, value.clone())));
+        let mut record = WarcRecord::new(WarcRecordType::Metadata, date, block)
+            .with_target_uri(url.as_str())
+            .with_content_type("application/warc-fields");
+        if let Some(refers_to) = refers_to {
+            record = record.with_field("WARC-Refers-To", refers_to);
+        }
+        self.write_record(&record)
+    }
+    /// A `resource` record, e.g. a screenshot, under a `urn:<kind>:<url>`
+    /// target so it does not shadow the page itself in replay tools.
+    pub fn write_rendering(
+        &mut self,
+        kind: &str,
+        url: &Url,
+        date: &SnapshotDate,
+        content_type: &str,
+        bytes: &[u8],
+    ) -> std::io::Result<()> {
+        let record = WarcRecord::new(WarcRecordType::Resource, date, bytes.to_vec())
+            .with_target_uri(format!("urn:{kind}:{url}"))
+            .with_content_type(content_type);
+        self.write_record(&record)
+    }
+    pub fn flush(&mut self) -> std::io::Result<()> {
+        match self.file.as_mut() {
+            Some(file) => file.writer.flush(),
+            None => Ok(()),
+        }
+    }
+    fn open_next_file(&mut self) -> std::io::Result<()> {
+        self.flush()?;
+        std::fs::create_dir_all(&self.directory)?;
+        self.serial += 1;
+        let extension = match self.settings.compress {
+            true => "warc.gz",
+            false => "warc",
+        };
+        let file_name = format!("{}-{}-{:05}.{extension}", self.prefix, self.started, self.serial);
+        let path = self.directory.join(&file_name);
+        let writer = BufWriter::new(File::create(&path)?);
+        self.file = Some(WarcFile { path, writer, size: 0 });
+        let warcinfo = WarcRecord::new(
+            WarcRecordType::Warcinfo,
+            &SnapshotDate::now(),
+            warc_fields([
+                ("software", format!("web-crawler/{}", env!("CARGO_PKG_VERSION"))),
+                ("format", String::from("WARC File Format 1.1")),
+                ("conformsTo", String::from("https://iipc.github.io/warc-specifications/specifications/warc-format/warc-1.1/")),
+                ("isPartOf", self.prefix.clone()),
+                ("http-header-user-agent", self.user_agent.clone()),
+            ]),
+        )
+            .with_content_type("application/warc-fields")
+            .with_field("WARC-Filename", file_name);
+        self.append(&warcinfo)
+    }
+    fn append(&mut self, record: &WarcRecord) -> std::io::Result<()> {
+        let Some(file) = self.file.as_mut() else {
+            return Ok(())
+        };
+        let bytes = record.to_bytes();
+        let bytes = match self.settings.compress {
+            true => {
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(&bytes)?;
+                encoder.finish()?
+            }
+            false => bytes,
+        };
+        file.writer.write_all(&bytes)?;
+        file.size += bytes.len() as u64;
+        Ok(())
+    }
+}
+
+impl Drop for WarcWriter {
+    fn drop(&mut self) {
+        if let Err(error) = self.flush() {
+            let path = self.file.as_ref().map(|file| file.path.display().to_string());
+            eprintln!("{}", format!("\t ⚠️ Failed to flush {path:?}: {error}").red());
+        }
+    }
+}
+
+// ————————————————————————————————————————————————————————————————————————————
+// EXPORT
+// ————————————————————————————————————————————————————————————————————————————
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WarcExport {
+    pub pages: usize,
+    pub versions: usize,
+    pub resources: usize,
+    pub files: usize,
+}
+
+/// Write the snapshots, earlier versions, renders and downloaded documents
+/// of a project directory to WARC files in `output_directory`. Files that
+/// cannot be read are left out and reported to `observer`.
+pub fn export_project(
+    project_directory: &Path,
+    project_log: &ProjectLog,
+    output_directory: &Path,
+    settings: WarcSettings,
+    user_agent: &str,
+    observer: &dyn CrawlObserver,
+) -> std::io::Result<WarcExport> {
+    let prefix = project_directory
+        .file_name()
+        .map(|name| format!("{}-export", name.to_string_lossy()))
+        .unwrap_or_else(|| String::from("export"));
+    let mut writer = WarcWriter::new(output_directory, prefix, settings, user_agent);
+    let mut export = WarcExport::default();
+    let read = |path: &Path| -> Option<Vec<u8>> {
+        std::fs::read(project_directory.join(path))
+            .inspect_err(|error| {
+                observer.warning(&CrawlWarning::UnreadableFile { path, error: error.to_string() });
+            })
+            .ok()
+    };
+    let snapshot_logs = project_log.snapshot_logs().map_err(|error| std::io::Error::other(error.to_string()))?;
+    for log in snapshot_logs {
+        let url = &log.original_url.0;
+        for version in log.versions.iter() {
+            let Some(body) = read(&version.snapshot_path.0) else {
+                continue
+            };
+            writer.write_page(&WarcPage {
+                url,
+                date: &version.snapshot_date,
+                http_status: None,
+                content_type: "text/html; charset=utf-8",
+                body: &body,
+                response: None,
+                metadata: vec![("snapshot-path", version.snapshot_path.0.to_string_lossy().into_owned())],
+            })?;
+            export.versions += 1;
+        }
+        let metadata = snapshot_metadata(&log);
+        if log.duplicate_of.is_some() {
+            writer.write_metadata(url, &log.snapshot_date, None, &metadata)?;
+            continue
+        }
+        let Some(body) = read(&log.snapshot_path.0) else {
+            continue
+        };
+        writer.write_page(&WarcPage {
+            url,
+            date: &log.snapshot_date,
+            http_status: log.http_status,
+            content_type: "text/html; charset=utf-8",
+            body: &body,
+            response: log.response.as_ref(),
+            metadata,
+        })?;
+        export.pages += 1;
+        if let Some(bytes) = log.screenshot_path.as_ref().and_then(|path| read(&path.0)) {
+            let content_type = match log.screenshot_path.as_ref().and_then(|path| path.0.extension()) {
+                Some(extension) if extension == "jpg" => "image/jpeg",
+                _ => "image/png",
+            };
+            writer.write_rendering("screenshot", url, &log.snapshot_date, content_type, &bytes)?;
+        }
+        if let Some(bytes) = log.pdf_path.as_ref().and_then(|path| read(&path.0)) {
+            writer.write_rendering("pdf", url, &log.snapshot_date, "application/pdf", &bytes)?;
+        }
+        if let Some(bytes) = log.har_path.as_ref().and_then(|path| read(&path.0)) {
+            writer.write_rendering("har", url, &log.snapshot_date, "application/json", &bytes)?;
+        }
+    }
+    let task_logs = project_log.task_logs().map_err(|error| std::io::Error::other(error.to_string()))?;
+    for task_log in task_logs {
+        let date = task_log.last_attempt.clone().unwrap_or_else(SnapshotDate::now);
+        for status in task_log.entries.iter() {
+            let Status::Resource { url, http_status, content_type, file_path, .. } = status else {
+                continue
+            };
+            let Some(body) = read(&file_path.0) else {
+                continue
+            };
+            writer.write_page(&WarcPage {
+                url: &url.0,
+                date: &date,
+                http_status: *http_status,
+                content_type,
+                body: &body,
+                response: None,
+                metadata: Vec::new(),
+            })?;
+            export.resources += 1;
+        }
+    }
+    writer.flush()?;
+    export.files = writer.file_count();
+    Ok(export)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::io::Read;
+
+    use super::*;
+    use crate::test_utils::TempDirectory;
+
+    fn url(source: &str) -> Url {
+        Url::parse(source).unwrap()
+    }
+
+    fn date() -> SnapshotDate {
+        SnapshotDate(String::from("2024-05-01T12:30:45.123+02:00"))
+    }
+
+    /// The contents of every file in `directory`, by name.
+    fn read_files(directory: &Path) -> Vec<(String, Vec<u8>)> {
+        let mut files = std::fs::read_dir(directory)
+            .unwrap()
+            .map(|entry| {
+                let path = entry.unwrap().path();
+                let name = path.file_name().unwrap().to_string_lossy().into_owned();
+                (name, std::fs::read(path).unwrap())
+            })
+            .collect::<Vec<_>>();
+        files.sort();
+        files
+    }
+
+    fn record_types(contents: &str) -> Vec<&str> {
+        contents
+            .lines()
+            .filter_map(|line| line.strip_prefix("WARC-Type: "))
+            .collect()
+    }
+
+    fn page<'a>(url: &'a Url, date: &'a SnapshotDate, response: Option<&'a DocumentResponse>) -> WarcPage<'a> {
+        WarcPage {
+            url,
+            date,
+            http_status: Some(200),
+            content_type: "text/html",
+            body: b"<p>hi</p>",
+            response,
+            metadata: vec![("outlink", String::from("https://example.com/next"))],
+        }
+    }
+
+    #[test]
+    fn frames_a_record() {
+        let mut record = WarcRecord::new(WarcRecordType::Resource, &date(), b"hello".to_vec())
+            .with_target_uri("https://example.com/")
+            .with_content_type("text/plain")
+            .with_field("WARC-Concurrent-To", "<urn:uuid:other>");
+        record.record_id = String::from("<urn:uuid:record>");
+        let expected = format!(
+            "WARC/1.1\r\n\
+             WARC-Type: resource\r\n\
+             WARC-Record-ID: <urn:uuid:record>\r\n\
+             WARC-Date: 2024-05-01T10:30:45Z\r\n\
+             WARC-Target-URI: https://example.com/\r\n\
+             WARC-Concurrent-To: <urn:uuid:other>\r\n\
+             WARC-Block-Digest: {}\r\n\
+             Content-Type: text/plain\r\n\
+             Content-Length: 5\r\n\
+             \r\n\
+             hello\r\n\r\n",
+            crate::recrawl::content_hash(b"hello"),
+        );
+        assert_eq!(String::from_utf8(record.to_bytes()).unwrap(), expected);
+    }
+
+    #[test]
+    fn counts_the_block_in_bytes() {
+        let block = "café\r\n".as_bytes().to_vec();
+        let record = WarcRecord::new(WarcRecordType::Metadata, &date(), block.clone());
+        let bytes = record.to_bytes();
+        let contents = String::from_utf8(bytes.clone()).unwrap();
+        assert!(contents.contains("Content-Length: 7\r\n\r\n"), "{contents}");
+        assert!(!contents.contains("Content-Type"), "{contents}");
+        let (_, rest) = contents.split_once("\r\n\r\n").unwrap();
+        assert_eq!(rest.as_bytes(), [block.as_slice(), b"\r\n\r\n"].concat());
+    }
+
+    #[test]
+    fn record_ids_are_distinct_v4_uuids() {
+        let ids = (0..100).map(|_| new_record_id()).collect::<std::collections::HashSet<_>>();
+        assert_eq!(ids.len(), 100);
+        for id in ids {
+            let uuid = id.strip_prefix("<urn:uuid:").and_then(|id| id.strip_suffix('>')).unwrap();
+            let groups = uuid.split('-').map(str::len).collect::<Vec<_>>();
+            assert_eq!(groups, [8, 4, 4, 4, 12], "{id}");
+            assert_eq!(&uuid[14..15], "4", "{id}");
+            assert!(matches!(&uuid[19..20], "8" | "9" | "a" | "b"), "{id}");
+        }
+    }
+
+    #[test]
+    fn writes_dates_in_utc_seconds() {
+        assert_eq!(warc_date(&date()), "2024-05-01T10:30:45Z");
+        assert_eq!(warc_date(&SnapshotDate(String::from("2024-05-01T00:00:00Z"))), "2024-05-01T00:00:00Z");
+        // Unparsable dates fall back to now, still in WARC form.
+        let now = warc_date(&SnapshotDate(String::from("yesterday")));
+        assert!(chrono::DateTime::parse_from_rfc3339(&now).is_ok(), "{now}");
+        assert!(now.ends_with('Z') && !now.contains('.'), "{now}");
+    }
+
+    #[test]
+    fn builds_the_request_block() {
+        let block = http_request_block(&url("http://example.com:8080/a%20b?q=1#top"), "bot/1.0");
+        assert_eq!(
+            String::from_utf8(block).unwrap(),
+            "GET /a%20b?q=1 HTTP/1.1\r\nHost: example.com:8080\r\nUser-Agent: bot/1.0\r\n\r\n",
+        );
+        let block = http_request_block(&url("https://example.com/"), "bot/1.0");
+        assert!(String::from_utf8(block).unwrap().starts_with("GET / HTTP/1.1\r\nHost: example.com\r\n"));
+    }
+
+    #[test]
+    fn builds_the_response_block() {
+        let url = url("https://example.com/");
+        let date = date();
+        let response = DocumentResponse {
+            url: url.to_string(),
+            status: 200,
+            status_text: String::new(),
+            mime_type: String::from("text/html"),
+            protocol: Some(String::from("h2")),
+            remote_ip_address: Some(String::from("[2001:db8::1]")),
+            remote_port: Some(443),
+            timing: None,
+            headers: BTreeMap::from([
+                (String::from("content-encoding"), String::from("gzip")),
+                (String::from("content-length"), String::from("1234")),
+                (String::from("content-type"), String::from("text/html")),
+                (String::from("set-cookie"), String::from("a=1\nb=2")),
+            ]),
+        };
+        let block = http_response_block(&page(&url, &date, Some(&response)));
+        assert_eq!(
+            String::from_utf8(block).unwrap(),
+            "HTTP/1.1 200 OK\r\n\
+             content-type: text/html\r\n\
+             set-cookie: a=1\r\n\
+             set-cookie: b=2\r\n\
+             content-length: 9\r\n\
+             \r\n\
+             <p>hi</p>",
+        );
+        let block = http_response_block(&WarcPage { http_status: Some(404), ..page(&url, &date, None) });
+        assert_eq!(
+            String::from_utf8(block).unwrap(),
+            "HTTP/1.1 404 Not Found\r\ncontent-type: text/html\r\ncontent-length: 9\r\n\r\n<p>hi</p>",
+        );
+    }
+
+    #[test]
+    fn writes_a_page_after_the_warcinfo_record() {
+        let directory = TempDirectory::new("warc-page");
+        let settings = WarcSettings { compress: false, ..WarcSettings::default() };
+        let url = url("https://example.com/");
+        let date = date();
+        let mut writer = WarcWriter::new(directory.to_path_buf(), "crawl", settings, "bot/1.0");
+        let response_id = writer.write_page(&page(&url, &date, None)).unwrap();
+        writer.flush().unwrap();
+        let files = read_files(&directory);
+        let [(name, contents)] = files.as_slice() else {
+            panic!("expected one file, got {files:?}")
+        };
+        assert!(name.starts_with("crawl-") && name.ends_with("-00001.warc"), "{name}");
+        let contents = String::from_utf8(contents.clone()).unwrap();
+        assert_eq!(record_types(&contents), ["warcinfo", "request", "response", "metadata"]);
+        assert!(contents.contains(&format!("WARC-Filename: {name}\r\n")));
+        assert!(contents.contains(&format!("WARC-Concurrent-To: {response_id}\r\n")));
+        assert!(contents.contains(&format!("WARC-Refers-To: {response_id}\r\n")));
+        assert!(contents.contains("outlink: https://example.com/next\r\n"));
+        assert_eq!(contents.matches("WARC/1.1\r\n").count(), 4);
+        assert!(contents.ends_with("\r\n\r\n"));
+    }
+
+    #[test]
+    fn starts_a_new_file_past_the_size_limit() {
+        let directory = TempDirectory::new("warc-rotate");
+        let settings = WarcSettings { compress: false, max_file_size: 1, ..WarcSettings::default() };
+        let url = url("https://example.com/");
+        let date = date();
+        let mut writer = WarcWriter::new(directory.to_path_buf(), "crawl", settings, "bot/1.0");
+        writer.write_rendering("screenshot", &url, &date, "image/png", b"png").unwrap();
+        writer.write_rendering("pdf", &url, &date, "application/pdf", b"pdf").unwrap();
+        writer.flush().unwrap();
+        assert_eq!(writer.file_count(), 2);
+        let files = read_files(&directory);
+        assert_eq!(files.len(), 2);
+        for (_, contents) in files.iter() {
+            let contents = String::from_utf8(contents.clone()).unwrap();
+            assert_eq!(record_types(&contents), ["warcinfo", "resource"]);
+        }
+        assert!(String::from_utf8_lossy(&files[0].1).contains("WARC-Target-URI: urn:screenshot:https://example.com/\r\n"));
+    }
+
+    #[test]
+    fn compresses_every_record_as_its_own_gzip_member() {
+        let directory = TempDirectory::new("warc-gzip");
+        let settings = WarcSettings { compress: true, ..WarcSettings::default() };
+        let url = url("https://example.com/");
+        let date = date();
+        let mut writer = WarcWriter::new(directory.to_path_buf(), "crawl", settings, "bot/1.0");
+        writer.write_page(&page(&url, &date, None)).unwrap();
+        writer.flush().unwrap();
+        let files = read_files(&directory);
+        let [(name, compressed)] = files.as_slice() else {
+            panic!("expected one file, got {files:?}")
+        };
+        assert!(name.ends_with(".warc.gz"), "{name}");
+        // One member per record: a gzip header at the start of each.
+        let members = compressed.windows(3).filter(|window| window == &[0x1f, 0x8b, 0x08]).count();
+        assert_eq!(members, 4);
+        let mut contents = String::new();
+        flate2::read::MultiGzDecoder::new(compressed.as_slice())
+            .read_to_string(&mut contents)
+            .unwrap();
+        assert_eq!(record_types(&contents), ["warcinfo", "request", "response", "metadata"]);
+    }
+}
@@ -1,5 +1,7 @@
 use std::path::PathBuf;
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
+use web_crawler_core::graph::LinkGraphFormat;
+use web_crawler_core::retry::CrawlMode;
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
@@ -11,6 +13,15 @@ pub struct CommandLineInterface {
 #[derive(Subcommand, Debug)]
 enum SubCommand {
     Run(RunCli),
+    /// Export the link graph of a crawled project.
+    Graph(GraphCli),
+    /// Convert the snapshots of a crawled project to WARC files.
+    Warc(WarcCli),
+    /// Import the TOML logs of a project crawled before the project database
+    /// existed. Crawls import them on their own as well.
+    Import(ImportCli),
+    /// Print the URL stored at a snapshot path, or the snapshot path of a URL.
+    Locate(LocateCli),
 }
 
 #[derive(Parser, Debug)]
@@ -22,6 +33,73 @@ struct RunCli {
     /// Name of the project ro run.
     #[arg(short, long)]
     pub id: String,
+
+    /// Only revisit URLs whose last attempt failed with a retryable error.
+    #[arg(long)]
+    pub retry_failed: bool,
+}
+
+#[derive(Parser, Debug)]
+struct GraphCli {
+    /// Path to the manifest file.
+    pub manifest: PathBuf,
+
+    /// Name of the project to export.
+    #[arg(short, long)]
+    pub id: String,
+
+    #[arg(short, long, value_enum, default_value_t = GraphFormat::Json)]
+    pub format: GraphFormat,
+
+    /// Defaults to `link-graph.<format>` in the project directory.
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Parser, Debug)]
+struct WarcCli {
+    /// Path to the manifest file.
+    pub manifest: PathBuf,
+
+    /// Name of the project to convert.
+    #[arg(short, long)]
+    pub id: String,
+
+    /// Directory for the WARC files. Defaults to `warc/` in the project
+    /// directory.
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Parser, Debug)]
+struct ImportCli {
+    /// Path to the manifest file.
+    pub manifest: PathBuf,
+
+    /// Name of the project to import.
+    #[arg(short, long)]
+    pub id: String,
+}
+
+#[derive(Parser, Debug)]
+struct LocateCli {
+    /// Path to the manifest file.
+    pub manifest: PathBuf,
+
+    /// Name of the project to search.
+    #[arg(short, long)]
+    pub id: String,
+
+    /// A URL, or a snapshot file or directory, either relative to the
+    /// project directory or below it.
+    pub target: String,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum GraphFormat {
+    Json,
+    Dot,
+    Graphml,
 }
 
 impl CommandLineInterface {
@@ -31,14 +109,49 @@ impl CommandLineInterface {
     pub async fn execute(self) {
         match self.command {
             SubCommand::Run(build) => build.execute().await,
+            SubCommand::Graph(graph) => graph.execute(),
+            SubCommand::Warc(warc) => warc.execute(),
+            SubCommand::Import(import) => import.execute(),
+            SubCommand::Locate(locate) => locate.execute(),
         }
     }
 }
 
 impl RunCli {
     pub async fn execute(self) {
-        crate::evaluate(&self.manifest, &self.id).await
+        let mode = match self.retry_failed {
+            true => CrawlMode::RetryFailed,
+            false => CrawlMode::Full,
+        };
+        crate::evaluate(&self.manifest, &self.id, mode).await
+    }
+}
+
+impl GraphCli {
+    pub fn execute(self) {
+        let format = match self.format {
+            GraphFormat::Json => LinkGraphFormat::Json,
+            GraphFormat::Dot => LinkGraphFormat::Dot,
+            GraphFormat::Graphml => LinkGraphFormat::GraphMl,
+        };
+        crate::export_link_graph(&self.manifest, &self.id, format, self.output)
+    }
+}
+
+impl WarcCli {
+    pub fn execute(self) {
+        crate::export_warc(&self.manifest, &self.id, self.output)
     }
 }
 
+impl ImportCli {
+    pub fn execute(self) {
+        crate::import_project_logs(&self.manifest, &self.id)
+    }
+}
 
+impl LocateCli {
+    pub fn execute(self) {
+        crate::locate_snapshot(&self.manifest, &self.id, &self.target)
+    }
+}
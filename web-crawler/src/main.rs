@@ -1,10 +1,34 @@
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
 
+use colored::Colorize;
 use indexmap::IndexSet;
 use url::Url;
+use web_automation_manifest::specification::{FrontierOrderSpec, HarBodiesSpec, HeadlessSpec, NormalizationRuleSpec, ProjectSpec, RetryableFailureSpec, ScopeModeSpec, ScreenshotFormatSpec, SnapshotOutputSpec, UrlRuleActionSpec, UrlRuleTargetSpec, WaitStrategySpec};
+use web_crawler_core::browser::{BrowserSettings, HeadlessMode, NavigationTimeouts, WaitSettings, WaitStrategy, WebClientSettings};
+use web_crawler_core::budget::CrawlBudget;
+use web_crawler_core::capture::{CaptureSettings, HarBodies, HarOptions, PdfOptions, ScreenshotFormat, ScreenshotOptions};
+use web_crawler_core::canonicalize::{NormalizationRule, UrlNormalizer};
+use web_crawler_core::dedup::DedupSettings;
 use web_crawler_core::engine::{CrawlerSettings, WebCrawler};
-use web_crawler_core::settings::UrlVisitorSettings;
+use web_crawler_core::frontier::{FrontierOrder, KeywordScorer};
+use web_crawler_core::graph::{LinkGraph, LinkGraphFormat};
+use web_crawler_core::metadata::project::ProjectLog;
+use web_crawler_core::metadata::common::RelativeFilePath;
+use web_crawler_core::metadata::store::ProjectStore;
+use web_crawler_core::observer::ConsoleObserver;
+use web_crawler_core::path_utils;
+use web_crawler_core::politeness::PolitenessSettings;
+use web_crawler_core::recrawl::RecrawlSettings;
+use web_crawler_core::resources::ResourceSettings;
+use web_crawler_core::retry::{CrawlMode, RetryPolicy, RetryableFailure};
+use web_crawler_core::robots::RobotsSettings;
+use web_crawler_core::scope::ScopeMode;
+use web_crawler_core::sitemap::SitemapSettings;
+use web_crawler_core::warc::{SnapshotOutput, WarcSettings};
+use web_crawler_core::settings::{UrlPattern, UrlRule, UrlRuleAction, UrlRuleTarget, UrlVisitorSettings};
 // use web_crawler_core::{settings::{FileSystemPaths, UrlVisitorSettings}, CrawlerSettings, WebCrawler};
 
 pub mod cli;
@@ -21,39 +45,538 @@ async fn main() {
 //     evaluate(manifest_path, project_id).await
 // }
 
-pub async fn evaluate(file_path: impl AsRef<Path>, project_id: &str) {
+pub async fn evaluate(file_path: impl AsRef<Path>, project_id: &str, mode: CrawlMode) {
+    let project = load_project(file_path, project_id);
+    let mut crawler_settings = crawler_settings_from_spec(&project);
+    crawler_settings.mode = mode;
+    run(crawler_settings).await;
+}
+
+/// The project `project_id` of the manifest at `file_path`. Exits with the
+/// reason when the manifest cannot be read, is invalid or has no such
+/// project.
+pub fn load_project(file_path: impl AsRef<Path>, project_id: &str) -> ProjectSpec {
     let file_path = file_path.as_ref();
-    let manifest = web_automation_manifest::ManifestContext::load(&file_path).unwrap();
-    let project = manifest.get_project(project_id).unwrap();
+    let manifest = web_automation_manifest::ManifestContext::load(file_path).unwrap_or_else(|error| {
+        eprintln!("{}", format!("❌ Invalid manifest {}: {error}", file_path.display()).red());
+        std::process::exit(1)
+    });
+    match manifest.get_project(project_id) {
+        Some(project) => project.clone(),
+        None => {
+            eprintln!("{}", format!("❌ No project {project_id:?} in {}", file_path.display()).red());
+            std::process::exit(1)
+        }
+    }
+}
+
+/// The logs of the project at `project_directory`. Exits with the reason when
+/// they cannot be read.
+pub fn load_project_log(project_directory: &Path) -> ProjectLog {
+    ProjectLog::load(project_directory).unwrap_or_else(|error| {
+        eprintln!("{}", format!("❌ Failed to open the project {}: {error}", project_directory.display()).red());
+        std::process::exit(1)
+    })
+}
+
+/// Settings for a project of a loaded manifest; loading has already
+/// [validated](ProjectSpec::validate) every value used here.
+pub fn crawler_settings_from_spec(project: &ProjectSpec) -> CrawlerSettings {
     let seed_urls = project.seed_urls
         .iter()
-        .map(|x| Url::from_str(x).unwrap())
+        .map(|x| Url::from_str(x).expect("seed URLs are validated by the manifest"))
         .collect::<Vec<_>>();
-    run(seed_urls, project_id, project.namespace.as_ref()).await;
-}
-
-pub async fn run(seed_urls: impl IntoIterator<Item=Url>, id: &str, namespace: Option<&String>) {
-    let seed_urls = seed_urls.into_iter().collect::<Vec<_>>();
-    let namespace = namespace.map(|x| x.as_str());
-    let project_directory = PathBuf::from(".output").join(namespace.unwrap_or(id));
+    let namespace = project.namespace.as_deref();
+    let project_directory = match project.output_directory.clone() {
+        Some(output_directory) => output_directory,
+        None => project.output_root
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(".output"))
+            .join(namespace.unwrap_or(&project.id)),
+    };
     // let manifest_path = snapshot_directory.join("manifest.toml");
-    let url_visitor_settings = UrlVisitorSettings::from_seed_urls_with_defaults(&seed_urls);
-    let crawler_settings = CrawlerSettings {
+    let url_normalizer = {
+        let spec = project.canonicalization.clone().unwrap_or_default();
+        let mut url_normalizer = match spec.standard_rules.unwrap_or(true) {
+            true => UrlNormalizer::standard(),
+            false => UrlNormalizer::fragment_only(),
+        };
+        for rule in spec.rules.iter() {
+            url_normalizer = url_normalizer.with_rule(match rule {
+                NormalizationRuleSpec::StripFragment => NormalizationRule::StripFragment,
+                NormalizationRuleSpec::LowercaseHost => NormalizationRule::LowercaseHost,
+                NormalizationRuleSpec::RemoveDefaultPort => NormalizationRule::RemoveDefaultPort,
+                NormalizationRuleSpec::StripWww => NormalizationRule::StripWww,
+                NormalizationRuleSpec::StripTrailingSlash => NormalizationRule::StripTrailingSlash,
+                NormalizationRuleSpec::StripIndexFile => {
+                    NormalizationRule::StripIndexFile(NormalizationRule::default_index_files())
+                }
+                NormalizationRuleSpec::DropTrackingParams => {
+                    NormalizationRule::DropQueryParams(NormalizationRule::default_tracking_params())
+                }
+                NormalizationRuleSpec::SortQuery => NormalizationRule::SortQuery,
+                NormalizationRuleSpec::RemoveEmptyQuery => NormalizationRule::RemoveEmptyQuery,
+            });
+        }
+        if !spec.drop_query_params.is_empty() {
+            url_normalizer = url_normalizer.with_rule(NormalizationRule::DropQueryParams(spec.drop_query_params));
+        }
+        url_normalizer
+    };
+    // Scope checks run against canonical URLs, so derive the whitelist from
+    // the canonical seeds (e.g. without `www.` when that rule is enabled).
+    let canonical_seed_urls = seed_urls
+        .iter()
+        .map(|x| url_normalizer.normalize(x))
+        .collect::<Vec<_>>();
+    let mut url_visitor_settings = UrlVisitorSettings::from_seed_urls_with_defaults(&canonical_seed_urls)
+        .with_scope(match project.scope {
+            None | Some(ScopeModeSpec::ExactHost) => ScopeMode::ExactHost,
+            Some(ScopeModeSpec::IncludeSubdomains) => ScopeMode::IncludeSubdomains,
+            Some(ScopeModeSpec::RegistrableDomain) => ScopeMode::RegistrableDomain,
+        });
+    for domain in project.allowed_domains.iter() {
+        url_visitor_settings = url_visitor_settings.with_whitelisted_domain(domain);
+    }
+    for protocol in project.blocked_protocols.iter() {
+        url_visitor_settings = url_visitor_settings.with_blacklisted_protocol(protocol.to_ascii_lowercase());
+    }
+    for rule in project.url_rules.iter() {
+        let pattern = match (rule.glob.as_deref(), rule.regex.as_deref()) {
+            (Some(glob), _) => UrlPattern::glob(glob),
+            (None, regex) => UrlPattern::regex(regex.unwrap_or_default()),
+        };
+        let pattern = pattern.expect("URL rule patterns are validated by the manifest");
+        url_visitor_settings = url_visitor_settings.with_url_rule(UrlRule {
+            action: match rule.action {
+                UrlRuleActionSpec::Include => UrlRuleAction::Include,
+                UrlRuleActionSpec::Exclude => UrlRuleAction::Exclude,
+            },
+            target: match rule.target.unwrap_or(UrlRuleTargetSpec::Path) {
+                UrlRuleTargetSpec::Path => UrlRuleTarget::Path,
+                UrlRuleTargetSpec::Query => UrlRuleTarget::Query,
+                UrlRuleTargetSpec::PathAndQuery => UrlRuleTarget::PathAndQuery,
+            },
+            pattern,
+        });
+    }
+    let politeness = {
+        let defaults = PolitenessSettings::default();
+        let spec = project.politeness.clone().unwrap_or_default();
+        PolitenessSettings {
+            min_delay: spec.min_delay_ms
+                .map(Duration::from_millis)
+                .unwrap_or(defaults.min_delay),
+            max_in_flight_per_host: spec.max_in_flight_per_host
+                .unwrap_or(defaults.max_in_flight_per_host),
+            max_backoff: spec.max_backoff_ms
+                .map(Duration::from_millis)
+                .unwrap_or(defaults.max_backoff),
+        }
+    };
+    let robots = {
+        let defaults = RobotsSettings::default();
+        let spec = project.robots.clone().unwrap_or_default();
+        RobotsSettings {
+            enabled: spec.enabled.unwrap_or(defaults.enabled),
+            user_agent: spec.user_agent.unwrap_or(defaults.user_agent),
+            unreachable_retry: defaults.unreachable_retry,
+        }
+    };
+    let sitemaps = {
+        let defaults = SitemapSettings::default();
+        let spec = project.sitemaps.clone().unwrap_or_default();
+        SitemapSettings {
+            enabled: spec.enabled.unwrap_or(defaults.enabled),
+            max_sitemaps: spec.max_sitemaps.unwrap_or(defaults.max_sitemaps),
+        }
+    };
+    let budget = {
+        let spec = project.budget.clone().unwrap_or_default();
+        CrawlBudget {
+            max_depth: spec.max_depth,
+            max_pages: spec.max_pages,
+            max_pages_per_host: spec.max_pages_per_host,
+            max_duration: spec.max_duration_secs.map(Duration::from_secs),
+        }
+    };
+    let recrawl = {
+        let spec = project.recrawl.clone().unwrap_or_default();
+        RecrawlSettings {
+            max_age: spec.max_age_secs.map(Duration::from_secs),
+        }
+    };
+    let retry = {
+        let defaults = RetryPolicy::default();
+        let spec = project.retry.clone().unwrap_or_default();
+        RetryPolicy {
+            max_attempts: spec.max_attempts.unwrap_or(defaults.max_attempts),
+            initial_backoff: spec.initial_backoff_secs
+                .map(Duration::from_secs)
+                .unwrap_or(defaults.initial_backoff),
+            max_backoff: spec.max_backoff_secs
+                .map(Duration::from_secs)
+                .unwrap_or(defaults.max_backoff),
+            retryable: match spec.retryable {
+                None => defaults.retryable,
+                Some(retryable) => retryable
+                    .into_iter()
+                    .map(|class| match class {
+                        RetryableFailureSpec::ServerError => RetryableFailure::ServerError,
+                        RetryableFailureSpec::TooManyRequests => RetryableFailure::TooManyRequests,
+                        RetryableFailureSpec::RequestTimeout => RetryableFailure::RequestTimeout,
+                        RetryableFailureSpec::NoResponse => RetryableFailure::NoResponse,
+                        RetryableFailureSpec::Timeout => RetryableFailure::Timeout,
+                        RetryableFailureSpec::Browser => RetryableFailure::Browser,
+                    })
+                    .collect(),
+            },
+        }
+    };
+    let resources = {
+        let defaults = ResourceSettings::default();
+        let spec = project.resources.clone().unwrap_or_default();
+        ResourceSettings {
+            enabled: spec.enabled.unwrap_or(defaults.enabled),
+            allowed_mime_types: spec.allowed_mime_types.unwrap_or(defaults.allowed_mime_types),
+            max_size: spec.max_size_bytes.or(defaults.max_size),
+        }
+    };
+    let dedup = {
+        let defaults = DedupSettings::default();
+        let spec = project.dedup.clone().unwrap_or_default();
+        DedupSettings {
+            enabled: spec.enabled.unwrap_or(defaults.enabled),
+            max_distance: spec.max_distance.unwrap_or(defaults.max_distance),
+            expand_links: spec.expand_links.unwrap_or(defaults.expand_links),
+        }
+    };
+    let capture = {
+        let spec = project.capture.clone().unwrap_or_default();
+        let screenshot = spec.screenshot.unwrap_or_default().then(|| {
+            let defaults = ScreenshotOptions::default();
+            ScreenshotOptions {
+                format: match spec.screenshot_format {
+                    None => defaults.format,
+                    Some(ScreenshotFormatSpec::Png) => ScreenshotFormat::Png,
+                    Some(ScreenshotFormatSpec::Jpeg) => ScreenshotFormat::Jpeg,
+                },
+                quality: spec.jpeg_quality.or(defaults.quality),
+                full_page: spec.full_page.unwrap_or(defaults.full_page),
+            }
+        });
+        let pdf = spec.pdf.unwrap_or_default().then(|| {
+            let defaults = PdfOptions::default();
+            PdfOptions {
+                landscape: spec.pdf_landscape.unwrap_or(defaults.landscape),
+                ..defaults
+            }
+        });
+        let har = spec.har.unwrap_or_default().then(|| {
+            let defaults = HarOptions::default();
+            HarOptions {
+                bodies: match spec.har_bodies {
+                    None => defaults.bodies,
+                    Some(HarBodiesSpec::None) => HarBodies::None,
+                    Some(HarBodiesSpec::Text) => HarBodies::Text,
+                    Some(HarBodiesSpec::All) => HarBodies::All,
+                },
+                max_body_size: spec.har_max_body_size_bytes.or(defaults.max_body_size),
+            }
+        });
+        CaptureSettings { screenshot, pdf, har }
+    };
+    let warc = {
+        let defaults = WarcSettings::default();
+        let spec = project.warc.clone().unwrap_or_default();
+        WarcSettings {
+            output: match spec.output {
+                None => defaults.output,
+                Some(SnapshotOutputSpec::Directory) => SnapshotOutput::Directory,
+                Some(SnapshotOutputSpec::Warc) => SnapshotOutput::Warc,
+                Some(SnapshotOutputSpec::Both) => SnapshotOutput::Both,
+            },
+            max_file_size: spec.max_file_size_bytes.unwrap_or(defaults.max_file_size),
+            compress: spec.compress.unwrap_or(defaults.compress),
+        }
+    };
+    let frontier_order = {
+        let spec = project.frontier.clone().unwrap_or_default();
+        match spec.order {
+            None | Some(FrontierOrderSpec::BreadthFirst) => FrontierOrder::BreadthFirst,
+            Some(FrontierOrderSpec::DepthFirst) => FrontierOrder::DepthFirst,
+            Some(FrontierOrderSpec::BestFirst) => FrontierOrder::BestFirst(Arc::new(KeywordScorer {
+                path_keywords: spec.path_keywords.into_iter().collect(),
+                anchor_keywords: spec.anchor_keywords.into_iter().collect(),
+                depth_weight: spec.depth_weight.unwrap_or_default(),
+            })),
+        }
+    };
+    let browser = {
+        let spec = project.browser.clone().unwrap_or_default();
+        let client = WebClientSettings {
+            headless_mode: spec.headless.map(|headless| match headless {
+                HeadlessSpec::Off => HeadlessMode::False,
+                HeadlessSpec::Old => HeadlessMode::True,
+                HeadlessSpec::New => HeadlessMode::New,
+            }),
+            chrome_executable: spec.executable,
+            // The manifest only accepts both or neither.
+            window_size: spec.window_width.zip(spec.window_height),
+            user_data_dir: spec.user_data_dir,
+            no_sandbox: spec.no_sandbox.unwrap_or_default(),
+            request_timeout: spec.request_timeout_ms.map(Duration::from_millis),
+            args: spec.args,
+        };
+        let timeouts = {
+            let defaults = NavigationTimeouts::default();
+            let spec = project.timeouts.clone().unwrap_or_default();
+            NavigationTimeouts {
+                navigation: spec.navigation_ms
+                    .map(Duration::from_millis)
+                    .unwrap_or(defaults.navigation),
+                ready: spec.ready_ms
+                    .map(Duration::from_millis)
+                    .unwrap_or(defaults.ready),
+                document_response: spec.document_response_ms
+                    .map(Duration::from_millis)
+                    .unwrap_or(defaults.document_response),
+            }
+        };
+        let wait = {
+            let defaults = WaitSettings::default();
+            let spec = project.wait.clone().unwrap_or_default();
+            WaitSettings {
+                strategy: match spec.strategy {
+                    None => defaults.strategy,
+                    Some(WaitStrategySpec::Load) => WaitStrategy::Load,
+                    Some(WaitStrategySpec::DomReady) => WaitStrategy::DomReady,
+                    Some(WaitStrategySpec::NetworkIdle) => WaitStrategy::NetworkIdle,
+                    Some(WaitStrategySpec::FullySettled) => WaitStrategy::FullySettled,
+                },
+                timeout: spec.timeout_ms
+                    .map(Duration::from_millis)
+                    .unwrap_or(defaults.timeout),
+                quiet_window: spec.quiet_window_ms
+                    .map(Duration::from_millis)
+                    .unwrap_or(defaults.quiet_window),
+                network_idle_timeout: defaults.network_idle_timeout,
+                settle_delay: spec.settle_delay_ms
+                    .map(Duration::from_millis)
+                    .unwrap_or(defaults.settle_delay),
+            }
+        };
+        BrowserSettings { client, timeouts, wait }
+    };
+    CrawlerSettings {
         seed_urls: IndexSet::from_iter(seed_urls),
         url_visitor_settings,
         project_directory,
-    };
-    let mut web_crawler = WebCrawler::new(crawler_settings);
-    web_crawler.execute().await;
-    let (crawler_settings, project_log) = web_crawler.finalize();
-    // // - TODO -
-    let _ = crawler_settings;
-    let _ = project_log;
+        max_concurrent_tabs: project.max_concurrent_tabs
+            .unwrap_or(CrawlerSettings::DEFAULT_MAX_CONCURRENT_TABS),
+        politeness,
+        robots,
+        sitemaps,
+        budget,
+        url_normalizer,
+        recrawl,
+        resources,
+        dedup,
+        capture,
+        warc,
+        frontier_order,
+        retry,
+        browser,
+        mode: CrawlMode::Full,
+    }
+}
+
+pub async fn run(crawler_settings: CrawlerSettings) {
+    let mut web_crawler = WebCrawler::new(crawler_settings).unwrap_or_else(|error| {
+        eprintln!("{}", format!("❌ Failed to open the project: {error}").red());
+        std::process::exit(1)
+    });
+    if let Err(error) = web_crawler.execute().await {
+        eprintln!("{}", format!("❌ Crawl aborted: {error}").red());
+        std::process::exit(1)
+    }
+    let (crawler_settings, mut project_log) = web_crawler.finalize();
+    // - LINK GRAPH -
+    let updated = LinkGraph::build(
+        &project_log,
+        crawler_settings.seed_urls.iter(),
+        &crawler_settings.url_normalizer,
+    ).and_then(|link_graph| link_graph.apply_incoming_links(&mut project_log));
+    if let Err(error) = updated {
+        eprintln!("{}", format!("\t ⚠️ Failed to update incoming links: {error}").yellow());
+    }
 }
 
+/// Rebuild the link graph of a project, refresh `incoming_links` in its
+/// snapshot logs and export the graph.
+pub fn export_link_graph(
+    file_path: impl AsRef<Path>,
+    project_id: &str,
+    format: LinkGraphFormat,
+    output: Option<PathBuf>,
+) {
+    let project = load_project(file_path, project_id);
+    let crawler_settings = crawler_settings_from_spec(&project);
+    let project_directory = crawler_settings.project_directory.as_path();
+    let mut project_log = load_project_log(project_directory);
+    let link_graph = LinkGraph::build(
+        &project_log,
+        crawler_settings.seed_urls.iter(),
+        &crawler_settings.url_normalizer,
+    ).unwrap_or_else(|error| {
+        eprintln!("{}", format!("❌ Failed to build the link graph: {error}").red());
+        std::process::exit(1)
+    });
+    let updated = link_graph
+        .apply_incoming_links(&mut project_log)
+        .unwrap_or_else(|error| {
+            eprintln!("{}", format!("❌ Failed to update incoming links: {error}").red());
+            std::process::exit(1)
+        });
+    let output = output.unwrap_or_else(|| {
+        project_directory.join(format!("link-graph.{}", format.extension()))
+    });
+    if let Err(error) = std::fs::write(&output, link_graph.render(format)) {
+        eprintln!("{}", format!("❌ Failed to write {}: {error}", output.display()).red());
+        std::process::exit(1)
+    }
+    eprintln!(
+        "🕸  {} pages » {} links » {} orphans » {} snapshot logs updated » {}",
+        link_graph.nodes.len(),
+        link_graph.edges.len(),
+        link_graph.orphans().count(),
+        updated,
+        output.display(),
+    );
+}
+
+
 
 
+pub fn export_warc(
+    file_path: impl AsRef<Path>,
+    project_id: &str,
+    output: Option<PathBuf>,
+) {
+    let project = load_project(file_path, project_id);
+    let crawler_settings = crawler_settings_from_spec(&project);
+    let project_directory = crawler_settings.project_directory.as_path();
+    let project_log = load_project_log(project_directory);
+    let output = output.unwrap_or_else(|| project_directory.join(WarcSettings::DIRECTORY_NAME));
+    let export = web_crawler_core::warc::export_project(
+        project_directory,
+        &project_log,
+        &output,
+        crawler_settings.warc.clone(),
+        &crawler_settings.robots.user_agent,
+        &ConsoleObserver,
+    ).unwrap_or_else(|error| {
+        eprintln!("{}", format!("❌ WARC export failed: {error}").red());
+        std::process::exit(1)
+    });
+    eprintln!(
+        "🗄  {} pages » {} earlier versions » {} documents » {} files » {}",
+        export.pages,
+        export.versions,
+        export.resources,
+        export.files,
+        output.display(),
+    );
+}
 
+/// Fill the project database from the TOML logs in the project directory,
+/// unless that already happened.
+pub fn import_project_logs(
+    file_path: impl AsRef<Path>,
+    project_id: &str,
+) {
+    let project = load_project(file_path, project_id);
+    let crawler_settings = crawler_settings_from_spec(&project);
+    let project_directory = crawler_settings.project_directory.as_path();
+    let database = project_directory.join(ProjectStore::FILE_NAME);
+    let mut store = ProjectStore::open(project_directory).unwrap_or_else(|error| {
+        eprintln!("{}", format!("❌ Failed to open {}: {error}", database.display()).red());
+        std::process::exit(1)
+    });
+    // The store is the only record once imported; importing again would
+    // drop every log written since.
+    let imported = store.is_imported().unwrap_or_else(|error| {
+        eprintln!("{}", format!("❌ Failed to read {}: {error}", database.display()).red());
+        std::process::exit(1)
+    });
+    if imported {
+        eprintln!("{}", format!(
+            "\t ☞ {} is already imported",
+            database.display(),
+        ).blue());
+        return
+    }
+    let summary = store.import_toml_logs(project_directory).unwrap_or_else(|error| {
+        eprintln!("{}", format!("❌ Import into {} failed: {error}", database.display()).red());
+        std::process::exit(1)
+    });
+    eprintln!(
+        "🗃  {} snapshot logs » {} task logs » {} unreadable » {}",
+        summary.snapshot_logs,
+        summary.task_logs,
+        summary.skipped,
+        database.display(),
+    );
+}
+
+/// Map a URL to its snapshot path or a snapshot path back to its URL.
+///
+/// Paths are looked up in the project's snapshot logs first, so hashed and
+/// disambiguated directories resolve too. Otherwise the path mapping is
+/// reversed directly.
+pub fn locate_snapshot(
+    file_path: impl AsRef<Path>,
+    project_id: &str,
+    target: &str,
+) {
+    let project = load_project(file_path, project_id);
+    let crawler_settings = crawler_settings_from_spec(&project);
+    let project_directory = crawler_settings.project_directory.as_path();
+    let project_log = load_project_log(project_directory);
+    // - URL → PATH -
+    if let Some(url) = Url::parse(target).ok().filter(Url::has_host) {
+        let canonical_url = crawler_settings.url_normalizer.canonicalize(&url);
+        let snapshot_log = project_log
+            .snapshot_log_for(&url)
+            .or_else(|| project_log.snapshot_log_for(&canonical_url.0));
+        match snapshot_log {
+            Some(log) => println!("{}", log.snapshot_path.0.display()),
+            None => {
+                let path = path_utils::build_rel_html_snapshot_file_path(canonical_url.0.as_str());
+                match path {
+                    Some(path) => println!("{} (not crawled)", path.0.display()),
+                    None => eprintln!("{}", format!("❌ No snapshot path for {target}").red()),
+                }
+            }
+        }
+        return
+    }
+    // - PATH → URL -
+    let path = Path::new(target);
+    let path = path.strip_prefix(project_directory).unwrap_or(path);
+    let file_path = match path.ends_with(path_utils::SNAPSHOT_FILE_NAME) {
+        true => path.to_path_buf(),
+        false => path.join(path_utils::SNAPSHOT_FILE_NAME),
+    };
+    let url = project_log
+        .snapshot_log_at(&RelativeFilePath(file_path))
+        .map(|log| log.original_url.0)
+        .or_else(|| path_utils::url_from_rel_snapshot_dir(path));
+    match url {
+        Some(url) => println!("{url}"),
+        None => eprintln!("{}", format!("❌ No URL maps to {}", path.display()).red()),
+    }
+}
 
 // pub async fn run(seed_urls: impl IntoIterator<Item=Url>, id: &str) {
 //     let seed_urls = seed_urls.into_iter().collect::<Vec<_>>();
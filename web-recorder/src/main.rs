@@ -2,7 +2,6 @@
 pub mod system;
 pub mod cli;
 pub mod manifest;
-pub mod path_utils;
 pub mod data;
 
 #[tokio::main]
@@ -160,7 +160,7 @@ async fn process_complete_response(
                 body.body.clone()
             };
             if is_json_mime_type {
-                let directory_tree = crate::path_utils::build_rel_html_snapshot_dir(url).unwrap();
+                let directory_tree = web_crawler_core::path_utils::build_rel_html_snapshot_dir(url).unwrap();
                 let output_dir = settings.output_directory.join(directory_tree);
                 let mut index = 0usize;
                 let timestamp = settings.session_timestamp.0.as_str();